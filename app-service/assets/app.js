@@ -6,11 +6,19 @@ logoutLink.addEventListener("click", (e) => {
     e.preventDefault();
 
     let url = logoutLink.href;
+    let authServiceUrl = new URL(url).origin;
 
-    fetch(url, {
+    // Logout is authenticated by cookie, so the auth service wants its CSRF token
+    // back in a header. Our origin can't read its cookies, so ask for it.
+    fetch(`${authServiceUrl}/csrf-token`, {
+        credentials: 'include',
+    }).then(response => response.json()).then(data => fetch(url, {
         method: 'POST',
         credentials: 'include', // This will include cookies in the request
-    }).then(response => {
+        headers: {
+            'X-CSRF-Token': data.csrfToken,
+        },
+    })).then(response => {
         if (response.ok) {
             loginLink.style.display = "block";
             logoutLink.style.display = "none";
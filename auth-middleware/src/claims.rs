@@ -0,0 +1,9 @@
+use serde::{Deserialize, Serialize};
+
+// Claims of a token issued by the auth service
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Claims {
+    // Email of the user
+    pub sub: String,
+    pub exp: usize,
+}
@@ -0,0 +1,37 @@
+use axum::{Json, http::StatusCode, response::{IntoResponse, Response}};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+// Why a request was turned away. Every app using the crate answers with the
+// same status codes and body.
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum AuthRejection {
+    #[error("Missing token")]
+    MissingToken,
+    #[error("Invalid token")]
+    InvalidToken,
+    // The auth service couldn't be reached
+    #[error("Token verification unavailable")]
+    Unavailable,
+}
+
+impl IntoResponse for AuthRejection {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            AuthRejection::MissingToken => (StatusCode::UNAUTHORIZED, "Missing token"),
+            AuthRejection::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid token"),
+            AuthRejection::Unavailable => (StatusCode::SERVICE_UNAVAILABLE, "Authentication is temporarily unavailable"),
+        };
+
+        let body = Json(ErrorResponse {
+            error: message.to_owned(),
+        });
+
+        (status, body).into_response()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub error: String,
+}
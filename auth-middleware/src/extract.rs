@@ -0,0 +1,79 @@
+use axum::{extract::FromRequestParts, http::request::Parts};
+
+use crate::claims::Claims;
+use crate::error::AuthRejection;
+use crate::verifier::AuthVerifier;
+
+// The authenticated user of a request. Handlers behind an `AuthLayer` get the
+// user the layer verified; elsewhere the token is verified with an
+// `AuthVerifier` added to the router with `Extension`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthUser {
+    pub claims: Claims,
+}
+
+impl AuthUser {
+    pub fn email(&self) -> &str {
+        &self.claims.sub
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for AuthUser {
+    type Rejection = AuthRejection;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(user) = parts.extensions.get::<AuthUser>() {
+            return Ok(user.clone());
+        }
+
+        let Some(verifier) = parts.extensions.get::<AuthVerifier>() else {
+            tracing::error!("AuthUser used without an AuthLayer or AuthVerifier extension");
+            return Err(AuthRejection::Unavailable);
+        };
+
+        verifier.authenticate(&parts.headers).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Extension, Router, body::Body, http::{Request, StatusCode, header::COOKIE}, routing::get};
+    use jsonwebtoken::{EncodingKey, Header, encode, get_current_timestamp};
+    use tower::ServiceExt;
+
+    async fn email(user: AuthUser) -> String {
+        user.email().to_owned()
+    }
+
+    fn app() -> Router {
+        Router::new()
+            .route("/", get(email))
+            .layer(Extension(AuthVerifier::shared_secret(b"secret")))
+    }
+
+    fn cookie() -> String {
+        let claims = serde_json::json!({
+            "sub": "user@example.com",
+            "exp": get_current_timestamp() + 600,
+        });
+        let token = encode(&Header::default(), &claims, &EncodingKey::from_secret(b"secret")).unwrap();
+        format!("jwt={}", token)
+    }
+
+    async fn status(cookie: Option<String>) -> StatusCode {
+        let mut request = Request::builder().uri("/");
+        if let Some(cookie) = cookie {
+            request = request.header(COOKIE, cookie);
+        }
+
+        app().oneshot(request.body(Body::empty()).unwrap()).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn verifies_token_with_verifier_extension() {
+        assert_eq!(status(None).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(Some("jwt=invalid".to_owned())).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(Some(cookie())).await, StatusCode::OK);
+    }
+}
@@ -0,0 +1,113 @@
+use std::convert::Infallible;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use axum::{extract::Request, response::{IntoResponse, Response}};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::verifier::AuthVerifier;
+
+// Rejects requests without a valid token before they reach the routes it
+// wraps. The verified `AuthUser` is handed to handlers.
+#[derive(Clone)]
+pub struct AuthLayer {
+    verifier: AuthVerifier,
+}
+
+impl AuthLayer {
+    pub fn new(verifier: AuthVerifier) -> Self {
+        Self { verifier }
+    }
+}
+
+impl<S> Layer<S> for AuthLayer {
+    type Service = AuthService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AuthService {
+            inner,
+            verifier: self.verifier.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct AuthService<S> {
+    inner: S,
+    verifier: AuthVerifier,
+}
+
+impl<S> Service<Request> for AuthService<S>
+where
+    S: Service<Request, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request) -> Self::Future {
+        // The clone isn't ready, so keep the service that is and leave the clone behind
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let verifier = self.verifier.clone();
+
+        Box::pin(async move {
+            let user = match verifier.authenticate(request.headers()).await {
+                Ok(user) => user,
+                Err(rejection) => return Ok(rejection.into_response()),
+            };
+
+            request.extensions_mut().insert(user);
+            inner.call(request).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, body::Body, http::{StatusCode, header::AUTHORIZATION}, routing::get};
+    use jsonwebtoken::{EncodingKey, Header, encode, get_current_timestamp};
+    use tower::ServiceExt;
+
+    use crate::extract::AuthUser;
+
+    fn token() -> String {
+        let claims = serde_json::json!({
+            "sub": "user@example.com",
+            "exp": get_current_timestamp() + 600,
+        });
+        encode(&Header::default(), &claims, &EncodingKey::from_secret(b"secret")).unwrap()
+    }
+
+    fn app(layer: AuthLayer) -> Router {
+        Router::new()
+            .route("/", get(|user: AuthUser| async move { user.email().to_owned() }))
+            .layer(layer)
+    }
+
+    async fn status(app: Router, token: Option<String>) -> StatusCode {
+        let mut request = Request::builder().uri("/");
+        if let Some(token) = token {
+            request = request.header(AUTHORIZATION, format!("Bearer {}", token));
+        }
+
+        app.oneshot(request.body(Body::empty()).unwrap()).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn rejects_requests_without_valid_token() {
+        let layer = AuthLayer::new(AuthVerifier::shared_secret(b"secret"));
+
+        assert_eq!(status(app(layer.clone()), None).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(app(layer.clone()), Some("invalid".to_owned())).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(app(layer), Some(token())).await, StatusCode::OK);
+    }
+}
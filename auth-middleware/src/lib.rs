@@ -0,0 +1,17 @@
+// Authentication for axum apps that sit behind the auth service.
+//
+// Build an `AuthVerifier`, then either put it in the router state and take an
+// `AuthUser` in handlers, or wrap routes in an `AuthLayer`. Missing or invalid
+// tokens get a 401.
+mod claims;
+mod error;
+mod extract;
+mod layer;
+mod remote;
+mod verifier;
+
+pub use claims::Claims;
+pub use error::{AuthRejection, ErrorResponse};
+pub use extract::AuthUser;
+pub use layer::{AuthLayer, AuthService};
+pub use verifier::AuthVerifier;
@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use jsonwebtoken::dangerous::insecure_decode;
+use reqwest::StatusCode;
+use tokio::sync::RwLock;
+
+use crate::claims::Claims;
+use crate::error::AuthRejection;
+
+// Expired entries are pruned once the cache holds this many. If they are all
+// live, new verdicts aren't cached until some expire.
+const MAX_CACHED_TOKENS: usize = 10_000;
+
+// Asks the auth service's `/verify-token` route whether a token is valid. The
+// answer is cached for a while, so a busy app doesn't call the auth service on
+// every request. A logged out token is accepted until its cache entry expires.
+pub(crate) struct RemoteVerifier {
+    url: String,
+    http_client: reqwest::Client,
+    cache_ttl: Duration,
+    cache: RwLock<HashMap<String, CachedVerdict>>,
+    capacity: usize,
+}
+
+#[derive(Clone)]
+struct CachedVerdict {
+    claims: Option<Claims>,
+    expires_at: Instant,
+}
+
+impl RemoteVerifier {
+    pub(crate) fn new(url: String, http_client: reqwest::Client, cache_ttl: Duration) -> Self {
+        Self {
+            url,
+            http_client,
+            cache_ttl,
+            cache: RwLock::new(HashMap::new()),
+            capacity: MAX_CACHED_TOKENS,
+        }
+    }
+
+    pub(crate) async fn verify(&self, token: &str) -> Result<Claims, AuthRejection> {
+        let cached = self.cache.read().await.get(token).cloned();
+        if let Some(verdict) = cached
+            && verdict.expires_at > Instant::now()
+        {
+            return verdict.claims.ok_or(AuthRejection::InvalidToken);
+        }
+
+        let claims = self.ask_auth_service(token).await?;
+        self.remember(token, claims.clone()).await;
+
+        claims.ok_or(AuthRejection::InvalidToken)
+    }
+
+    // `None` when the auth service rejected the token
+    async fn ask_auth_service(&self, token: &str) -> Result<Option<Claims>, AuthRejection> {
+        let response = self.http_client
+            .post(&self.url)
+            .json(&serde_json::json!({ "token": token }))
+            .send()
+            .await
+            .map_err(|e| {
+                tracing::error!(error = %e, "Failed to reach the auth service");
+                AuthRejection::Unavailable
+            })?;
+
+        match response.status() {
+            // The auth service answers with the token's claims, which is the only way to
+            // read opaque session IDs. Without them there is no user to trust.
+            StatusCode::OK => match response.json::<Claims>().await {
+                Ok(claims) => Ok(Some(claims)),
+                Err(e) => {
+                    tracing::error!(error = %e, "Auth service accepted the token without its claims");
+                    Err(AuthRejection::Unavailable)
+                }
+            },
+            StatusCode::BAD_REQUEST | StatusCode::UNAUTHORIZED | StatusCode::UNPROCESSABLE_ENTITY => Ok(None),
+            status => {
+                tracing::error!(%status, "Unexpected response from the auth service");
+                Err(AuthRejection::Unavailable)
+            }
+        }
+    }
+
+    async fn remember(&self, token: &str, claims: Option<Claims>) {
+        // A valid token must not outlive its expiry in the cache
+        let ttl = match &claims {
+            Some(claims) => self.cache_ttl.min(time_until(claims.exp)),
+            // Random strings are never sent twice, caching their rejection would
+            // only let them fill the cache
+            None if insecure_decode::<Claims>(token).is_err() => return,
+            None => self.cache_ttl,
+        };
+
+        let now = Instant::now();
+        let mut cache = self.cache.write().await;
+        if cache.len() >= self.capacity {
+            cache.retain(|_, verdict| verdict.expires_at > now);
+        }
+        if cache.len() >= self.capacity && !cache.contains_key(token) {
+            tracing::warn!(capacity = self.capacity, "Token cache is full, not caching the verdict");
+            return;
+        }
+        cache.insert(token.to_owned(), CachedVerdict { claims, expires_at: now + ttl });
+    }
+}
+
+fn time_until(exp: usize) -> Duration {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+
+    Duration::from_secs(exp as u64).saturating_sub(now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{EncodingKey, Header, encode, get_current_timestamp};
+    use wiremock::{Mock, MockServer, ResponseTemplate, matchers::{method, path}};
+
+    fn token() -> String {
+        token_for("user@example.com")
+    }
+
+    fn token_for(sub: &str) -> String {
+        let claims = serde_json::json!({
+            "sub": sub,
+            "exp": get_current_timestamp() + 600,
+        });
+        encode(&Header::default(), &claims, &EncodingKey::from_secret(b"secret")).unwrap()
+    }
+
+    async fn verifier_answering(status: u16, expected_calls: u64) -> (MockServer, RemoteVerifier) {
+        verifier_responding(ResponseTemplate::new(status), expected_calls).await
+    }
+
+    async fn verifier_responding(response: ResponseTemplate, expected_calls: u64) -> (MockServer, RemoteVerifier) {
+        let server = MockServer::start().await;
+        Mock::given(path("/verify-token"))
+            .and(method("POST"))
+            .respond_with(response)
+            .expect(expected_calls)
+            .mount(&server)
+            .await;
+
+        let verifier = RemoteVerifier::new(
+            format!("{}/verify-token", server.uri()),
+            reqwest::Client::new(),
+            Duration::from_secs(60),
+        );
+
+        (server, verifier)
+    }
+
+    fn claims_body() -> serde_json::Value {
+        serde_json::json!({
+            "sub": "user@example.com",
+            "exp": get_current_timestamp() + 600,
+        })
+    }
+
+    #[tokio::test]
+    async fn caches_valid_tokens() {
+        let (_server, verifier) = verifier_responding(ResponseTemplate::new(200).set_body_json(claims_body()), 1).await;
+        let token = token();
+
+        let claims = verifier.verify(&token).await.unwrap();
+        assert_eq!(claims.sub, "user@example.com");
+
+        assert_eq!(verifier.verify(&token).await, Ok(claims));
+    }
+
+    #[tokio::test]
+    async fn reads_claims_from_the_response_body() {
+        let exp = get_current_timestamp() + 600;
+        let body = serde_json::json!({ "sub": "user@example.com", "exp": exp });
+        let (_server, verifier) = verifier_responding(ResponseTemplate::new(200).set_body_json(body), 1).await;
+
+        // Session IDs aren't JWTs, only the auth service knows who they belong to
+        let claims = verifier.verify("opaque-session-id").await.unwrap();
+        assert_eq!(claims.sub, "user@example.com");
+        assert_eq!(claims.exp, exp as usize);
+    }
+
+    #[tokio::test]
+    async fn caches_rejected_tokens() {
+        let (_server, verifier) = verifier_answering(401, 1).await;
+        let token = token();
+
+        assert_eq!(verifier.verify(&token).await, Err(AuthRejection::InvalidToken));
+        assert_eq!(verifier.verify(&token).await, Err(AuthRejection::InvalidToken));
+    }
+
+    #[tokio::test]
+    async fn does_not_cache_auth_service_failures() {
+        let (_server, verifier) = verifier_answering(500, 2).await;
+        let token = token();
+
+        assert_eq!(verifier.verify(&token).await, Err(AuthRejection::Unavailable));
+        assert_eq!(verifier.verify(&token).await, Err(AuthRejection::Unavailable));
+    }
+
+    #[tokio::test]
+    async fn rejects_accepted_token_without_claims() {
+        let (_server, verifier) = verifier_answering(200, 2).await;
+        let token = token();
+
+        assert_eq!(verifier.verify(&token).await, Err(AuthRejection::Unavailable));
+        assert_eq!(verifier.verify(&token).await, Err(AuthRejection::Unavailable));
+    }
+
+    #[tokio::test]
+    async fn does_not_cache_rejected_garbage() {
+        let (_server, verifier) = verifier_answering(401, 2).await;
+
+        assert_eq!(verifier.verify("not-a-token").await, Err(AuthRejection::InvalidToken));
+        assert_eq!(verifier.verify("not-a-token").await, Err(AuthRejection::InvalidToken));
+        assert!(verifier.cache.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn stops_caching_when_full_of_live_entries() {
+        let (_server, mut verifier) = verifier_answering(401, 3).await;
+        verifier.capacity = 1;
+        let (first, second) = (token(), token_for("other@example.com"));
+
+        assert_eq!(verifier.verify(&first).await, Err(AuthRejection::InvalidToken));
+        assert_eq!(verifier.verify(&second).await, Err(AuthRejection::InvalidToken));
+        assert_eq!(verifier.verify(&second).await, Err(AuthRejection::InvalidToken));
+
+        let cache = verifier.cache.read().await;
+        assert_eq!(cache.len(), 1);
+        assert!(cache.contains_key(&first));
+    }
+}
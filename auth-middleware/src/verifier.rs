@@ -0,0 +1,197 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::{HeaderMap, header::AUTHORIZATION};
+use axum_extra::extract::CookieJar;
+use jsonwebtoken::{DecodingKey, Validation, decode};
+
+use crate::claims::Claims;
+use crate::error::AuthRejection;
+use crate::extract::AuthUser;
+use crate::remote::RemoteVerifier;
+
+// Cookies the auth service may keep its token in, depending on its settings
+const DEFAULT_COOKIE_NAMES: [&str; 2] = ["__Host-jwt", "jwt"];
+
+// Checks the tokens of incoming requests. Cheap to clone, so it can live in
+// the router state.
+#[derive(Clone)]
+pub struct AuthVerifier {
+    method: Arc<Method>,
+    cookie_names: Arc<[String]>,
+}
+
+enum Method {
+    SharedSecret(DecodingKey),
+    Remote(RemoteVerifier),
+}
+
+impl AuthVerifier {
+    // Verifies tokens locally with the secret the auth service signs them with.
+    // Only the signature and expiry are checked: logouts, revoked sessions and
+    // banned tokens go unnoticed until the token expires, and session IDs of an
+    // auth service in session mode are always rejected.
+    pub fn shared_secret(secret: &[u8]) -> Self {
+        Self::with_method(Method::SharedSecret(DecodingKey::from_secret(secret)))
+    }
+
+    // Asks the auth service at `verify_token_url` about each token and caches
+    // the answer for `cache_ttl`. Logouts take up to that long to be noticed.
+    pub fn remote(verify_token_url: impl Into<String>, cache_ttl: Duration) -> Self {
+        Self::with_method(Method::Remote(RemoteVerifier::new(
+            verify_token_url.into(),
+            reqwest::Client::new(),
+            cache_ttl,
+        )))
+    }
+
+    // Cookies to look for the token in when there is no Bearer header
+    pub fn with_cookie_names<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.cookie_names = names.into_iter().map(Into::into).collect();
+        self
+    }
+
+    fn with_method(method: Method) -> Self {
+        Self {
+            method: Arc::new(method),
+            cookie_names: DEFAULT_COOKIE_NAMES.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+
+    pub async fn verify(&self, token: &str) -> Result<Claims, AuthRejection> {
+        match self.method.as_ref() {
+            Method::SharedSecret(key) => decode::<Claims>(token, key, &Validation::default())
+                .map(|data| data.claims)
+                .map_err(|_| AuthRejection::InvalidToken),
+            Method::Remote(verifier) => verifier.verify(token).await,
+        }
+    }
+
+    // The user behind a request, from a Bearer header or the auth cookie
+    pub async fn authenticate(&self, headers: &HeaderMap) -> Result<AuthUser, AuthRejection> {
+        let token = self.token(headers).ok_or(AuthRejection::MissingToken)?;
+        let claims = self.verify(&token).await?;
+
+        Ok(AuthUser { claims })
+    }
+
+    fn token(&self, headers: &HeaderMap) -> Option<String> {
+        if let Some(token) = bearer_token(headers) {
+            return Some(token.to_owned());
+        }
+
+        let jar = CookieJar::from_headers(headers);
+        self.cookie_names
+            .iter()
+            .find_map(|name| jar.get(name))
+            .map(|cookie| cookie.value().to_owned())
+    }
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    let value = headers.get(AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+    let token = token.trim();
+
+    (scheme.eq_ignore_ascii_case("bearer") && !token.is_empty()).then_some(token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::header::COOKIE;
+    use jsonwebtoken::{EncodingKey, Header, encode, get_current_timestamp};
+
+    const SECRET: &[u8] = b"secret";
+
+    fn token(secret: &[u8], exp: u64) -> String {
+        let claims = serde_json::json!({ "sub": "user@example.com", "exp": exp });
+        encode(&Header::default(), &claims, &EncodingKey::from_secret(secret)).unwrap()
+    }
+
+    fn valid_token() -> String {
+        token(SECRET, get_current_timestamp() + 600)
+    }
+
+    fn headers(name: axum::http::HeaderName, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, value.parse().unwrap());
+        headers
+    }
+
+    #[tokio::test]
+    async fn accepts_token_signed_with_shared_secret() {
+        let verifier = AuthVerifier::shared_secret(SECRET);
+
+        let claims = verifier.verify(&valid_token()).await.unwrap();
+
+        assert_eq!(claims.sub, "user@example.com");
+    }
+
+    #[tokio::test]
+    async fn rejects_token_signed_with_other_secret() {
+        let verifier = AuthVerifier::shared_secret(SECRET);
+        let token = token(b"other secret", get_current_timestamp() + 600);
+
+        assert_eq!(verifier.verify(&token).await, Err(AuthRejection::InvalidToken));
+    }
+
+    #[tokio::test]
+    async fn rejects_expired_token() {
+        let verifier = AuthVerifier::shared_secret(SECRET);
+        let token = token(SECRET, get_current_timestamp() - 600);
+
+        assert_eq!(verifier.verify(&token).await, Err(AuthRejection::InvalidToken));
+    }
+
+    #[tokio::test]
+    async fn reads_token_from_bearer_header_or_cookie() {
+        let verifier = AuthVerifier::shared_secret(SECRET);
+        let token = valid_token();
+
+        let bearer = headers(AUTHORIZATION, &format!("Bearer {}", token));
+        assert!(verifier.authenticate(&bearer).await.is_ok());
+
+        let cookie = headers(COOKIE, &format!("__Host-jwt={}", token));
+        assert!(verifier.authenticate(&cookie).await.is_ok());
+
+        let cookie = headers(COOKIE, &format!("jwt={}", token));
+        assert!(verifier.authenticate(&cookie).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn prefers_bearer_header_over_cookie() {
+        let verifier = AuthVerifier::shared_secret(SECRET);
+
+        let mut headers = headers(AUTHORIZATION, "Bearer invalid");
+        headers.insert(COOKIE, format!("jwt={}", valid_token()).parse().unwrap());
+
+        assert_eq!(verifier.authenticate(&headers).await, Err(AuthRejection::InvalidToken));
+    }
+
+    #[tokio::test]
+    async fn uses_configured_cookie_names() {
+        let verifier = AuthVerifier::shared_secret(SECRET).with_cookie_names(["session"]);
+
+        let cookie = headers(COOKIE, &format!("jwt={}", valid_token()));
+        assert_eq!(verifier.authenticate(&cookie).await, Err(AuthRejection::MissingToken));
+
+        let cookie = headers(COOKIE, &format!("session={}", valid_token()));
+        assert!(verifier.authenticate(&cookie).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn rejects_request_without_token() {
+        let verifier = AuthVerifier::shared_secret(SECRET);
+
+        assert_eq!(verifier.authenticate(&HeaderMap::new()).await, Err(AuthRejection::MissingToken));
+        assert_eq!(
+            verifier.authenticate(&headers(AUTHORIZATION, "Basic dXNlcjpwYXNz")).await,
+            Err(AuthRejection::MissingToken)
+        );
+    }
+}
@@ -6,6 +6,13 @@ const signupLink = document.getElementById("signup-link");
 const twoFALoginLink = document.getElementById("2fa-login-link");
 const signupLoginLink = document.getElementById("signup-login-link");
 
+// The server sets a CSRF token cookie on the first visit. Requests that
+// are authenticated by the JWT cookie must send it back in a header.
+function csrfToken() {
+    const match = document.cookie.match(/(?:^|;\s*)csrf_token=([^;]*)/);
+    return match ? decodeURIComponent(match[1]) : "";
+}
+
 signupLink.addEventListener("click", (e) => {
     e.preventDefault();
 
@@ -46,6 +53,7 @@ loginButton.addEventListener("click", (e) => {
         method: 'POST',
         headers: {
             'Content-Type': 'application/json',
+            'X-CSRF-Token': csrfToken(),
         },
         body: JSON.stringify({ email, password }),
     }).then(response => {
@@ -96,6 +104,7 @@ signupButton.addEventListener("click", (e) => {
         method: 'POST',
         headers: {
             'Content-Type': 'application/json',
+            'X-CSRF-Token': csrfToken(),
         },
         body: JSON.stringify({ email, password, requires2FA }),
     }).then(response => {
@@ -137,6 +146,7 @@ TwoFAButton.addEventListener("click", (e) => {
         method: 'POST',
         headers: {
             'Content-Type': 'application/json',
+            'X-CSRF-Token': csrfToken(),
         },
         body: JSON.stringify({ email, loginAttemptId, "2FACode": TwoFACode }),
     }).then(response => {
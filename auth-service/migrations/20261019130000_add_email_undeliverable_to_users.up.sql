@@ -0,0 +1 @@
+ALTER TABLE users ADD COLUMN IF NOT EXISTS email_undeliverable BOOLEAN NOT NULL DEFAULT FALSE;
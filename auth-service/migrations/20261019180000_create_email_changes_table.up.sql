@@ -0,0 +1,12 @@
+-- Confirmed email changes, kept while they can still be reverted
+CREATE TABLE IF NOT EXISTS email_changes (
+    id TEXT PRIMARY KEY,
+    from_email TEXT NOT NULL,
+    to_email TEXT NOT NULL,
+    confirmed_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    -- Set once the change is reverted or the account moves on to another address
+    ended_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS email_changes_to_email_idx ON email_changes (to_email) WHERE ended_at IS NULL;
+CREATE INDEX IF NOT EXISTS email_changes_confirmed_at_idx ON email_changes (confirmed_at);
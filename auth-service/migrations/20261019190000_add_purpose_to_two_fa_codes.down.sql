@@ -0,0 +1,7 @@
+DELETE FROM two_fa_codes WHERE purpose <> 'login';
+
+ALTER TABLE two_fa_codes DROP CONSTRAINT IF EXISTS two_fa_codes_pkey;
+ALTER TABLE two_fa_codes ADD PRIMARY KEY (email);
+
+ALTER TABLE two_fa_codes DROP COLUMN IF EXISTS failed_attempts;
+ALTER TABLE two_fa_codes DROP COLUMN IF EXISTS purpose;
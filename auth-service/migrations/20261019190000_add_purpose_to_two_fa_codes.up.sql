@@ -0,0 +1,6 @@
+-- Codes sent for different purposes are kept apart, each with its own count of wrong guesses
+ALTER TABLE two_fa_codes ADD COLUMN IF NOT EXISTS purpose TEXT NOT NULL DEFAULT 'login';
+ALTER TABLE two_fa_codes ADD COLUMN IF NOT EXISTS failed_attempts INTEGER NOT NULL DEFAULT 0;
+
+ALTER TABLE two_fa_codes DROP CONSTRAINT IF EXISTS two_fa_codes_pkey;
+ALTER TABLE two_fa_codes ADD PRIMARY KEY (email, purpose);
@@ -0,0 +1,4 @@
+ALTER TABLE email_outbox ADD COLUMN IF NOT EXISTS idempotency_key TEXT;
+UPDATE email_outbox SET idempotency_key = id::text WHERE idempotency_key IS NULL;
+ALTER TABLE email_outbox ALTER COLUMN idempotency_key SET NOT NULL;
+ALTER TABLE email_outbox ADD CONSTRAINT email_outbox_idempotency_key_key UNIQUE (idempotency_key);
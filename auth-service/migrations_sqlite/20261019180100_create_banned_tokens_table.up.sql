@@ -0,0 +1,7 @@
+-- expires_at is in seconds since the Unix epoch
+CREATE TABLE IF NOT EXISTS banned_tokens (
+    token_hash TEXT PRIMARY KEY,
+    expires_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS banned_tokens_expires_at_idx ON banned_tokens (expires_at);
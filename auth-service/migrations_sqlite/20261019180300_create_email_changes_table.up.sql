@@ -0,0 +1,11 @@
+-- confirmed_at and ended_at are in seconds since the Unix epoch
+CREATE TABLE IF NOT EXISTS email_changes (
+    id TEXT PRIMARY KEY,
+    from_email TEXT NOT NULL,
+    to_email TEXT NOT NULL,
+    confirmed_at INTEGER NOT NULL,
+    ended_at INTEGER
+);
+
+CREATE INDEX IF NOT EXISTS email_changes_to_email_idx ON email_changes (to_email) WHERE ended_at IS NULL;
+CREATE INDEX IF NOT EXISTS email_changes_confirmed_at_idx ON email_changes (confirmed_at);
@@ -0,0 +1,15 @@
+-- SQLite can't change a primary key, and codes only live for minutes, so the
+-- table is recreated. expires_at is in seconds since the Unix epoch.
+DROP TABLE IF EXISTS two_fa_codes;
+
+CREATE TABLE two_fa_codes (
+    email TEXT NOT NULL,
+    purpose TEXT NOT NULL,
+    login_attempt_id TEXT NOT NULL,
+    code TEXT NOT NULL,
+    failed_attempts INTEGER NOT NULL DEFAULT 0,
+    expires_at INTEGER NOT NULL,
+    PRIMARY KEY (email, purpose)
+);
+
+CREATE INDEX IF NOT EXISTS two_fa_codes_expires_at_idx ON two_fa_codes (expires_at);
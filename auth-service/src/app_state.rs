@@ -1,13 +1,18 @@
 use std::sync::Arc;
-use tokio::sync::RwLock;
 
-use crate::domain::{data_stores::UserStore, data_stores::BannedTokenStore, data_stores::TwoFACodeStore, EmailClient};
+use crate::domain::{data_stores::UserStore, data_stores::BannedTokenStore, data_stores::TwoFACodeStore, data_stores::RateLimitStore, data_stores::EmailOutboxStore, data_stores::AccountLockoutStore, data_stores::SessionStore, EmailClient};
+use crate::services::geoip::GeoIp;
+use crate::utils::config::Config;
 
 // Using a type alias to improve readability!
-pub type UserStoreType = Arc<RwLock<dyn UserStore>>;
-pub type BannedTokenStoreType = Arc<RwLock<dyn BannedTokenStore>>;
-pub type TwoFACodeStoreType = Arc<RwLock<dyn TwoFACodeStore>>;
-pub type EmailClientType = Arc<RwLock<dyn EmailClient>>;
+pub type UserStoreType = Arc<dyn UserStore>;
+pub type BannedTokenStoreType = Arc<dyn BannedTokenStore>;
+pub type TwoFACodeStoreType = Arc<dyn TwoFACodeStore>;
+pub type EmailClientType = Arc<dyn EmailClient>;
+pub type RateLimitStoreType = Arc<dyn RateLimitStore>;
+pub type EmailOutboxStoreType = Arc<dyn EmailOutboxStore>;
+pub type AccountLockoutStoreType = Arc<dyn AccountLockoutStore>;
+pub type SessionStoreType = Arc<dyn SessionStore>;
 
 #[derive(Clone, Debug)]
 pub struct AppState {
@@ -15,20 +20,36 @@ pub struct AppState {
     pub banned_token_store: BannedTokenStoreType,
     pub two_fa_code_store: TwoFACodeStoreType,
     pub email_client: EmailClientType,
+    pub rate_limit_store: RateLimitStoreType,
+    pub account_lockout_store: AccountLockoutStoreType,
+    pub session_store: SessionStoreType,
+    pub geoip: Arc<GeoIp>,
+    pub config: Arc<Config>,
 }
 
 impl AppState {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         user_store: UserStoreType, 
         banned_token_store: BannedTokenStoreType, 
         two_fa_code_store: TwoFACodeStoreType,
         email_client: EmailClientType,
+        rate_limit_store: RateLimitStoreType,
+        account_lockout_store: AccountLockoutStoreType,
+        session_store: SessionStoreType,
+        geoip: GeoIp,
+        config: Config,
     ) -> Self {
         Self {
             user_store,
             banned_token_store,
             two_fa_code_store,
-            email_client
+            email_client,
+            rate_limit_store,
+            account_lockout_store,
+            session_store,
+            geoip: Arc::new(geoip),
+            config: Arc::new(config),
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+// When failed password logins lock an account. Every `threshold` consecutive
+// failures lock it, and each lockout lasts twice as long as the previous one,
+// up to `max_duration`. Counters are forgotten after `reset_after` without a failure.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LockoutPolicy {
+    pub threshold: u32,
+    pub base_duration: Duration,
+    pub max_duration: Duration,
+    pub reset_after: Duration,
+}
+
+impl LockoutPolicy {
+    pub fn new(threshold: u32, base_duration: Duration, max_duration: Duration, reset_after: Duration) -> Self {
+        Self {
+            threshold,
+            base_duration,
+            max_duration,
+            reset_after,
+        }
+    }
+
+    // How long the account is locked the `lockouts`-th time, starting at 1
+    pub fn lockout_duration(&self, lockouts: u32) -> Duration {
+        let doublings = lockouts.saturating_sub(1).min(31);
+        self.base_duration
+            .saturating_mul(2u32.pow(doublings))
+            .min(self.max_duration)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LockoutStatus {
+    Unlocked,
+    Locked { retry_after: Duration },
+}
+
+// What happened after a failed password was recorded
+#[derive(Debug, Clone, PartialEq)]
+pub enum FailedLoginOutcome {
+    Counted { failed_attempts: u32 },
+    // This failure locked the account
+    Locked { lock_duration: Duration },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lockout_duration_doubles_up_to_max() {
+        let policy = LockoutPolicy::new(5, Duration::from_secs(60), Duration::from_secs(300), Duration::from_secs(3600));
+
+        assert_eq!(policy.lockout_duration(1), Duration::from_secs(60));
+        assert_eq!(policy.lockout_duration(2), Duration::from_secs(120));
+        assert_eq!(policy.lockout_duration(3), Duration::from_secs(240));
+        assert_eq!(policy.lockout_duration(4), Duration::from_secs(300));
+        assert_eq!(policy.lockout_duration(100), Duration::from_secs(300));
+    }
+}
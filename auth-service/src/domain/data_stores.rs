@@ -1,7 +1,10 @@
-use super::{User, Email, LoginAttemptId, TwoFACode};
+use super::{User, Email, EmailChangeRevert, LoginAttemptId, TwoFACode, TwoFACodePurpose, RateLimitPolicy, RateLimitDecision, OutboxEmail, LockoutPolicy, LockoutStatus, FailedLoginOutcome, Session, SessionId};
+use chrono::{DateTime, Utc};
 use thiserror::Error;
 use color_eyre::eyre::Report;
 use secrecy::SecretString;
+use std::time::Duration;
+use uuid::Uuid;
 
 // User Store
 #[derive(Debug, Error)]
@@ -18,11 +21,20 @@ pub enum UserStoreError {
 
 #[async_trait::async_trait]
 pub trait UserStore: Send + Sync {
-    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError>;
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError>;
 
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError>;
     
     async fn validate_user(&self, email: &Email, raw_password: &str) -> Result<(), UserStoreError>;
+
+    // Moves the account to `new_email` and records the change under `change_id`
+    async fn confirm_email_change(&self, change_id: &str, current_email: &Email, new_email: Email) -> Result<(), UserStoreError>;
+
+    // Moves the account back to the address it had before the change. Only a
+    // change that gave the account the address it still holds is reverted.
+    async fn revert_email_change(&self, change_id: &str) -> Result<EmailChangeRevert, UserStoreError>;
+
+    async fn mark_email_undeliverable(&self, email: &Email) -> Result<(), UserStoreError>;
 }
 
 impl PartialEq for UserStoreError {
@@ -54,7 +66,8 @@ pub enum BannedTokenStoreError {
 
 #[async_trait::async_trait]
 pub trait BannedTokenStore: Send + Sync {
-    async fn add_token(&mut self, token: &SecretString) -> Result<(), BannedTokenStoreError>;
+    // Keeps the token banned for `ttl`, which should last until the token would have expired anyway
+    async fn add_token(&self, token: &SecretString, ttl: Duration) -> Result<(), BannedTokenStoreError>;
 
     async fn check_token(&self, token: &SecretString) -> Result<bool, BannedTokenStoreError>;
 }
@@ -75,6 +88,47 @@ impl std::fmt::Debug for dyn BannedTokenStore {
     }
 }
 
+// Session Store
+#[derive(Debug, Error)]
+pub enum SessionStoreError {
+    #[error("Session not found")]
+    SessionNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+#[async_trait::async_trait]
+pub trait SessionStore: Send + Sync {
+    // Keeps the session for `ttl`, after which it is gone
+    async fn add_session(&self, session: Session, ttl: Duration) -> Result<(), SessionStoreError>;
+
+    async fn get_session(&self, id: &SessionId) -> Result<Session, SessionStoreError>;
+
+    // Records activity on the session and keeps it for another `ttl`
+    async fn touch_session(&self, id: &SessionId, last_seen: DateTime<Utc>, ttl: Duration) -> Result<(), SessionStoreError>;
+
+    async fn remove_session(&self, id: &SessionId) -> Result<(), SessionStoreError>;
+
+    // Every session of the user that hasn't ended yet
+    async fn list_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError>;
+}
+
+impl PartialEq for SessionStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::SessionNotFound, Self::SessionNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+impl std::fmt::Debug for dyn SessionStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SessionStore")
+    }
+}
+
 // 2FA Store
 #[derive(Debug, Error)]
 pub enum TwoFACodeStoreError {
@@ -87,15 +141,20 @@ pub enum TwoFACodeStoreError {
 #[async_trait::async_trait]
 pub trait TwoFACodeStore: Send + Sync {
     async fn add_code(
-        &mut self,
+        &self,
         email: Email,
+        purpose: TwoFACodePurpose,
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
     ) -> Result<(), TwoFACodeStoreError>;
 
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError>;
+    async fn remove_code(&self, email: &Email, purpose: TwoFACodePurpose) -> Result<(), TwoFACodeStoreError>;
 
-    async fn get_code(&self, email: &Email) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError>;
+    async fn get_code(&self, email: &Email, purpose: TwoFACodePurpose) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError>;
+
+    // Counts a wrong guess against the current code and returns how many
+    // there have been. A new code starts again from zero.
+    async fn record_failed_attempt(&self, email: &Email, purpose: TwoFACodePurpose) -> Result<u32, TwoFACodeStoreError>;
 }
 
 impl PartialEq for TwoFACodeStoreError {
@@ -112,4 +171,108 @@ impl std::fmt::Debug for dyn TwoFACodeStore {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "TwoFACodeStore")
     }
-}
\ No newline at end of file
+}
+
+// Rate Limit Store
+#[derive(Debug, Error)]
+pub enum RateLimitStoreError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+#[async_trait::async_trait]
+pub trait RateLimitStore: Send + Sync {
+    // Takes one token from the bucket identified by `key`
+    async fn check(&self, key: &str, policy: &RateLimitPolicy) -> Result<RateLimitDecision, RateLimitStoreError>;
+}
+
+impl PartialEq for RateLimitStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+impl std::fmt::Debug for dyn RateLimitStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "RateLimitStore")
+    }
+}
+
+// Email Outbox Store
+#[derive(Debug, Error)]
+pub enum EmailOutboxStoreError {
+    #[error("Email not found")]
+    EmailNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+#[async_trait::async_trait]
+pub trait EmailOutboxStore: Send + Sync {
+    async fn enqueue(&self, email: OutboxEmail) -> Result<(), EmailOutboxStoreError>;
+
+    // Takes the pending email that has been due the longest and counts an attempt.
+    // It is hidden from other workers for `lease`, after which it is due again.
+    async fn claim_next(&self, lease: Duration) -> Result<Option<OutboxEmail>, EmailOutboxStoreError>;
+
+    // The bodies are cleared, they may hold login codes and links
+    async fn mark_sent(&self, id: Uuid) -> Result<(), EmailOutboxStoreError>;
+
+    async fn schedule_retry(&self, id: Uuid, error: &str, retry_in: Duration) -> Result<(), EmailOutboxStoreError>;
+
+    // Gives up on an email. It stays in the store for inspection, without its bodies,
+    // but is never sent.
+    async fn dead_letter(&self, id: Uuid, error: &str) -> Result<(), EmailOutboxStoreError>;
+}
+
+impl PartialEq for EmailOutboxStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::EmailNotFound, Self::EmailNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+impl std::fmt::Debug for dyn EmailOutboxStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "EmailOutboxStore")
+    }
+}
+
+// Account Lockout Store
+#[derive(Debug, Error)]
+pub enum AccountLockoutStoreError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+#[async_trait::async_trait]
+pub trait AccountLockoutStore: Send + Sync {
+    async fn status(&self, email: &Email) -> Result<LockoutStatus, AccountLockoutStoreError>;
+
+    // Counts a failed password and locks the account once the policy threshold is reached
+    async fn record_failure(&self, email: &Email, policy: &LockoutPolicy) -> Result<FailedLoginOutcome, AccountLockoutStoreError>;
+
+    // Forgets failures and lockouts, after a successful login or an admin unlock
+    async fn reset(&self, email: &Email) -> Result<(), AccountLockoutStoreError>;
+}
+
+impl PartialEq for AccountLockoutStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+impl std::fmt::Debug for dyn AccountLockoutStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "AccountLockoutStore")
+    }
+}
@@ -6,7 +6,8 @@ pub trait EmailClient: Send + Sync {
     async fn send_email(&self, 
         recipient: Email, 
         subject: &str, 
-        content: &str
+        html_content: &str,
+        text_content: &str,
     ) -> Result<()>;
 }
 
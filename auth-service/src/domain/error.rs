@@ -1,7 +1,8 @@
-use axum::{Json, http::status::StatusCode, response::{IntoResponse, Response}};
+use axum::{Json, http::{header::RETRY_AFTER, status::StatusCode}, response::{IntoResponse, Response}};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use color_eyre::eyre::Report;
+use std::time::Duration;
 
 #[derive(Debug, Error)]
 pub enum AuthAPIError {
@@ -19,6 +20,16 @@ pub enum AuthAPIError {
     InvalidLoginAttempId,
     #[error("Invalid 2FA Code")]
     InvalidTwoFACode,
+    #[error("Too many requests")]
+    TooManyRequests { retry_after: Duration },
+    #[error("Email undeliverable")]
+    EmailUndeliverable,
+    #[error("Account locked")]
+    AccountLocked { retry_after: Duration },
+    #[error("Invalid CSRF token")]
+    InvalidCsrfToken,
+    #[error("Session not found")]
+    SessionNotFound,
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
@@ -27,6 +38,13 @@ impl IntoResponse for AuthAPIError {
     fn into_response(self) -> Response {
         log_error_chain(&self);
 
+        // Tell clients when they can try again
+        let retry_after = match &self {
+            AuthAPIError::TooManyRequests { retry_after }
+                | AuthAPIError::AccountLocked { retry_after } => Some(retry_after.as_secs_f64().ceil().max(1.0) as u64),
+            _ => None,
+        };
+
         let (status, message) = match self {
             AuthAPIError::UserAlreadyExists => (StatusCode::CONFLICT, "User already exists"),
             AuthAPIError::InvalidCredentials => (StatusCode::BAD_REQUEST, "Invalid credentials"),
@@ -35,6 +53,11 @@ impl IntoResponse for AuthAPIError {
             AuthAPIError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid token"),
             AuthAPIError::InvalidLoginAttempId => (StatusCode::BAD_REQUEST, "Invalid login attempt id"),
             AuthAPIError::InvalidTwoFACode => (StatusCode::BAD_REQUEST, "Invalid 2FA code"),
+            AuthAPIError::TooManyRequests { .. } => (StatusCode::TOO_MANY_REQUESTS, "Too many requests"),
+            AuthAPIError::EmailUndeliverable => (StatusCode::UNPROCESSABLE_ENTITY, "Emails to this address are bouncing, contact support"),
+            AuthAPIError::AccountLocked { .. } => (StatusCode::LOCKED, "Account is temporarily locked, try again later"),
+            AuthAPIError::InvalidCsrfToken => (StatusCode::FORBIDDEN, "Missing or invalid CSRF token"),
+            AuthAPIError::SessionNotFound => (StatusCode::NOT_FOUND, "Session not found"),
             AuthAPIError::UnexpectedError(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Unexpetected error"),
         };
 
@@ -42,7 +65,10 @@ impl IntoResponse for AuthAPIError {
             error: message.to_string(),
         });
 
-        (status, body).into_response()
+        match retry_after {
+            Some(secs) => (status, [(RETRY_AFTER, secs.to_string())], body).into_response(),
+            None => (status, body).into_response(),
+        }
     }
 }
 
@@ -57,6 +83,11 @@ impl PartialEq for AuthAPIError {
                 | (Self::InvalidToken, Self::InvalidToken)
                 | (Self::InvalidLoginAttempId, Self::InvalidLoginAttempId)
                 | (Self::InvalidTwoFACode, Self::InvalidTwoFACode)
+                | (Self::TooManyRequests { .. }, Self::TooManyRequests { .. })
+                | (Self::EmailUndeliverable, Self::EmailUndeliverable)
+                | (Self::AccountLocked { .. }, Self::AccountLocked { .. })
+                | (Self::InvalidCsrfToken, Self::InvalidCsrfToken)
+                | (Self::SessionNotFound, Self::SessionNotFound)
                 | (Self::UnexpectedError(_), Self::UnexpectedError(_))
         )
     }
@@ -6,6 +6,10 @@ mod password;
 mod login_attempt_id;
 mod two_fa_code;
 mod email_client;
+mod rate_limit;
+mod outbox_email;
+mod account_lockout;
+mod session;
 
 pub use user::*;
 pub use error::*;
@@ -13,4 +17,8 @@ pub use email::*;
 pub use password::*;
 pub use login_attempt_id::*;
 pub use two_fa_code::*;
-pub use email_client::*;
\ No newline at end of file
+pub use email_client::*;
+pub use rate_limit::*;
+pub use outbox_email::*;
+pub use account_lockout::*;
+pub use session::*;
\ No newline at end of file
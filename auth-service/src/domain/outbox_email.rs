@@ -0,0 +1,46 @@
+use uuid::Uuid;
+
+use super::Email;
+
+// An email waiting in the outbox to be delivered by the background worker
+#[derive(Debug, Clone)]
+pub struct OutboxEmail {
+    pub id: Uuid,
+    pub recipient: Email,
+    pub subject: String,
+    pub html_body: String,
+    pub text_body: String,
+    // Delivery attempts so far, including the one in progress
+    pub attempts: u32,
+}
+
+impl OutboxEmail {
+    pub fn new(recipient: Email, subject: &str, html_body: &str, text_body: &str) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            recipient,
+            subject: subject.to_owned(),
+            html_body: html_body.to_owned(),
+            text_body: text_body.to_owned(),
+            attempts: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::SecretString;
+
+    fn email(address: &str) -> Email {
+        Email::parse(SecretString::new(address.to_owned().into_boxed_str())).unwrap()
+    }
+
+    #[test]
+    fn test_same_content_gets_its_own_id() {
+        let first = OutboxEmail::new(email("a@example.com"), "Subject", "<p>Body</p>", "Body");
+        let second = OutboxEmail::new(email("a@example.com"), "Subject", "<p>Body</p>", "Body");
+
+        assert_ne!(first.id, second.id);
+    }
+}
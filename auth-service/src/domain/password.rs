@@ -5,6 +5,11 @@ use argon2::{
 use super::AuthAPIError;
 use color_eyre::eyre::{Context, Result};
 use secrecy::{ExposeSecret, SecretString};
+use tokio::sync::OnceCell;
+use uuid::Uuid;
+
+// Hash of a random password, computed once with the same parameters as real ones
+static DUMMY_PASSWORD_HASH: OnceCell<HashedPassword> = OnceCell::const_new();
 
 #[derive(Debug, Clone)]
 pub struct HashedPassword(SecretString);
@@ -64,6 +69,23 @@ impl HashedPassword {
         })
         .await?
     }
+
+    // Spends as long as checking a wrong password, for requests about users that
+    // don't exist. Otherwise response times reveal which emails are registered.
+    #[tracing::instrument(name = "Verify dummy password", skip_all)]
+    pub async fn verify_dummy_password(password_candidate: &SecretString) -> Result<()> {
+        let dummy = DUMMY_PASSWORD_HASH
+            .get_or_try_init(|| async {
+                let password = SecretString::new(Uuid::new_v4().to_string().into_boxed_str());
+                compute_password_hash(&password).await.map(Self)
+            })
+            .await?;
+
+        // Never matches, only the time spent matters
+        let _ = dummy.verify_raw_password(password_candidate).await;
+
+        Ok(())
+    }
 }
 
 #[tracing::instrument(name = "Computing password hash", skip_all)]
@@ -105,6 +127,14 @@ mod tests {
         assert!(password.verify_raw_password(&raw_password_wrong).await.is_err());
     }
 
+    #[tokio::test]
+    async fn test_verify_dummy_password() {
+        let raw_password = SecretString::new("RustOrBust456!".to_owned().into_boxed_str());
+
+        assert!(HashedPassword::verify_dummy_password(&raw_password).await.is_ok());
+        assert!(DUMMY_PASSWORD_HASH.get().is_some());
+    }
+
     #[tokio::test]
     async fn test_asref_impl() {
         let raw_password = SecretString::new( "RustOrBust456!".to_owned().into_boxed_str());
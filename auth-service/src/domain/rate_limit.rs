@@ -0,0 +1,24 @@
+use std::time::Duration;
+
+// Token bucket settings. The bucket holds at most `capacity` tokens and
+// gets one token back every `refill_interval`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimitPolicy {
+    pub capacity: u32,
+    pub refill_interval: Duration,
+}
+
+impl RateLimitPolicy {
+    pub fn new(capacity: u32, refill_interval: Duration) -> Self {
+        Self {
+            capacity,
+            refill_interval,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RateLimitDecision {
+    Allowed,
+    Limited { retry_after: Duration },
+}
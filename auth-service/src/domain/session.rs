@@ -0,0 +1,168 @@
+use std::net::IpAddr;
+use std::time::Duration;
+
+use chrono::{DateTime, SubsecRound, Utc};
+use color_eyre::eyre::{Result, eyre};
+use secrecy::{ExposeSecret, SecretString};
+use sha2::{Digest, Sha256};
+
+use super::Email;
+
+// Opaque ID kept in the auth cookie when sessions are stored server-side
+#[derive(Debug, Clone)]
+pub struct SessionId(SecretString);
+
+impl SessionId {
+    pub fn parse(id: String) -> Result<Self> {
+        if id.len() != 64 || !id.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(eyre!("Invalid session ID"));
+        }
+
+        Ok(SessionId(SecretString::new(id.into_boxed_str())))
+    }
+
+    // Names the session in listings without revealing the ID, which is as good as a password
+    pub fn public_id(&self) -> String {
+        let digest = Sha256::digest(self.0.expose_secret().as_bytes());
+        hex::encode(&digest[..16])
+    }
+}
+
+impl Default for SessionId {
+    fn default() -> Self {
+        let bytes: [u8; 32] = rand::random();
+        SessionId(SecretString::new(hex::encode(bytes).into_boxed_str()))
+    }
+}
+
+impl AsRef<str> for SessionId {
+    fn as_ref(&self) -> &str {
+        self.0.expose_secret()
+    }
+}
+
+impl PartialEq for SessionId {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    pub id: SessionId,
+    pub email: Email,
+    pub created_at: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    pub ip: Option<IpAddr>,
+    pub user_agent: Option<String>,
+}
+
+impl Session {
+    pub fn new(email: Email, ip: Option<IpAddr>, user_agent: Option<String>) -> Self {
+        // Milliseconds are all every store keeps, so the session reads back unchanged
+        let now = Utc::now().trunc_subsecs(3);
+
+        Self {
+            id: SessionId::default(),
+            email,
+            created_at: now,
+            last_seen: now,
+            ip,
+            user_agent,
+        }
+    }
+
+    // Whichever timeout comes first ends the session
+    pub fn expires_at(&self, policy: &SessionPolicy) -> DateTime<Utc> {
+        let idle_expiry = self.last_seen + policy.idle_timeout;
+        let absolute_expiry = self.created_at + policy.absolute_timeout;
+
+        idle_expiry.min(absolute_expiry)
+    }
+
+    pub fn is_expired(&self, policy: &SessionPolicy, now: DateTime<Utc>) -> bool {
+        self.expires_at(policy) <= now
+    }
+
+    // How long the store has to keep the session, at least a second
+    pub fn time_to_live(&self, policy: &SessionPolicy, now: DateTime<Utc>) -> Duration {
+        (self.expires_at(policy) - now)
+            .to_std()
+            .unwrap_or_default()
+            .max(Duration::from_secs(1))
+    }
+}
+
+// Sessions end after `idle_timeout` without a request, and `absolute_timeout`
+// after login no matter how active they are
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionPolicy {
+    pub idle_timeout: chrono::Duration,
+    pub absolute_timeout: chrono::Duration,
+}
+
+impl SessionPolicy {
+    pub fn new(idle_timeout: Duration, absolute_timeout: Duration) -> Self {
+        Self {
+            idle_timeout: chrono::Duration::from_std(idle_timeout).unwrap_or(chrono::Duration::MAX),
+            absolute_timeout: chrono::Duration::from_std(absolute_timeout).unwrap_or(chrono::Duration::MAX),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session() -> Session {
+        let email = Email::parse(SecretString::new("test@example.com".to_owned().into_boxed_str())).unwrap();
+        Session::new(email, None, None)
+    }
+
+    #[test]
+    fn test_session_ids_are_random_and_parse() {
+        let id = SessionId::default();
+
+        assert_ne!(id, SessionId::default());
+        assert_eq!(SessionId::parse(id.as_ref().to_owned()).unwrap(), id);
+        assert!(SessionId::parse("not-a-session-id".to_owned()).is_err());
+    }
+
+    #[test]
+    fn test_public_id_is_stable_and_differs_from_id() {
+        let id = SessionId::default();
+
+        assert_eq!(id.public_id(), id.public_id());
+        assert_eq!(id.public_id().len(), 32);
+        assert!(!id.as_ref().starts_with(&id.public_id()));
+        assert_ne!(id.public_id(), SessionId::default().public_id());
+    }
+
+    #[test]
+    fn test_session_expires_after_idle_timeout() {
+        let policy = SessionPolicy::new(Duration::from_secs(60), Duration::from_secs(3600));
+        let session = session();
+
+        assert!(!session.is_expired(&policy, session.created_at + chrono::Duration::seconds(59)));
+        assert!(session.is_expired(&policy, session.created_at + chrono::Duration::seconds(60)));
+    }
+
+    #[test]
+    fn test_session_expires_after_absolute_timeout_despite_activity() {
+        let policy = SessionPolicy::new(Duration::from_secs(60), Duration::from_secs(3600));
+        let mut session = session();
+        session.last_seen = session.created_at + chrono::Duration::seconds(3590);
+
+        assert_eq!(session.expires_at(&policy), session.created_at + chrono::Duration::seconds(3600));
+        assert!(session.is_expired(&policy, session.created_at + chrono::Duration::seconds(3600)));
+    }
+
+    #[test]
+    fn test_time_to_live() {
+        let policy = SessionPolicy::new(Duration::from_secs(60), Duration::from_secs(3600));
+        let session = session();
+
+        assert_eq!(session.time_to_live(&policy, session.created_at), Duration::from_secs(60));
+        assert_eq!(session.time_to_live(&policy, session.created_at + chrono::Duration::seconds(120)), Duration::from_secs(1));
+    }
+}
@@ -44,4 +44,22 @@ impl PartialEq for TwoFACode {
     fn eq(&self, other: &Self) -> bool {
         self.0.expose_secret() == other.0.expose_secret()
     }
-}
\ No newline at end of file
+}
+
+// What a code was sent for. Each purpose keeps its own code per email, so a
+// code can only be used on the route it was sent for and requesting one
+// never replaces the other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TwoFACodePurpose {
+    Login,
+    EmailOtp,
+}
+
+impl TwoFACodePurpose {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Login => "login",
+            Self::EmailOtp => "email_otp",
+        }
+    }
+}
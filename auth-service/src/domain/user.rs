@@ -5,6 +5,8 @@ pub struct User {
     pub email: Email,
     pub password: HashedPassword,
     pub requires_2fa: bool,
+    // Set when the email provider reports the address bounced or complained
+    pub email_undeliverable: bool,
 }
 
 impl User {
@@ -12,7 +14,18 @@ impl User {
         Self {
             email,
             password,
-            requires_2fa
+            requires_2fa,
+            email_undeliverable: false,
         }
     }
-}
\ No newline at end of file
+}
+// What became of a request to revert an email change
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EmailChangeRevert {
+    // The account is back at the address it had before the change
+    Reverted,
+    // The change was never confirmed, so the account never moved
+    NotConfirmed,
+    // The change was already reverted, or the account has moved on to another address
+    Ended,
+}
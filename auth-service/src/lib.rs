@@ -4,23 +4,31 @@ pub mod services;
 pub mod app_state;
 pub mod utils;
 
-use redis::{Client, RedisResult};
+use redis::RedisResult;
 use routes as api_routes;
+use services::redis_connection::RedisConnection;
 use app_state::AppState;
-use sqlx::{PgPool, postgres::PgPoolOptions};
+use sqlx::{PgPool, SqlitePool, postgres::PgPoolOptions, sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions}};
 use utils::constants::{DROPLET_IP};
+use utils::config::RedisConfig;
 use utils::tracing::{make_span_with_request_id, on_request, on_response};
+use utils::rate_limit::rate_limit_by_ip;
+use utils::csrf::{issue_csrf_token, require_csrf_token};
+use utils::constants::CSRF_HEADER_NAME;
 use secrecy::{ExposeSecret, SecretString};
 
 use std::error::Error;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::time::Duration;
 
-use axum::{Router, routing::post, serve::Serve, http::Method};
+use axum::{Router, routing::{delete, get, post}, serve::Serve, handler::HandlerWithoutStateExt, http::{Method, HeaderName, StatusCode, header::{AUTHORIZATION, CONTENT_TYPE}}, middleware::{self, AddExtension}, extract::{ConnectInfo, connect_info::IntoMakeServiceWithConnectInfo}};
 use tokio::net::TcpListener;
 use tower_http::{services::ServeDir, cors::CorsLayer, trace::TraceLayer};
 
 // This struct encapsulates our application-related logic.
 pub struct Application {
-    server: Serve<TcpListener, Router, Router>,
+    server: Serve<TcpListener, IntoMakeServiceWithConnectInfo<Router, SocketAddr>, AddExtension<Router, ConnectInfo<SocketAddr>>>,
     // address is exposed as a public field
     // so we have access to it in tests.
     pub address: String,
@@ -28,7 +36,11 @@ pub struct Application {
 
 impl Application {
     pub async fn build(app_state: AppState, address: &str) -> Result<Self, Box<dyn Error>> {
-        let assets_dir = ServeDir::new("assets");
+        // Unknown paths fall through to the assets, which would answer POSTs with 405.
+        // Routes that are disabled must look like they don't exist.
+        let assets_dir = ServeDir::new("assets")
+            .call_fallback_on_method_not_allowed(true)
+            .not_found_service(StatusCode::NOT_FOUND.into_service());
 
         let allowed_origins = [
             "http://localhost:8001".parse()?,
@@ -36,17 +48,62 @@ impl Application {
         ];
 
         let cors = CorsLayer::new()
-            .allow_methods([Method::GET, Method::POST])
+            .allow_methods([Method::GET, Method::POST, Method::DELETE])
+            .allow_headers([AUTHORIZATION, CONTENT_TYPE, HeaderName::from_static(CSRF_HEADER_NAME)])
             .allow_credentials(true)
             .allow_origin(allowed_origins);
 
-        let router = Router::new()
+        // Routes authenticated by the JWT cookie or a Bearer token that change
+        // state. Browsers attach the cookie to cross-site requests too, so
+        // cookie requests need a CSRF token.
+        let cookie_authenticated = Router::new()
+            .route("/logout", post(api_routes::logout))
+            .route("/change-email", post(api_routes::change_email))
+            .route("/sessions/{id}", delete(api_routes::revoke_session))
+            .route_layer(middleware::from_fn_with_state(app_state.clone(), require_csrf_token));
+
+        let mut router = Router::new()
             .fallback_service(assets_dir)
+            .merge(cookie_authenticated)
             .route("/signup", post(api_routes::signup))
             .route("/login", post(api_routes::login))
-            .route("/logout", post(api_routes::logout))
+            .route("/login/magic-link", post(api_routes::request_magic_link))
+            .route("/login/magic-link/callback", get(api_routes::magic_link_callback))
             .route("/verify-2fa", post(api_routes::verify_2fa))
             .route("/verify-token", post(api_routes::verify_token))
+            .route("/change-email/confirm", get(api_routes::confirm_email_change))
+            .route("/change-email/revert", get(api_routes::revert_email_change))
+            .route("/csrf-token", get(api_routes::csrf_token))
+            .route("/sessions", get(api_routes::list_sessions));
+
+        if app_state.config.email_otp_login.enabled {
+            router = router
+                .route("/login/email-otp", post(api_routes::request_email_otp))
+                .route("/login/email-otp/verify", post(api_routes::verify_email_otp));
+        }
+
+        if app_state.config.postmark_webhook.is_enabled() {
+            router = router.route("/webhooks/postmark", post(api_routes::postmark_webhook));
+        }
+
+        if app_state.config.admin.api_token.is_some() {
+            router = router
+                .route("/admin/unlock-account", post(api_routes::unlock_account))
+                .route("/metrics", get(api_routes::metrics));
+        }
+
+        if app_state.config.dev_mode {
+            router = router
+                .route("/dev/emails/{name}", get(api_routes::preview_email))
+                .route("/dev/mailbox", get(api_routes::mailbox));
+        }
+
+        if app_state.config.ip_rate_limit.enabled {
+            router = router.layer(middleware::from_fn_with_state(app_state.clone(), rate_limit_by_ip));
+        }
+
+        let router = router
+            .layer(middleware::from_fn(issue_csrf_token))
             .with_state(app_state)
             .layer(cors)
             .layer(
@@ -58,7 +115,8 @@ impl Application {
 
         let listener = tokio::net::TcpListener::bind(address).await?;
         let address = listener.local_addr()?.to_string();
-        let server = axum::serve(listener, router);
+        // The rate limiter needs the address of the peer
+        let server = axum::serve(listener, router.into_make_service_with_connect_info::<SocketAddr>());
         
         Ok(
             Self {
@@ -79,7 +137,18 @@ pub async fn get_postgres_pool(url: &SecretString) -> Result<PgPool, sqlx::Error
     PgPoolOptions::new().max_connections(5).connect(url.expose_secret()).await
 }
 
-pub fn get_redis_client(redis_hostname: String) -> RedisResult<Client> {
-    let redis_url = format!("redis://{}/", redis_hostname);
-    redis::Client::open(redis_url)
-}
\ No newline at end of file
+pub async fn get_sqlite_pool(url: &SecretString) -> Result<SqlitePool, sqlx::Error> {
+    // The database file is created on first start. WAL lets reads run alongside
+    // the single writer, which waits for the lock instead of failing.
+    let options = SqliteConnectOptions::from_str(url.expose_secret())?
+        .create_if_missing(true)
+        .journal_mode(SqliteJournalMode::Wal)
+        .busy_timeout(Duration::from_secs(5));
+
+    SqlitePoolOptions::new().max_connections(5).connect_with(options).await
+}
+
+// Connection shared by every Redis store, to a single server, Sentinel or Cluster
+pub async fn get_redis_connection(config: &RedisConfig) -> RedisResult<RedisConnection> {
+    RedisConnection::connect(config).await
+}
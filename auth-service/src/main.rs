@@ -1,38 +1,185 @@
 use auth_service::services::data_stores::{
     postgres_user_store::PostgresUserStore,
+    postgres_email_outbox_store::PostgresEmailOutboxStore,
     redis_banned_token_store::RedisBannedTokenStore,
     redis_two_fa_code_store::RedisTwoFACodeStore,
+    redis_rate_limit_store::RedisRateLimitStore,
+    redis_account_lockout_store::RedisAccountLockoutStore,
+    redis_session_store::RedisSessionStore,
+    postgres_account_lockout_store::PostgresAccountLockoutStore,
+    postgres_banned_token_store::PostgresBannedTokenStore,
+    postgres_two_fa_code_store::PostgresTwoFACodeStore,
+    postgres_session_store::PostgresSessionStore,
+    hashmap_account_lockout_store::HashmapAccountLockoutStore,
+    hashmap_rate_limit_store::HashmapRateLimitStore,
+    hashmap_session_store::HashmapSessionStore,
+    hashmap_two_fa_code_store::HashmapTwoFACodeStore,
+    hashset_banned_token_store::HashsetBannedTokenStore,
+    hashmap_email_outbox_store::HashmapEmailOutboxStore,
+    sqlite_user_store::SqliteUserStore,
+    sqlite_banned_token_store::SqliteBannedTokenStore,
+    sqlite_two_fa_code_store::SqliteTwoFACodeStore,
 };
 // use auth_service::services::mock_email_client::MockEmailClient;
-use auth_service::services::postmark_email_client::PostmarkEmailClient;
-use auth_service::app_state::AppState;
-use auth_service::{Application, get_postgres_pool, get_redis_client};
-use auth_service::utils::constants::{prod, DATABASE_URL, REDIS_HOST_NAME, POSTMARK_AUTH_TOKEN};
+use auth_service::services::failover_email_client::FailoverEmailClient;
+use auth_service::services::email_outbox::{EmailOutboxWorker, OutboxEmailClient};
+use auth_service::services::geoip::GeoIp;
+use auth_service::services::redis_connection::RedisConnection;
+use auth_service::services::postgres_purger::PostgresPurger;
+use auth_service::services::sqlite_purger::SqlitePurger;
+use auth_service::services::memory_purger::{ExpiringStore, MemoryPurger};
+use auth_service::app_state::{
+    AccountLockoutStoreType, AppState, BannedTokenStoreType, EmailClientType, EmailOutboxStoreType,
+    RateLimitStoreType, SessionStoreType, TwoFACodeStoreType, UserStoreType,
+};
+use auth_service::{Application, get_postgres_pool, get_redis_connection, get_sqlite_pool};
+use auth_service::utils::constants::{prod, DATABASE_URL};
+use auth_service::utils::config::{Config, RedisConfig, StoreKind};
 use auth_service::utils::tracing::init_tracing;
-use auth_service::domain::Email;
-use sqlx::PgPool;
+use auth_service::utils::metrics::init_metrics;
 use secrecy::SecretString;
+use sqlx::{PgPool, SqlitePool};
 
 use std::sync::Arc;
-use tokio::sync::RwLock;
-use reqwest::Client;
 
 
 #[tokio::main]
 async fn main() {
     color_eyre::install().expect("Failed to install color_eyre");
     init_tracing().expect("Failed to initialize tracing");
+    init_metrics().expect("Failed to initialize metrics");
+
+    let config = Config::from_env();
+
+    let pg_pool = match config.uses(StoreKind::Postgres) {
+        true => Some(configure_postgresql().await),
+        false => None,
+    };
+    let sqlite_pool = match config.uses(StoreKind::Sqlite) {
+        true => Some(configure_sqlite(&config.stores.sqlite_url).await),
+        false => None,
+    };
+    let redis_conn = match config.uses(StoreKind::Redis) {
+        true => Some(configure_redis(&config.redis).await),
+        false => None,
+    };
+    // Only called for stores kept in that backend, in which case it was connected to
+    let postgres = || pg_pool.clone().expect("PostgreSQL is not connected");
+    let sqlite = || sqlite_pool.clone().expect("SQLite is not connected");
+    let redis = || redis_conn.clone().expect("Redis is not connected");
+
+    let stores = &config.stores;
+    // In-memory stores with expiring entries, for the purge task
+    let mut memory_stores: Vec<Arc<dyn ExpiringStore>> = Vec::new();
+
+    let user_store: UserStoreType = match config.stores.users {
+        StoreKind::Postgres => Arc::new(PostgresUserStore::new(postgres())),
+        StoreKind::Sqlite => Arc::new(SqliteUserStore::new(sqlite())),
+        kind => unsupported_store("user", kind),
+    };
+    // In memory, emails queued before a restart are never sent
+    let email_outbox_store: EmailOutboxStoreType = match config.stores.email_outbox {
+        StoreKind::Postgres => Arc::new(PostgresEmailOutboxStore::new(postgres())),
+        StoreKind::Memory => {
+            let store = Arc::new(HashmapEmailOutboxStore::new(config.email_outbox.retention));
+            memory_stores.push(store.clone());
+            store
+        }
+        kind => unsupported_store("email outbox", kind),
+    };
+    let banned_token_store: BannedTokenStoreType = match config.stores.banned_tokens {
+        StoreKind::Redis => Arc::new(RedisBannedTokenStore::new(redis())),
+        StoreKind::Postgres => Arc::new(PostgresBannedTokenStore::new(postgres())),
+        StoreKind::Sqlite => Arc::new(SqliteBannedTokenStore::new(sqlite())),
+        StoreKind::Memory => {
+            let store = Arc::new(HashsetBannedTokenStore::new(stores.memory_capacity));
+            memory_stores.push(store.clone());
+            store
+        }
+    };
+    let two_fa_code_store: TwoFACodeStoreType = match config.stores.two_fa_codes {
+        StoreKind::Redis => Arc::new(RedisTwoFACodeStore::new(redis())),
+        StoreKind::Postgres => Arc::new(PostgresTwoFACodeStore::new(postgres())),
+        StoreKind::Sqlite => Arc::new(SqliteTwoFACodeStore::new(sqlite())),
+        StoreKind::Memory => {
+            let store = Arc::new(HashmapTwoFACodeStore::new(stores.memory_capacity));
+            memory_stores.push(store.clone());
+            store
+        }
+    };
+    let session_store: SessionStoreType = match config.stores.sessions {
+        StoreKind::Redis => Arc::new(RedisSessionStore::new(redis())),
+        StoreKind::Postgres => Arc::new(PostgresSessionStore::new(postgres())),
+        StoreKind::Memory => {
+            let store = Arc::new(HashmapSessionStore::new(stores.memory_capacity));
+            memory_stores.push(store.clone());
+            store
+        }
+        kind => unsupported_store("session", kind),
+    };
+    // In memory, every instance enforces its own limits
+    let rate_limit_store: RateLimitStoreType = match config.stores.rate_limits {
+        StoreKind::Redis => Arc::new(RedisRateLimitStore::new(redis())),
+        StoreKind::Memory => {
+            let store = Arc::new(HashmapRateLimitStore::new(stores.memory_capacity));
+            memory_stores.push(store.clone());
+            store
+        }
+        kind => unsupported_store("rate limit", kind),
+    };
+    let account_lockout_store: AccountLockoutStoreType = match config.account_lockout.store {
+        StoreKind::Redis => Arc::new(RedisAccountLockoutStore::new(redis())),
+        StoreKind::Postgres => Arc::new(PostgresAccountLockoutStore::new(postgres())),
+        StoreKind::Memory => {
+            let store = Arc::new(HashmapAccountLockoutStore::new(stores.memory_capacity));
+            memory_stores.push(store.clone());
+            store
+        }
+        kind => unsupported_store("account lockout", kind),
+    };
 
-    let pg_pool = configure_postgresql().await;
-    let redis_conn = Arc::new(RwLock::new(configure_redis()));
+    let expiring_stores = [stores.banned_tokens, stores.two_fa_codes, stores.sessions, stores.email_outbox];
+    if expiring_stores.contains(&StoreKind::Postgres) {
+        let outbox_retention = config.email_outbox.retention;
+        tokio::spawn(PostgresPurger::new(postgres(), stores.purge_interval, outbox_retention).run());
+    }
+    if expiring_stores.contains(&StoreKind::Sqlite) {
+        tokio::spawn(SqlitePurger::new(sqlite(), stores.purge_interval).run());
+    }
+    if !memory_stores.is_empty() {
+        tokio::spawn(MemoryPurger::new(memory_stores, stores.purge_interval).run());
+    }
 
-    let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
-    let banned_token_store = Arc::new(RwLock::new(RedisBannedTokenStore::new(redis_conn.clone())));
-    let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_conn)));
-    // let email_client = Arc::new(RwLock::new(MockEmailClient::default()));
-    let email_client = Arc::new(RwLock::new(configure_postmark_email_client()));
+    // let email_client = Arc::new(MockEmailClient::default());
+    let provider_email_client: EmailClientType = Arc::new(
+        FailoverEmailClient::from_config(&config.email_providers).expect("Failed to build email client"),
+    );
 
-    let app_state = AppState::new(user_store, banned_token_store, two_fa_code_store, email_client); 
+    // Routes only queue emails, the worker delivers them through the provider
+    let email_outbox_worker = EmailOutboxWorker::new(
+        email_outbox_store.clone(),
+        provider_email_client,
+        config.email_outbox.clone(),
+    );
+    tokio::spawn(email_outbox_worker.run());
+    let email_client = Arc::new(OutboxEmailClient::new(email_outbox_store));
+
+    let geoip = match &config.session.geoip_db_path {
+        Some(path) => GeoIp::open(path).expect("Failed to open GeoIP database"),
+        None => GeoIp::default(),
+    };
+
+    let app_state = AppState::new(
+        user_store,
+        banned_token_store,
+        two_fa_code_store,
+        email_client,
+        rate_limit_store,
+        account_lockout_store,
+        session_store,
+        geoip,
+        config,
+    );
     
     let app = Application::build(app_state, prod::APP_ADDRESS)
         .await.expect("Failed to build app");
@@ -55,23 +202,25 @@ async fn configure_postgresql() -> PgPool {
     pg_pool
 }
 
-fn configure_redis() -> redis::Connection {
-    get_redis_client(REDIS_HOST_NAME.to_owned())
-        .expect("Failed to get Redis client")
-        .get_connection()
-        .expect("Failed to get Redis connection")
+async fn configure_sqlite(url: &SecretString) -> SqlitePool {
+    let sqlite_pool = get_sqlite_pool(url)
+        .await
+        .expect("Failed to open SQLite database!");
+
+    sqlx::migrate!("./migrations_sqlite")
+        .run(&sqlite_pool)
+        .await
+        .expect("Failed to run SQLite migrations");
+
+    sqlite_pool
+}
+
+fn unsupported_store(store: &str, kind: StoreKind) -> ! {
+    panic!("The {store} store can't be kept in {kind}.")
 }
 
-fn configure_postmark_email_client() -> PostmarkEmailClient {
-    let http_client = Client::builder()
-        .timeout(prod::email_client::TIMEOUT)
-        .build()
-        .expect("Failed to build HTTP client");
-
-    PostmarkEmailClient::new(
-        prod::email_client::BASE_URL.to_owned(),
-        Email::parse(SecretString::new(prod::email_client::SENDER.to_owned().into_boxed_str())).unwrap(),
-        POSTMARK_AUTH_TOKEN.to_owned(),
-        http_client,
-    )
-}
\ No newline at end of file
+async fn configure_redis(config: &RedisConfig) -> RedisConnection {
+    get_redis_connection(config)
+        .await
+        .expect("Failed to get Redis connection")
+}
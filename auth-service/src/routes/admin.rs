@@ -0,0 +1,49 @@
+use axum::{Json, response::IntoResponse, http::{header::AUTHORIZATION, status::StatusCode, HeaderMap}, extract::State};
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, Email};
+use crate::utils::auth::constant_time_eq;
+
+// Lets an operator unlock an account before its lockout expires.
+// Only registered when an admin token is configured.
+#[tracing::instrument(name = "Unlock_Account", skip_all)]
+pub async fn unlock_account(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<UnlockAccountRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    if !is_admin(&state, &headers) {
+        return Err(AuthAPIError::InvalidToken);
+    }
+
+    let email = Email::parse(request.email)
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    state.account_lockout_store
+        .reset(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    tracing::info!("Account unlocked by an admin");
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+pub struct UnlockAccountRequest {
+    pub email: SecretString,
+}
+
+pub(crate) fn is_admin(state: &AppState, headers: &HeaderMap) -> bool {
+    let Some(api_token) = &state.config.admin.api_token else {
+        return false;
+    };
+
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| constant_time_eq(token.as_bytes(), api_token.expose_secret().as_bytes()))
+}
@@ -0,0 +1,199 @@
+use axum::{Json, response::IntoResponse, http::{status::StatusCode, HeaderMap}, extract::{Query, State}};
+use serde::{Deserialize, Serialize};
+use secrecy::{ExposeSecret, SecretString};
+
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, data_stores::UserStoreError, Email, EmailChangeRevert};
+use crate::services::email_templates::{send_email_template, EmailTemplate, Locale, SecurityEvent};
+use crate::utils::auth::{self, LinkTokenPurpose};
+use crate::utils::auth_user::{self, AuthenticatedUser};
+use crate::utils::constants::AUTH_SERVICE_URL;
+
+// Starts an email change. The user has to re-authenticate with their password.
+// The change is only committed once the link sent to the new address is opened.
+#[tracing::instrument(name = "Change_Email", skip_all)]
+pub async fn change_email(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    headers: HeaderMap,
+    Json(request): Json<ChangeEmailRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let current_email = Email::parse(SecretString::new(user.claims.sub.into_boxed_str()))
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+    let new_email = Email::parse(request.new_email)
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    if current_email == new_email {
+        return Err(AuthAPIError::InvalidCredentials);
+    }
+
+    {
+        let user_store = &state.user_store;
+
+        if let Err(err) = user_store.validate_user(&current_email, request.password.expose_secret()).await {
+            match err {
+                UserStoreError::UserNotFound => return Err(AuthAPIError::IncorrectCredentials),
+                UserStoreError::InvalidCredentials => return Err(AuthAPIError::IncorrectCredentials),
+                _ => return Err(AuthAPIError::UnexpectedError(err.into()))
+            }
+        }
+
+        match user_store.get_user(&new_email).await {
+            Ok(_) => return Err(AuthAPIError::UserAlreadyExists),
+            Err(UserStoreError::UserNotFound) => {},
+            Err(err) => return Err(AuthAPIError::UnexpectedError(err.into())),
+        }
+    }
+
+    // Both links share an ID, so reverting also cancels a change that is not confirmed yet
+    let change_id = uuid::Uuid::new_v4().to_string();
+
+    let confirm_token = auth::generate_link_token(&current_email, LinkTokenPurpose::ConfirmEmailChange, &change_id, Some(&new_email))
+        .map_err(AuthAPIError::UnexpectedError)?;
+    let revert_token = auth::generate_link_token(&current_email, LinkTokenPurpose::RevertEmailChange, &change_id, Some(&new_email))
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    let confirm_link = format!("{}/change-email/confirm?token={}", AUTH_SERVICE_URL.as_str(), confirm_token);
+    let revert_link = format!("{}/change-email/revert?token={}", AUTH_SERVICE_URL.as_str(), revert_token);
+
+    let locale = Locale::from_headers(&headers);
+    let branding = &state.config.branding;
+    let email_client = &state.email_client;
+
+    let template = EmailTemplate::Verification { link: confirm_link };
+    send_email_template(email_client.as_ref(), new_email.clone(), &template, locale, branding)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    let template = EmailTemplate::SecurityNotice {
+        event: SecurityEvent::EmailChangeRequested { new_email: new_email.as_ref().to_owned() },
+        link: Some(revert_link),
+    };
+    send_email_template(email_client.as_ref(), current_email, &template, locale, branding)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    let response = Json(ChangeEmailResponse {
+        message: "Confirmation email sent".to_owned(),
+    });
+
+    Ok((StatusCode::ACCEPTED, response))
+}
+
+#[tracing::instrument(name = "Confirm_Email_Change", skip_all)]
+pub async fn confirm_email_change(
+    State(state): State<AppState>,
+    Query(params): Query<EmailChangeLinkParams>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let claims = auth::validate_link_token(&params.token, LinkTokenPurpose::ConfirmEmailChange, state.banned_token_store.clone())
+        .await
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    // A revert link that was opened first cancels the change
+    let change_cancelled = state.banned_token_store
+        .check_token(&SecretString::new(claims.jti.clone().into_boxed_str()))
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    if change_cancelled {
+        return Err(AuthAPIError::InvalidToken);
+    }
+
+    let (current_email, new_email) = parse_link_emails(claims.sub, claims.new_email)?;
+
+    state.user_store
+        .confirm_email_change(&claims.jti, &current_email, new_email)
+        .await
+        .map_err(|err| match err {
+            UserStoreError::UserAlreadyExists => AuthAPIError::UserAlreadyExists,
+            UserStoreError::UserNotFound => AuthAPIError::InvalidToken,
+            _ => AuthAPIError::UnexpectedError(err.into()),
+        })?;
+
+    // Sessions opened under the old address end with it
+    auth_user::revoke_user_sessions(&state, &current_email)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    auth::ban_token(&params.token, claims.exp, state.banned_token_store.clone())
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    let response = Json(ChangeEmailResponse {
+        message: "Email address updated".to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Revert_Email_Change", skip_all)]
+pub async fn revert_email_change(
+    State(state): State<AppState>,
+    Query(params): Query<EmailChangeLinkParams>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let claims = auth::validate_link_token(&params.token, LinkTokenPurpose::RevertEmailChange, state.banned_token_store.clone())
+        .await
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+    let (current_email, new_email) = parse_link_emails(claims.sub, claims.new_email)?;
+
+    // Cancel the change in case it has not been confirmed yet. The confirm link
+    // expires before the revert link does.
+    auth::ban_token(&claims.jti, claims.exp, state.banned_token_store.clone())
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    // If it was confirmed then move the account back, unless the account has moved on
+    // since and someone else may hold the address now
+    let message = match state.user_store.revert_email_change(&claims.jti).await {
+        Ok(EmailChangeRevert::Reverted) => "Email change reverted",
+        Ok(EmailChangeRevert::NotConfirmed) => "Email change cancelled",
+        Ok(EmailChangeRevert::Ended) => return Err(AuthAPIError::InvalidToken),
+        Err(UserStoreError::UserAlreadyExists) => return Err(AuthAPIError::UserAlreadyExists),
+        Err(err) => return Err(AuthAPIError::UnexpectedError(err.into())),
+    };
+
+    // Whoever asked for the change may still be logged in, under either address
+    for email in [&current_email, &new_email] {
+        auth_user::revoke_user_sessions(&state, email)
+            .await
+            .map_err(AuthAPIError::UnexpectedError)?;
+    }
+
+    auth::ban_token(&params.token, claims.exp, state.banned_token_store.clone())
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    let response = Json(ChangeEmailResponse {
+        message: message.to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+fn parse_link_emails(current_email: String, new_email: Option<String>) -> Result<(Email, Email), AuthAPIError> {
+    let new_email = new_email.ok_or(AuthAPIError::InvalidToken)?;
+
+    let current_email = Email::parse(SecretString::new(current_email.into_boxed_str()))
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+    let new_email = Email::parse(SecretString::new(new_email.into_boxed_str()))
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    Ok((current_email, new_email))
+}
+
+#[derive(Deserialize)]
+pub struct ChangeEmailRequest {
+    pub password: SecretString,
+    #[serde(rename = "newEmail")]
+    pub new_email: SecretString,
+}
+
+#[derive(Deserialize)]
+pub struct EmailChangeLinkParams {
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChangeEmailResponse {
+    pub message: String,
+}
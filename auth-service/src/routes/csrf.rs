@@ -0,0 +1,17 @@
+use axum::{Extension, Json, response::IntoResponse};
+use serde::Serialize;
+
+use crate::utils::csrf::CsrfToken;
+
+// Hands the CSRF token to frontends served from another origin, which can't
+// read our cookies
+#[tracing::instrument(name = "CSRF_Token", skip_all)]
+pub async fn csrf_token(Extension(CsrfToken(token)): Extension<CsrfToken>) -> impl IntoResponse {
+    Json(CsrfTokenResponse { csrf_token: token })
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CsrfTokenResponse {
+    pub csrf_token: String,
+}
@@ -0,0 +1,66 @@
+use axum::{Json, response::{Html, IntoResponse, Response}, http::status::StatusCode, extract::{Path, Query, State}};
+use serde::Deserialize;
+
+use crate::app_state::AppState;
+use crate::domain::AuthAPIError;
+use crate::services::email_templates::{EmailTemplate, Locale};
+use crate::services::file_email_client::FileEmailClient;
+use crate::utils::constants::defaults;
+
+// Renders an email template with sample values so it can be checked in a browser.
+// Only registered when dev mode is on.
+#[tracing::instrument(name = "Preview_Email", skip_all)]
+pub async fn preview_email(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Query(params): Query<PreviewEmailParams>,
+) -> Result<Response, AuthAPIError> {
+    let Some(template) = EmailTemplate::sample(&name) else {
+        return Ok((StatusCode::NOT_FOUND, "Unknown email template").into_response());
+    };
+
+    let locale = params.locale
+        .as_deref()
+        .and_then(Locale::parse)
+        .unwrap_or_default();
+
+    let email = template.render(locale, &state.config.branding)
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    let response = match params.format.as_deref() {
+        Some("text") => email.text_body.into_response(),
+        _ => Html(email.html_body).into_response(),
+    };
+
+    Ok(response)
+}
+
+#[derive(Deserialize)]
+pub struct PreviewEmailParams {
+    pub locale: Option<String>,
+    pub format: Option<String>,
+}
+
+// Lists the most recent emails written by the file email provider.
+// Only registered when dev mode is on.
+#[tracing::instrument(name = "Mailbox", skip_all)]
+pub async fn mailbox(
+    State(state): State<AppState>,
+    Query(params): Query<MailboxParams>,
+) -> Result<Response, AuthAPIError> {
+    let Some(directory) = state.config.email_providers.file_directory() else {
+        return Ok((StatusCode::NOT_FOUND, "The file email provider is not configured").into_response());
+    };
+
+    let limit = params.limit.unwrap_or(defaults::MAILBOX_LIMIT);
+    let messages = FileEmailClient::recent_messages(directory, limit)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    Ok(Json(messages).into_response())
+}
+
+#[derive(Deserialize)]
+pub struct MailboxParams {
+    pub limit: Option<usize>,
+}
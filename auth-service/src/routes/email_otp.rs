@@ -0,0 +1,132 @@
+use axum::{Json, response::IntoResponse, http::{status::StatusCode, HeaderMap}, extract::State};
+use axum_extra::extract::CookieJar;
+use serde::Deserialize;
+use secrecy::SecretString;
+use tracing::Instrument;
+
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, data_stores::UserStoreError, Email, LoginAttemptId, RateLimitDecision, RateLimitPolicy, TwoFACode, TwoFACodePurpose};
+use crate::routes::{TwoFactorAuthResponse, issue_auth_token, verify_two_fa_code};
+use crate::services::email_templates::{EmailTemplate, Locale};
+use crate::utils::client_info::ClientInfo;
+
+// Emails a one-time code that is the only factor needed to log in.
+// Unknown emails get the same response, but no code is sent.
+#[tracing::instrument(name = "Request_Email_OTP", skip_all)]
+pub async fn request_email_otp(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<EmailOtpRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(request.email)
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let policy = &state.config.email_otp_login.request_limit;
+    check_rate_limit(&state, &format!("email_otp_request:{}", email.as_ref()), policy).await?;
+
+    // Addresses that bounce get the same response, there is no point sending to them
+    let can_send = match state.user_store.get_user(&email).await {
+        Ok(user) => !user.email_undeliverable,
+        Err(UserStoreError::UserNotFound) => false,
+        Err(err) => return Err(AuthAPIError::UnexpectedError(err.into())),
+    };
+
+    // The code is made for unknown accounts too, and stored and sent in the background,
+    // so the response takes as long whether or not the account exists
+    let login_attempt_id = LoginAttemptId::default();
+    let two_fa_code = TwoFACode::default();
+
+    let template = EmailTemplate::TwoFACode { code: two_fa_code.as_ref().to_owned() };
+    let rendered = template
+        .render(Locale::from_headers(&headers), &state.config.branding)
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    if can_send {
+        let email_client = state.email_client.clone();
+        let two_fa_code_store = state.two_fa_code_store.clone();
+        let login_attempt_id = login_attempt_id.clone();
+
+        tokio::spawn(async move {
+            let result = two_fa_code_store
+                .add_code(email.clone(), TwoFACodePurpose::EmailOtp, login_attempt_id, two_fa_code)
+                .await;
+
+            if let Err(e) = result {
+                tracing::error!(error = ?e, "Failed to store login code");
+                return;
+            }
+
+            let result = email_client
+                .send_email(email, &rendered.subject, &rendered.html_body, &rendered.text_body)
+                .await;
+
+            if let Err(e) = result {
+                tracing::error!(error = ?e, "Failed to send login code");
+            }
+        }.in_current_span());
+    }
+
+    let response = Json(TwoFactorAuthResponse {
+        message: "Login code sent".to_owned(),
+        login_attempt_id: login_attempt_id.as_ref().to_owned(),
+    });
+
+    Ok((StatusCode::PARTIAL_CONTENT, response))
+}
+
+#[tracing::instrument(name = "Verify_Email_OTP", skip_all)]
+pub async fn verify_email_otp(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    client: ClientInfo,
+    Json(request): Json<VerifyEmailOtpRequest>,
+) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
+    let email = Email::parse(request.email)
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let login_attempt_id = LoginAttemptId::parse(request.login_attempt_id)
+        .map_err(|_| AuthAPIError::InvalidLoginAttempId)?;
+    let two_fa_code = TwoFACode::parse(request.two_fa_code)
+        .map_err(|_| AuthAPIError::InvalidTwoFACode)?;
+
+    // The code is the only factor. Guesses are limited per email and per code
+    // inside, and codes sent for a password login can't be used here.
+    verify_two_fa_code(&state, &email, TwoFACodePurpose::EmailOtp, &login_attempt_id, &two_fa_code).await?;
+
+    let (updated_jar, token) = issue_auth_token(&email, &state, jar, &client, request.return_token).await?;
+
+    let response = match token {
+        Some(token) => (StatusCode::OK, Json(token)).into_response(),
+        None => StatusCode::OK.into_response(),
+    };
+
+    Ok((updated_jar, response))
+}
+
+pub(crate) async fn check_rate_limit(state: &AppState, key: &str, policy: &RateLimitPolicy) -> Result<(), AuthAPIError> {
+    let decision = state.rate_limit_store
+        .check(key, policy)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    match decision {
+        RateLimitDecision::Allowed => Ok(()),
+        RateLimitDecision::Limited { retry_after } => Err(AuthAPIError::TooManyRequests { retry_after }),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct EmailOtpRequest {
+    pub email: SecretString,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmailOtpRequest {
+    pub email: SecretString,
+    #[serde(rename = "loginAttemptId")]
+    pub login_attempt_id: String,
+    #[serde(rename = "2FACode")]
+    pub two_fa_code: String,
+    // Clients that can't keep cookies ask for the token in the response body
+    #[serde(default, rename = "returnToken")]
+    pub return_token: bool,
+}
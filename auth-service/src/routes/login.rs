@@ -1,16 +1,22 @@
-use axum::{Json, response::IntoResponse, http::status::StatusCode, extract::State};
+use axum::{Json, response::IntoResponse, http::{status::StatusCode, HeaderMap}, extract::State};
 use axum_extra::extract::CookieJar;
 use serde::{Deserialize, Serialize};
 use secrecy::{ExposeSecret, SecretString};
+use std::time::Duration;
 
 use crate::app_state::AppState;
-use crate::domain::{AuthAPIError, data_stores::UserStoreError, Email, HashedPassword, LoginAttemptId, TwoFACode};
+use crate::domain::{AuthAPIError, data_stores::UserStoreError, Email, FailedLoginOutcome, HashedPassword, LockoutStatus, LoginAttemptId, Session, SessionId, TwoFACode, TwoFACodePurpose, User};
+use crate::services::email_templates::{send_email_template, EmailTemplate, Locale, SecurityEvent};
 use crate::utils::auth;
+use crate::utils::client_info::ClientInfo;
+use crate::utils::config::AuthMode;
 
 #[tracing::instrument(name = "Login", skip_all)]
 pub async fn login(
     State(state): State<AppState>, 
     jar: CookieJar,
+    headers: HeaderMap,
+    client: ClientInfo,
     Json(request): Json<LoginRequest>
 ) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
     
@@ -19,17 +25,45 @@ pub async fn login(
     
     HashedPassword::parse(request.password.clone()).await?;
 
-    let user_store = state.user_store.write().await;
-    
-    if let Err(err) = user_store.validate_user(&email, request.password.expose_secret()).await {
+    let lockout = &state.config.account_lockout;
+
+    // A locked account is rejected even with the right password
+    if lockout.enabled {
+        let status = state.account_lockout_store
+            .status(&email)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+        if let LockoutStatus::Locked { retry_after } = status {
+            return Err(AuthAPIError::AccountLocked { retry_after });
+        }
+    }
+
+    if let Err(err) = state.user_store.validate_user(&email, request.password.expose_secret()).await {
         match err {
-            UserStoreError::UserNotFound => return Err(AuthAPIError::IncorrectCredentials),
-            UserStoreError::InvalidCredentials => return Err(AuthAPIError::IncorrectCredentials),
+            // Unknown emails are counted too, so a lockout doesn't reveal whether an account exists
+            UserStoreError::UserNotFound | UserStoreError::InvalidCredentials => {
+                // Unknown users skip the password check, so do an equivalent one
+                if err == UserStoreError::UserNotFound {
+                    HashedPassword::verify_dummy_password(&request.password)
+                        .await
+                        .map_err(AuthAPIError::UnexpectedError)?;
+                }
+
+                return Err(record_failed_login(&state, &email, Locale::from_headers(&headers)).await);
+            }
             _ => return Err(AuthAPIError::UnexpectedError(err.into()))
         }
     }
+
+    if lockout.enabled {
+        state.account_lockout_store
+            .reset(&email)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    }
     
-    let user = user_store.get_user(&email).await
+    let user = state.user_store.get_user(&email).await
         .map_err(|err| {
             match err {
                 UserStoreError::UserNotFound => AuthAPIError::InvalidCredentials,
@@ -38,36 +72,93 @@ pub async fn login(
         })?;
 
     let (res1, res2, res3) = match user.requires_2fa {
-        true => handle_2fa(&email, &state, jar.clone()).await,
-        false => handle_no_2fa(&email, jar.clone()).await,
+        true => handle_2fa(&user, &state, jar.clone(), Locale::from_headers(&headers)).await,
+        false => handle_no_2fa(&email, &state, jar.clone(), &client, request.return_token).await,
     }?;
 
     Ok((res1, (res2, res3.into_response())))
 }
 
+// Returns the error for a wrong password, which depends on whether it locked the account
+async fn record_failed_login(state: &AppState, email: &Email, locale: Locale) -> AuthAPIError {
+    let lockout = &state.config.account_lockout;
+
+    if !lockout.enabled {
+        return AuthAPIError::IncorrectCredentials;
+    }
+
+    let outcome = state.account_lockout_store
+        .record_failure(email, &lockout.policy)
+        .await;
+
+    match outcome {
+        Ok(FailedLoginOutcome::Counted { .. }) => AuthAPIError::IncorrectCredentials,
+        Ok(FailedLoginOutcome::Locked { lock_duration }) => {
+            tracing::warn!("Account locked for {}s after failed logins", lock_duration.as_secs());
+
+            if lockout.notify_user {
+                notify_account_locked(state, email, lock_duration, locale).await;
+            }
+
+            AuthAPIError::AccountLocked { retry_after: lock_duration }
+        }
+        Err(err) => AuthAPIError::UnexpectedError(err.into()),
+    }
+}
+
+// The lock is already in place, so a notice that can't be sent is only logged
+async fn notify_account_locked(state: &AppState, email: &Email, lock_duration: Duration, locale: Locale) {
+    let user = match state.user_store.get_user(email).await {
+        Ok(user) if !user.email_undeliverable => user,
+        Ok(_) | Err(UserStoreError::UserNotFound) => return,
+        Err(err) => {
+            tracing::error!(error = %err, "Failed to get user for lockout notice");
+            return;
+        }
+    };
+
+    let template = EmailTemplate::SecurityNotice {
+        event: SecurityEvent::AccountLocked { minutes: lock_duration.as_secs().div_ceil(60) },
+        link: None,
+    };
+
+    let email_client = &state.email_client;
+    if let Err(err) = send_email_template(email_client.as_ref(), user.email, &template, locale, &state.config.branding).await {
+        tracing::error!(error = %err, "Failed to send lockout notice");
+    }
+}
+
 #[derive(Deserialize)]
 pub struct LoginRequest {
     pub email: SecretString,
     pub password: SecretString,
+    // Clients that can't keep cookies ask for the token in the response body
+    #[serde(default, rename = "returnToken")]
+    pub return_token: bool,
 }
 
 #[tracing::instrument(name = "Handle_2FA", skip_all)]
-async fn handle_2fa(email: &Email, state: &AppState, jar: CookieJar) -> Result<(CookieJar, StatusCode, Json<LoginResponse>), AuthAPIError> {
+pub(crate) async fn handle_2fa(user: &User, state: &AppState, jar: CookieJar, locale: Locale) -> Result<(CookieJar, StatusCode, Json<LoginResponse>), AuthAPIError> {
+    // The code would never arrive, so tell the user instead of sending it
+    if user.email_undeliverable {
+        return Err(AuthAPIError::EmailUndeliverable);
+    }
+
+    let email = &user.email;
 
     let login_attempt_id = LoginAttemptId::default();
     let two_fa_code = TwoFACode::default();
 
     // Send 2FA email
-    let email_client = state.email_client.write().await;
-    let subject= "Code";
-    let content = two_fa_code.as_ref();
-    email_client.send_email(email.clone(), subject, content)
+    let email_client = &state.email_client;
+    let template = EmailTemplate::TwoFACode { code: two_fa_code.as_ref().to_owned() };
+    send_email_template(email_client.as_ref(), email.clone(), &template, locale, &state.config.branding)
         .await
         .map_err(AuthAPIError::UnexpectedError)?;
 
     // Add 2FA code to store
-    let mut two_fa_code_store = state.two_fa_code_store.write().await;
-    two_fa_code_store.add_code(email.clone(), login_attempt_id.clone(), two_fa_code)
+    let two_fa_code_store = &state.two_fa_code_store;
+    two_fa_code_store.add_code(email.clone(), TwoFACodePurpose::Login, login_attempt_id.clone(), two_fa_code)
         .await
         .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
     
@@ -82,22 +173,78 @@ async fn handle_2fa(email: &Email, state: &AppState, jar: CookieJar) -> Result<(
 }
 
 #[tracing::instrument(name = "Handle_no_2FA", skip_all)]
-async fn handle_no_2fa(email: &Email, jar: CookieJar) -> Result<(CookieJar, StatusCode, Json<LoginResponse>), AuthAPIError> {
-    let auth_cookie = auth::generate_auth_cookie(email)
-        .map_err(|e| {AuthAPIError::UnexpectedError(e)})?;
+pub(crate) async fn handle_no_2fa(email: &Email, state: &AppState, jar: CookieJar, client: &ClientInfo, return_token: bool) -> Result<(CookieJar, StatusCode, Json<LoginResponse>), AuthAPIError> {
+    let (updated_jar, token) = issue_auth_token(email, state, jar, client, return_token).await?;
 
-    let updated_jar = jar.add(auth_cookie);
-
-    let response = LoginResponse::RegularAuth;
+    let response = match token {
+        Some(token) => LoginResponse::Token(token),
+        None => LoginResponse::RegularAuth,
+    };
 
     Ok((updated_jar, StatusCode::OK, response.into()))
 }
 
+// Sets the auth cookie, or returns the token for the response body when the
+// client asked for it. Either way a session is recorded. In session mode the
+// token is its ID, in JWT mode the token carries it and is only accepted while
+// the session is in the store.
+pub(crate) async fn issue_auth_token(email: &Email, state: &AppState, jar: CookieJar, client: &ClientInfo, return_token: bool) -> Result<(CookieJar, Option<TokenResponse>), AuthAPIError> {
+    if state.config.auth_mode == AuthMode::Session {
+        return start_session(email, state, jar, client, return_token).await;
+    }
+
+    // The JWT can't outlive its session record
+    let ttl = Duration::from_secs(auth::TOKEN_TTL_SECONDS as u64);
+    let session_id = record_session(email, state, client, ttl).await?;
+
+    if return_token {
+        let token = auth::generate_auth_token(email, Some(&session_id))
+            .map_err(AuthAPIError::UnexpectedError)?;
+
+        return Ok((jar, Some(TokenResponse::bearer(token, auth::TOKEN_TTL_SECONDS))));
+    }
+
+    let auth_cookie = auth::generate_auth_cookie(email, Some(&session_id), &state.config.auth_cookie)
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    Ok((jar.add(auth_cookie), None))
+}
+
+#[tracing::instrument(name = "Start_Session", skip_all)]
+async fn start_session(email: &Email, state: &AppState, jar: CookieJar, client: &ClientInfo, return_token: bool) -> Result<(CookieJar, Option<TokenResponse>), AuthAPIError> {
+    let policy = &state.config.session.policy;
+    // Activity keeps the session going, so the first idle timeout is all that is certain
+    let ttl = policy.idle_timeout.min(policy.absolute_timeout);
+    let id = record_session(email, state, client, ttl.to_std().unwrap_or_default()).await?;
+
+    if return_token {
+        return Ok((jar, Some(TokenResponse::bearer(id.as_ref().to_owned(), ttl.num_seconds()))));
+    }
+
+    let auth_cookie = auth::generate_session_cookie(&id, policy, &state.config.auth_cookie);
+
+    Ok((jar.add(auth_cookie), None))
+}
+
+// Adds a new session of the client to the session store
+async fn record_session(email: &Email, state: &AppState, client: &ClientInfo, ttl: Duration) -> Result<SessionId, AuthAPIError> {
+    let session = Session::new(email.clone(), client.ip, client.user_agent.clone());
+    let id = session.id.clone();
+
+    state.session_store
+        .add_session(session, ttl)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(id)
+}
+
 #[derive(Debug, Serialize)]
 #[serde(untagged)]
 pub enum LoginResponse {
     RegularAuth,
     TwoFactorAuth(TwoFactorAuthResponse),
+    Token(TokenResponse),
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -105,4 +252,23 @@ pub struct TwoFactorAuthResponse {
     pub message: String,
     #[serde(rename = "loginAttemptId")]
     pub login_attempt_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenResponse {
+    pub token: String,
+    pub token_type: String,
+    // Seconds until the token expires
+    pub expires_in: i64,
+}
+
+impl TokenResponse {
+    fn bearer(token: String, expires_in: i64) -> Self {
+        Self {
+            token,
+            token_type: "Bearer".to_owned(),
+            expires_in,
+        }
+    }
 }
\ No newline at end of file
@@ -3,33 +3,29 @@ use axum::{response::IntoResponse,
     extract::State,
 };
 use axum_extra::extract::CookieJar;
-use secrecy::SecretString;
 
 use crate::{
     domain::AuthAPIError,
-    utils::constants::JWT_COOKIE_NAME,
     utils::auth,
+    utils::auth_user::{revoke_auth_token, AuthenticatedUser, TokenSource},
     AppState,
 };
 
 #[tracing::instrument(name = "Logout", skip_all)]
-pub async fn logout(State(state): State<AppState>, jar: CookieJar) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
-    // Retrieve JWT cookie from the `CookieJar`
-    let cookie = jar.get(JWT_COOKIE_NAME).ok_or(AuthAPIError::MissingToken)?;
-
-    let token = cookie.value().to_owned();
-    let banned_token_store = state.banned_token_store;
-
-    // validate_token
-    let _claims = auth::validate_token(&token, banned_token_store.clone()).await
-        .map_err(|_| AuthAPIError::InvalidToken)?;
-
-    let mut banned_token_store = banned_token_store.write().await;
-    banned_token_store.add_token(&SecretString::new(token.into_boxed_str()))
+pub async fn logout(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    user: AuthenticatedUser,
+) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
+    revoke_auth_token(&state, &user.token, &user.claims)
         .await
-        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+        .map_err(AuthAPIError::UnexpectedError)?;
 
-    let jar = jar.remove(JWT_COOKIE_NAME);
+    // Bearer clients keep the token themselves, there is no cookie to clear
+    let jar = match user.source {
+        TokenSource::Cookie => jar.remove(auth::removal_auth_cookie(&state.config.auth_cookie)),
+        TokenSource::Bearer => jar,
+    };
     
     Ok((jar, StatusCode::OK.into_response()))
-}
\ No newline at end of file
+}
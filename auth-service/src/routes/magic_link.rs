@@ -0,0 +1,122 @@
+use axum::{Json, response::IntoResponse, http::{status::StatusCode, HeaderMap}, extract::{Query, State}};
+use axum_extra::extract::CookieJar;
+use serde::{Deserialize, Serialize};
+use secrecy::SecretString;
+use tracing::Instrument;
+
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, data_stores::UserStoreError, Email};
+use crate::routes::{check_rate_limit, handle_2fa, handle_no_2fa};
+use crate::services::email_templates::{EmailTemplate, Locale};
+use crate::utils::auth::{self, LinkTokenPurpose};
+use crate::utils::client_info::ClientInfo;
+use crate::utils::constants::AUTH_SERVICE_URL;
+
+// Emails a single-use login link. The response is the same whether or not the
+// account exists so the route can't be used to find registered emails.
+#[tracing::instrument(name = "Request_Magic_Link", skip_all)]
+pub async fn request_magic_link(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<MagicLinkRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(request.email)
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let policy = &state.config.magic_link.request_limit;
+    check_rate_limit(&state, &format!("magic_link_request:{}", email.as_ref()), policy).await?;
+
+    // Addresses that bounce get the same response, there is no point sending to them
+    let can_send = match state.user_store.get_user(&email).await {
+        Ok(user) => !user.email_undeliverable,
+        Err(UserStoreError::UserNotFound) => false,
+        Err(err) => return Err(AuthAPIError::UnexpectedError(err.into())),
+    };
+
+    // The link is built for unknown accounts too, and delivery happens in the background,
+    // so the response takes as long and succeeds the same whether or not the account exists
+    let login_id = uuid::Uuid::new_v4().to_string();
+    let token = auth::generate_link_token(&email, LinkTokenPurpose::MagicLink, &login_id, None)
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    let template = EmailTemplate::LoginLink {
+        link: format!("{}/login/magic-link/callback?token={}", AUTH_SERVICE_URL.as_str(), token),
+        expires_in_minutes: auth::MAGIC_LINK_TTL_SECONDS / 60,
+    };
+    let rendered = template
+        .render(Locale::from_headers(&headers), &state.config.branding)
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    if can_send {
+        let email_client = state.email_client.clone();
+
+        tokio::spawn(async move {
+            let result = email_client
+                .send_email(email, &rendered.subject, &rendered.html_body, &rendered.text_body)
+                .await;
+
+            if let Err(e) = result {
+                tracing::error!(error = ?e, "Failed to send magic link");
+            }
+        }.in_current_span());
+    }
+
+    let response = Json(MagicLinkResponse {
+        message: "If the account exists, a login link has been sent".to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+// Logs the user in from a magic link. Accounts with 2FA enabled still have to
+// verify the emailed code, exactly like after a password login.
+#[tracing::instrument(name = "Magic_Link_Callback", skip_all)]
+pub async fn magic_link_callback(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    client: ClientInfo,
+    Query(params): Query<MagicLinkParams>,
+) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
+    let claims = auth::validate_link_token(&params.token, LinkTokenPurpose::MagicLink, state.banned_token_store.clone())
+        .await
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    // Ban the link right away so it can't be used twice
+    auth::ban_token(&params.token, claims.exp, state.banned_token_store.clone())
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    let email = Email::parse(SecretString::new(claims.sub.into_boxed_str()))
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let user = state.user_store
+        .get_user(&email)
+        .await
+        .map_err(|err| match err {
+            UserStoreError::UserNotFound => AuthAPIError::InvalidToken,
+            _ => AuthAPIError::UnexpectedError(err.into()),
+        })?;
+
+    let (res1, res2, res3) = match user.requires_2fa {
+        true => handle_2fa(&user, &state, jar.clone(), Locale::from_headers(&headers)).await,
+        false => handle_no_2fa(&email, &state, jar.clone(), &client, false).await,
+    }?;
+
+    Ok((res1, (res2, res3.into_response())))
+}
+
+#[derive(Deserialize)]
+pub struct MagicLinkRequest {
+    pub email: SecretString,
+}
+
+#[derive(Deserialize)]
+pub struct MagicLinkParams {
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MagicLinkResponse {
+    pub message: String,
+}
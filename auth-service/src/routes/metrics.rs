@@ -0,0 +1,23 @@
+use axum::{response::IntoResponse, http::{status::StatusCode, HeaderMap}, extract::State};
+
+use crate::app_state::AppState;
+use crate::domain::AuthAPIError;
+use crate::utils::metrics::render_metrics;
+use super::is_admin;
+
+// Metrics reveal traffic and provider health, so scrapers need the admin token.
+// Only registered when an admin token is configured.
+#[tracing::instrument(name = "Metrics", skip_all)]
+pub async fn metrics(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    if !is_admin(&state, &headers) {
+        return Err(AuthAPIError::InvalidToken);
+    }
+
+    match render_metrics() {
+        Some(body) => Ok((StatusCode::OK, body)),
+        None => Ok((StatusCode::NOT_FOUND, "Metrics are not enabled".to_owned())),
+    }
+}
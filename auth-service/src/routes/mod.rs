@@ -3,9 +3,27 @@ mod login;
 mod logout;
 mod verify_2fa;
 mod verify_token;
+mod change_email;
+mod magic_link;
+mod email_otp;
+mod dev;
+mod metrics;
+mod webhooks;
+mod admin;
+mod csrf;
+mod sessions;
 
 pub use signup::*;
 pub use login::*;
 pub use logout::*;
 pub use verify_2fa::*;
-pub use verify_token::*;
\ No newline at end of file
+pub use verify_token::*;
+pub use change_email::*;
+pub use magic_link::*;
+pub use email_otp::*;
+pub use dev::*;
+pub use metrics::*;
+pub use webhooks::*;
+pub use admin::*;
+pub use csrf::*;
+pub use sessions::*;
\ No newline at end of file
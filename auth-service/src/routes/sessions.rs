@@ -0,0 +1,116 @@
+use axum::{Json, extract::{Path, State}, http::StatusCode, response::IntoResponse};
+use secrecy::SecretString;
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, Email, Session};
+use crate::utils::auth_user::{self, AuthenticatedUser};
+
+// Where the user is logged in, most recently active first
+#[tracing::instrument(name = "List_Sessions", skip_all)]
+pub async fn list_sessions(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let mut sessions = user_sessions(&state, &user).await?;
+    sessions.sort_by_key(|session| std::cmp::Reverse(session.last_seen));
+
+    let current = user.claims.sid.as_deref();
+    let sessions = sessions
+        .iter()
+        .map(|session| SessionResponse::new(session, current, &state))
+        .collect();
+
+    Ok(Json(SessionsResponse { sessions }))
+}
+
+// Logs one of the user's sessions out, by the ID shown in the listing
+#[tracing::instrument(name = "Revoke_Session", skip_all)]
+pub async fn revoke_session(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let session = user_sessions(&state, &user)
+        .await?
+        .into_iter()
+        .find(|session| session.id.public_id() == id)
+        .ok_or(AuthAPIError::SessionNotFound)?;
+
+    auth_user::revoke_session(&state, &session.id)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn user_sessions(state: &AppState, user: &AuthenticatedUser) -> Result<Vec<Session>, AuthAPIError> {
+    let email = Email::parse(SecretString::new(user.claims.sub.clone().into_boxed_str()))
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    state.session_store
+        .list_sessions(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+}
+
+// "Chrome on Windows 10", when the user agent is recognised
+fn describe_device(user_agent: &str) -> Option<String> {
+    let agent = woothee::parser::Parser::new().parse(user_agent)?;
+    let known = |value: &str| value != woothee::woothee::VALUE_UNKNOWN;
+
+    match (known(agent.name), known(agent.os)) {
+        (true, true) => Some(format!("{} on {}", agent.name, agent.os)),
+        (true, false) => Some(agent.name.to_owned()),
+        (false, true) => Some(agent.os.to_owned()),
+        (false, false) => None,
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionsResponse {
+    pub sessions: Vec<SessionResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionResponse {
+    pub id: String,
+    pub device: Option<String>,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub location: Option<String>,
+    // RFC 3339 timestamps
+    pub created_at: String,
+    pub last_seen: String,
+    // The session making this request
+    pub current: bool,
+}
+
+impl SessionResponse {
+    fn new(session: &Session, current: Option<&str>, state: &AppState) -> Self {
+        Self {
+            id: session.id.public_id(),
+            device: session.user_agent.as_deref().and_then(describe_device),
+            user_agent: session.user_agent.clone(),
+            ip: session.ip.map(|ip| ip.to_string()),
+            location: session.ip.and_then(|ip| state.geoip.locate(ip)),
+            created_at: session.created_at.to_rfc3339(),
+            last_seen: session.last_seen.to_rfc3339(),
+            current: current == Some(session.id.as_ref()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describes_known_devices() {
+        let chrome = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+
+        assert_eq!(describe_device(chrome), Some("Chrome on Windows 10".to_owned()));
+        assert_eq!(describe_device("not a browser"), None);
+    }
+}
@@ -1,29 +1,31 @@
-use axum::{Json, response::IntoResponse, http::status::StatusCode, extract::State};
+use axum::{Json, response::IntoResponse, http::{status::StatusCode, HeaderMap}, extract::State};
 use serde::{Deserialize, Serialize};
 use secrecy::SecretString;
 
 use crate::app_state::AppState;
 use crate::domain::{AuthAPIError, User, data_stores::UserStoreError, Email, HashedPassword};
+use crate::services::email_templates::{send_email_template, EmailTemplate, Locale, SecurityEvent};
 
 #[tracing::instrument(name = "Signup", skip_all)]
-pub async fn signup(State(state): State<AppState>, Json(request): Json<SignupRequest>) -> Result<impl IntoResponse, AuthAPIError> {
+pub async fn signup(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<SignupRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
     let email = Email::parse(request.email)
         .map_err(|_| AuthAPIError::InvalidCredentials)?;
     let password = HashedPassword::parse(request.password).await?;
     let requires_2fa = request.requires_2fa;
 
-    let user = User::new(email, password, requires_2fa);
-    let mut user_store = state.user_store.write().await;
-
-    user_store.add_user(user).await
-        .map_err(|err| 
-            {
-                match err {
-                    UserStoreError::UserAlreadyExists => AuthAPIError::UserAlreadyExists,
-                    _ => AuthAPIError::UnexpectedError(err.into()),
-                }
-            }
-        )?;
+    let user = User::new(email.clone(), password, requires_2fa);
+    match state.user_store.add_user(user).await {
+        Ok(()) => {}
+        Err(UserStoreError::UserAlreadyExists) if state.config.enumeration_safe_signup => {
+            notify_signup_attempt(&state, &email, Locale::from_headers(&headers)).await?;
+        }
+        Err(UserStoreError::UserAlreadyExists) => return Err(AuthAPIError::UserAlreadyExists),
+        Err(err) => return Err(AuthAPIError::UnexpectedError(err.into())),
+    }
 
     let response = Json(SignupRespose {
         message: "User created successfully!".to_owned(),
@@ -32,6 +34,25 @@ pub async fn signup(State(state): State<AppState>, Json(request): Json<SignupReq
     Ok((StatusCode::CREATED, response))
 }
 
+// Lets the owner of an existing account know someone tried to sign up with it
+async fn notify_signup_attempt(state: &AppState, email: &Email, locale: Locale) -> Result<(), AuthAPIError> {
+    let user = state.user_store
+        .get_user(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    if user.email_undeliverable {
+        return Ok(());
+    }
+
+    let template = EmailTemplate::SecurityNotice { event: SecurityEvent::SignupAttempted, link: None };
+
+    let email_client = &state.email_client;
+    send_email_template(email_client.as_ref(), user.email, &template, locale, &state.config.branding)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)
+}
+
 #[derive(Deserialize, Debug)]
 pub struct SignupRequest {
     pub email: SecretString,
@@ -43,4 +64,4 @@ pub struct SignupRequest {
 #[derive(Serialize)]
 pub struct SignupRespose {
     pub message: String,
-}
\ No newline at end of file
+}
@@ -6,14 +6,16 @@ use axum_extra::extract::CookieJar;
 use serde::Deserialize;
 use secrecy::SecretString;
 
-use crate::domain::{AuthAPIError, Email, LoginAttemptId, TwoFACode};
+use crate::domain::{AuthAPIError, data_stores::TwoFACodeStoreError, Email, LoginAttemptId, TwoFACode, TwoFACodePurpose};
 use crate::AppState;
-use crate::utils::auth;
+use crate::utils::client_info::ClientInfo;
+use super::{check_rate_limit, issue_auth_token};
 
 #[tracing::instrument(name = "Verify_2FA", skip_all)]
 pub async fn verify_2fa(
     State(state): State<AppState>,
     jar: CookieJar,
+    client: ClientInfo,
     Json(request): Json<VerifyTwoFARequest>
 ) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
 
@@ -26,32 +28,56 @@ pub async fn verify_2fa(
         .map_err(|_| AuthAPIError::InvalidTwoFACode)?;
 
     // Verify login attempt ID and 2FA code Are correct. If not valid return HTTP code 401
-    let (login_attempt_id_true, two_fa_code_true) = {
-        let two_fa_store = state.two_fa_code_store.read().await;
-        let (login_attempt_id_true, two_fa_code_true) = two_fa_store.get_code(&email)
-            .await
-            .map_err(|_| AuthAPIError::IncorrectCredentials)?;
-        (login_attempt_id_true, two_fa_code_true)
+    verify_two_fa_code(&state, &email, TwoFACodePurpose::Login, &login_attempt_id, &two_fa_code).await?;
+
+    // Create JWT token
+    let (updated_jar, token) = issue_auth_token(&email, &state, jar, &client, request.return_token).await?;
+
+    let response = match token {
+        Some(token) => (StatusCode::OK, Json(token)).into_response(),
+        None => StatusCode::OK.into_response(),
     };
 
-    if (login_attempt_id != login_attempt_id_true) || (two_fa_code != two_fa_code_true) {
-        return Err(AuthAPIError::IncorrectCredentials);
-    }
+    Ok((updated_jar, response))
+}
 
-    // Create JWT token
-    let auth_cookie = auth::generate_auth_cookie(&email)
-        .map_err(|e| {AuthAPIError::UnexpectedError(e)})?;
-    let updated_jar = jar.add(auth_cookie);
+// Check the login attempt ID and 2FA code against the store.
+// The code is removed once it has been used, or after too many wrong guesses.
+// Guesses are limited per email across all purposes, so no route is an easier target.
+pub(crate) async fn verify_two_fa_code(
+    state: &AppState,
+    email: &Email,
+    purpose: TwoFACodePurpose,
+    login_attempt_id: &LoginAttemptId,
+    two_fa_code: &TwoFACode,
+) -> Result<(), AuthAPIError> {
+    let config = &state.config.two_fa;
+    check_rate_limit(state, &format!("two_fa_verify:{}", email.as_ref()), &config.verify_limit).await?;
 
-    // Remove 2FA code from store
-    {
-        let mut two_fa_store = state.two_fa_code_store.write().await;
-        two_fa_store.remove_code(&email)
-            .await
-            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    let two_fa_store = &state.two_fa_code_store;
+    let (login_attempt_id_true, two_fa_code_true) = two_fa_store.get_code(email, purpose)
+        .await
+        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+    if (*login_attempt_id != login_attempt_id_true) || (*two_fa_code != two_fa_code_true) {
+        // The code may have been used or replaced meanwhile, which is just as wrong
+        match two_fa_store.record_failed_attempt(email, purpose).await {
+            Ok(failed_attempts) if failed_attempts >= config.max_failed_attempts => {
+                two_fa_store.remove_code(email, purpose)
+                    .await
+                    .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+            }
+            Ok(_) | Err(TwoFACodeStoreError::LoginAttempIdNotFound) => {}
+            Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+        }
+
+        return Err(AuthAPIError::IncorrectCredentials);
     }
 
-    Ok((updated_jar, StatusCode::OK))
+    // Remove 2FA code from store
+    two_fa_store.remove_code(email, purpose)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
 }
 
 #[derive(Debug, Deserialize)]
@@ -61,4 +87,7 @@ pub struct VerifyTwoFARequest {
     pub login_attempt_id: String,
     #[serde(rename = "2FACode")]
     pub two_fa_code: String,
+    // Clients that can't keep cookies ask for the token in the response body
+    #[serde(default, rename = "returnToken")]
+    pub return_token: bool,
 }
@@ -1,24 +1,22 @@
-use axum::{Json, extract::State, http::status::StatusCode, response::IntoResponse};
+use axum::{Json, extract::State, response::IntoResponse};
 use serde::Deserialize;
 
 use crate::AppState;
 use crate::domain::AuthAPIError;
-use crate::utils::auth;
+use crate::utils::auth_user::validate_auth_token;
 
+// Answers with the token's claims, so services can tell who the user is even
+// when the token is an opaque session ID
 #[tracing::instrument(name = "Verify_Token", skip_all)]
 pub async fn verify_token(
     State(state): State<AppState>,
     Json(request): Json<VerifyTokenRequest>,
 ) -> Result<impl IntoResponse, AuthAPIError> {
-    let token = request.token;
-
-    let banned_token_store = state.banned_token_store;
-
-    let _claims = auth::validate_token(&token, banned_token_store)
+    let claims = validate_auth_token(&state, &request.token)
         .await
         .map_err(|_| AuthAPIError::InvalidToken)?;
 
-    Ok(StatusCode::OK.into_response())
+    Ok(Json(claims))
 }
 
 #[derive(Deserialize)]
@@ -0,0 +1,150 @@
+use axum::{Json, response::IntoResponse, http::{header::AUTHORIZATION, status::StatusCode, HeaderMap}, extract::State};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, data_stores::UserStoreError, Email};
+use crate::utils::auth::constant_time_eq;
+use crate::utils::config::PostmarkWebhookConfig;
+use crate::utils::constants::WEBHOOK_SECRET_HEADER;
+
+// Receives bounce and spam complaint notifications from Postmark and stops
+// relying on addresses that can't receive emails. Always answers 200 for
+// authenticated requests, otherwise Postmark keeps retrying.
+#[tracing::instrument(name = "Postmark_Webhook", skip_all)]
+pub async fn postmark_webhook(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<PostmarkWebhookPayload>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    if !is_authorized(&state.config.postmark_webhook, &headers) {
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+
+    if !payload.is_undeliverable() {
+        tracing::debug!(record_type = %payload.record_type, "Ignoring Postmark webhook");
+        return Ok(StatusCode::OK);
+    }
+
+    let Some(email) = payload.email.and_then(|email| Email::parse(SecretString::new(email.into_boxed_str())).ok()) else {
+        tracing::warn!(record_type = %payload.record_type, "Postmark webhook has no valid email");
+        return Ok(StatusCode::OK);
+    };
+
+    match state.user_store.mark_email_undeliverable(&email).await {
+        Ok(()) => tracing::info!(record_type = %payload.record_type, "Marked email as undeliverable"),
+        Err(UserStoreError::UserNotFound) => tracing::debug!("Postmark webhook for an unknown user"),
+        Err(err) => return Err(AuthAPIError::UnexpectedError(err.into())),
+    }
+
+    Ok(StatusCode::OK)
+}
+
+fn is_authorized(config: &PostmarkWebhookConfig, headers: &HeaderMap) -> bool {
+    let secret_matches = config.secret.as_ref().is_some_and(|secret| {
+        headers
+            .get(WEBHOOK_SECRET_HEADER)
+            .is_some_and(|value| constant_time_eq(value.as_bytes(), secret.expose_secret().as_bytes()))
+    });
+
+    let basic_auth_matches = config.basic_auth.as_ref().is_some_and(|credentials| {
+        let expected = format!("{}:{}", credentials.username, credentials.password.expose_secret());
+
+        headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Basic "))
+            .and_then(|encoded| STANDARD.decode(encoded.trim()).ok())
+            .is_some_and(|decoded| constant_time_eq(&decoded, expected.as_bytes()))
+    });
+
+    secret_matches || basic_auth_matches
+}
+
+// The fields we use from Postmark bounce and spam complaint webhooks.
+// See https://postmarkapp.com/developer/webhooks/bounce-webhook
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PostmarkWebhookPayload {
+    pub record_type: String,
+    #[serde(rename = "Type")]
+    pub bounce_type: Option<String>,
+    pub email: Option<String>,
+    // Postmark stopped sending to the address
+    #[serde(default)]
+    pub inactive: bool,
+}
+
+impl PostmarkWebhookPayload {
+    // Soft bounces like a full mailbox are temporary, so only permanent
+    // failures and complaints count
+    fn is_undeliverable(&self) -> bool {
+        match self.record_type.as_str() {
+            "SpamComplaint" => true,
+            "Bounce" => {
+                self.inactive
+                    || matches!(
+                        self.bounce_type.as_deref(),
+                        Some("HardBounce" | "BadEmailAddress" | "ManuallyDeactivated" | "SpamComplaint")
+                    )
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+    use crate::utils::config::BasicAuthCredentials;
+
+    fn payload(json: serde_json::Value) -> PostmarkWebhookPayload {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn test_is_undeliverable() {
+        let test_cases = [
+            (serde_json::json!({ "RecordType": "Bounce", "Type": "HardBounce", "Email": "a@example.com" }), true),
+            (serde_json::json!({ "RecordType": "Bounce", "Type": "SoftBounce", "Email": "a@example.com", "Inactive": true }), true),
+            (serde_json::json!({ "RecordType": "Bounce", "Type": "SoftBounce", "Email": "a@example.com", "Inactive": false }), false),
+            (serde_json::json!({ "RecordType": "Bounce", "Type": "Transient", "Email": "a@example.com" }), false),
+            (serde_json::json!({ "RecordType": "SpamComplaint", "Email": "a@example.com" }), true),
+            (serde_json::json!({ "RecordType": "Delivery", "Recipient": "a@example.com" }), false),
+        ];
+
+        for (json, expected) in test_cases {
+            assert_eq!(payload(json.clone()).is_undeliverable(), expected, "Failed for payload: {}", json);
+        }
+    }
+
+    #[test]
+    fn test_is_authorized() {
+        let config = PostmarkWebhookConfig {
+            basic_auth: Some(BasicAuthCredentials {
+                username: "postmark".to_owned(),
+                password: SecretString::new("password".to_owned().into_boxed_str()),
+            }),
+            secret: Some(SecretString::new("secret".to_owned().into_boxed_str())),
+        };
+
+        let test_cases = [
+            (AUTHORIZATION.as_str(), format!("Basic {}", STANDARD.encode("postmark:password")), true),
+            (AUTHORIZATION.as_str(), format!("Basic {}", STANDARD.encode("postmark:wrong")), false),
+            (AUTHORIZATION.as_str(), "Bearer secret".to_owned(), false),
+            (WEBHOOK_SECRET_HEADER, "secret".to_owned(), true),
+            (WEBHOOK_SECRET_HEADER, "wrong".to_owned(), false),
+        ];
+
+        for (name, value, expected) in test_cases {
+            let mut headers = HeaderMap::new();
+            headers.insert(name, HeaderValue::from_str(&value).unwrap());
+            assert_eq!(is_authorized(&config, &headers), expected, "Failed for {}: {}", name, value);
+        }
+
+        assert!(!is_authorized(&config, &HeaderMap::new()));
+        assert!(!is_authorized(&PostmarkWebhookConfig::default(), &HeaderMap::new()));
+    }
+}
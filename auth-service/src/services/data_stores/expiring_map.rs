@@ -0,0 +1,216 @@
+use std::collections::{BTreeSet, HashMap};
+use std::time::Duration;
+
+// Follows paused time in tests
+use tokio::time::Instant;
+
+// Entries with their own TTL, shared by the in-memory stores. Expired entries are
+// invisible right away and dropped by `evict_expired`, or when room is needed.
+// Once full, `insert` drops the entry closest to expiry to make room, while
+// `try_insert` refuses the new entry.
+pub(crate) struct ExpiringMap<V> {
+    entries: HashMap<String, (V, Instant)>,
+    // Keys ordered by when they expire, soonest first
+    expiries: BTreeSet<(Instant, String)>,
+    capacity: usize,
+}
+
+impl<V> ExpiringMap<V> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            expiries: BTreeSet::new(),
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&V> {
+        self.entries
+            .get(key)
+            .filter(|(_, expires_at)| *expires_at > Instant::now())
+            .map(|(value, _)| value)
+    }
+
+    // Changing the value keeps its TTL
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut V> {
+        self.entries
+            .get_mut(key)
+            .filter(|(_, expires_at)| *expires_at > Instant::now())
+            .map(|(value, _)| value)
+    }
+
+    // Unexpired entries in no particular order
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        let now = Instant::now();
+
+        self.entries
+            .values()
+            .filter(move |(_, expires_at)| *expires_at > now)
+            .map(|(value, _)| value)
+    }
+
+    // Replaces the entry of `key` along with its TTL
+    pub fn insert(&mut self, key: String, value: V, ttl: Duration) {
+        self.remove(&key);
+
+        if self.len() >= self.capacity {
+            self.evict_expired();
+        }
+        if self.len() >= self.capacity {
+            tracing::warn!(capacity = self.capacity, "In-memory store is full, evicting the entry closest to expiry");
+            if let Some((_, key)) = self.expiries.pop_first() {
+                self.entries.remove(&key);
+            }
+        }
+
+        // A TTL too long for `Instant` never expires in practice
+        let now = Instant::now();
+        let expires_at = now.checked_add(ttl).unwrap_or(now + Duration::from_secs(100 * 365 * 86400));
+        self.expiries.insert((expires_at, key.clone()));
+        self.entries.insert(key, (value, expires_at));
+    }
+
+    // Like `insert`, but never drops a live entry. Returns false and leaves the
+    // map as it was when it is full of them.
+    pub fn try_insert(&mut self, key: String, value: V, ttl: Duration) -> bool {
+        if !self.entries.contains_key(&key) && self.len() >= self.capacity {
+            self.evict_expired();
+
+            if self.len() >= self.capacity {
+                return false;
+            }
+        }
+
+        self.insert(key, value, ttl);
+        true
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<V> {
+        let (value, expires_at) = self.entries.remove(key)?;
+        self.expiries.remove(&(expires_at, key.to_owned()));
+
+        Some(value)
+    }
+
+    // Returns how many entries were dropped
+    pub fn evict_expired(&mut self) -> usize {
+        let now = Instant::now();
+        let mut count = 0;
+
+        while let Some((expires_at, _)) = self.expiries.first() {
+            if *expires_at > now {
+                break;
+            }
+
+            let (_, key) = self.expiries.pop_first().expect("first entry exists");
+            self.entries.remove(&key);
+            count += 1;
+        }
+
+        count
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn test_expired_entries_are_invisible() {
+        let mut map = ExpiringMap::new(10);
+
+        map.insert("live".to_owned(), 1, MINUTE);
+        map.insert("expired".to_owned(), 2, Duration::ZERO);
+
+        assert_eq!(map.get("live"), Some(&1));
+        assert_eq!(map.get("expired"), None);
+        assert_eq!(map.values().collect::<Vec<_>>(), [&1]);
+        // Still held until evicted
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn test_evict_expired() {
+        let mut map = ExpiringMap::new(10);
+        map.insert("live".to_owned(), 1, MINUTE);
+        map.insert("expired".to_owned(), 2, Duration::ZERO);
+
+        assert_eq!(map.evict_expired(), 1);
+        assert_eq!(map.evict_expired(), 0);
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.get("live"), Some(&1));
+    }
+
+    #[test]
+    fn test_insert_replaces_value_and_ttl() {
+        let mut map = ExpiringMap::new(10);
+        map.insert("key".to_owned(), 1, Duration::ZERO);
+
+        map.insert("key".to_owned(), 2, MINUTE);
+
+        assert_eq!(map.get("key"), Some(&2));
+        assert_eq!(map.evict_expired(), 0);
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn test_full_map_evicts_expired_entries_first() {
+        let mut map = ExpiringMap::new(2);
+        map.insert("expired".to_owned(), 1, Duration::ZERO);
+        map.insert("live".to_owned(), 2, MINUTE);
+
+        map.insert("new".to_owned(), 3, MINUTE);
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get("live"), Some(&2));
+        assert_eq!(map.get("new"), Some(&3));
+    }
+
+    #[test]
+    fn test_full_map_evicts_entry_closest_to_expiry() {
+        let mut map = ExpiringMap::new(2);
+        map.insert("later".to_owned(), 1, MINUTE * 2);
+        map.insert("sooner".to_owned(), 2, MINUTE);
+
+        map.insert("new".to_owned(), 3, MINUTE);
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get("sooner"), None);
+        assert_eq!(map.get("later"), Some(&1));
+        assert_eq!(map.get("new"), Some(&3));
+    }
+
+    #[test]
+    fn test_try_insert_keeps_live_entries_when_full() {
+        let mut map = ExpiringMap::new(2);
+        map.insert("expired".to_owned(), 1, Duration::ZERO);
+        map.insert("live".to_owned(), 2, MINUTE);
+
+        assert!(map.try_insert("new".to_owned(), 3, MINUTE));
+        assert!(!map.try_insert("other".to_owned(), 4, MINUTE * 2));
+        // Replacing an entry needs no room
+        assert!(map.try_insert("live".to_owned(), 5, MINUTE));
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get("live"), Some(&5));
+        assert_eq!(map.get("new"), Some(&3));
+        assert_eq!(map.get("other"), None);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut map = ExpiringMap::new(10);
+        map.insert("key".to_owned(), 1, MINUTE);
+
+        assert_eq!(map.remove("key"), Some(1));
+        assert_eq!(map.remove("key"), None);
+        assert_eq!(map.evict_expired(), 0);
+        assert_eq!(map.len(), 0);
+    }
+}
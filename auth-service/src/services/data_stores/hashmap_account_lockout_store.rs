@@ -0,0 +1,201 @@
+use crate::domain::{
+    data_stores::{AccountLockoutStore, AccountLockoutStoreError},
+    Email, FailedLoginOutcome, LockoutPolicy, LockoutStatus,
+};
+use crate::services::memory_purger::ExpiringStore;
+use crate::utils::constants::defaults;
+
+use tokio::sync::RwLock;
+use tokio::time::Instant;
+
+use super::expiring_map::ExpiringMap;
+
+// Accounts are kept until their failures are forgotten or their lock ends,
+// whichever comes later
+pub struct HashmapAccountLockoutStore {
+    accounts: RwLock<ExpiringMap<AccountFailures>>,
+}
+
+struct AccountFailures {
+    failed_attempts: u32,
+    lockouts: u32,
+    locked_until: Option<Instant>,
+    last_failure_at: Instant,
+}
+
+impl HashmapAccountLockoutStore {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            accounts: RwLock::new(ExpiringMap::new(capacity)),
+        }
+    }
+}
+
+impl Default for HashmapAccountLockoutStore {
+    fn default() -> Self {
+        Self::new(defaults::MEMORY_STORE_CAPACITY)
+    }
+}
+
+#[async_trait::async_trait]
+impl AccountLockoutStore for HashmapAccountLockoutStore {
+    async fn status(&self, email: &Email) -> Result<LockoutStatus, AccountLockoutStoreError> {
+        let now = Instant::now();
+
+        match self.accounts.read().await.get(email.as_ref()).and_then(|account| account.locked_until) {
+            Some(locked_until) if locked_until > now => Ok(LockoutStatus::Locked { retry_after: locked_until - now }),
+            _ => Ok(LockoutStatus::Unlocked),
+        }
+    }
+
+    async fn record_failure(&self, email: &Email, policy: &LockoutPolicy) -> Result<FailedLoginOutcome, AccountLockoutStoreError> {
+        let now = Instant::now();
+        let key = email.as_ref().to_owned();
+
+        let mut accounts = self.accounts.write().await;
+        let mut account = accounts
+            .remove(&key)
+            .unwrap_or(AccountFailures { failed_attempts: 0, lockouts: 0, locked_until: None, last_failure_at: now });
+
+        if now.duration_since(account.last_failure_at) > policy.reset_after {
+            account.failed_attempts = 0;
+            account.lockouts = 0;
+        }
+
+        account.failed_attempts += 1;
+        account.last_failure_at = now;
+
+        let outcome = if account.failed_attempts < policy.threshold {
+            FailedLoginOutcome::Counted { failed_attempts: account.failed_attempts }
+        } else {
+            account.failed_attempts = 0;
+            account.lockouts += 1;
+            let lock_duration = policy.lockout_duration(account.lockouts);
+            account.locked_until = Some(now + lock_duration);
+
+            FailedLoginOutcome::Locked { lock_duration }
+        };
+
+        let locked_for = account.locked_until.map(|locked_until| locked_until.saturating_duration_since(now));
+        let ttl = locked_for.unwrap_or_default().max(policy.reset_after);
+        accounts.insert(key, account, ttl);
+
+        Ok(outcome)
+    }
+
+    async fn reset(&self, email: &Email) -> Result<(), AccountLockoutStoreError> {
+        self.accounts.write().await.remove(email.as_ref());
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl ExpiringStore for HashmapAccountLockoutStore {
+    async fn evict_expired(&self) -> usize {
+        self.accounts.write().await.evict_expired()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::SecretString;
+    use std::time::Duration;
+
+    fn email() -> Email {
+        Email::parse(SecretString::new("test@example.com".to_owned().into_boxed_str())).unwrap()
+    }
+
+    fn policy() -> LockoutPolicy {
+        LockoutPolicy::new(3, Duration::from_secs(60), Duration::from_secs(600), Duration::from_secs(3600))
+    }
+
+    #[tokio::test]
+    async fn test_record_failure_locks_at_threshold() {
+        let store = HashmapAccountLockoutStore::default();
+        let email = email();
+
+        assert_eq!(store.record_failure(&email, &policy()).await, Ok(FailedLoginOutcome::Counted { failed_attempts: 1 }));
+        assert_eq!(store.record_failure(&email, &policy()).await, Ok(FailedLoginOutcome::Counted { failed_attempts: 2 }));
+        assert_eq!(store.status(&email).await, Ok(LockoutStatus::Unlocked));
+
+        assert_eq!(
+            store.record_failure(&email, &policy()).await,
+            Ok(FailedLoginOutcome::Locked { lock_duration: Duration::from_secs(60) })
+        );
+        assert!(matches!(store.status(&email).await, Ok(LockoutStatus::Locked { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_record_failure_locks_progressively_longer() {
+        let store = HashmapAccountLockoutStore::default();
+        let email = email();
+
+        for _ in 0..3 {
+            store.record_failure(&email, &policy()).await.unwrap();
+        }
+        for _ in 0..2 {
+            store.record_failure(&email, &policy()).await.unwrap();
+        }
+
+        assert_eq!(
+            store.record_failure(&email, &policy()).await,
+            Ok(FailedLoginOutcome::Locked { lock_duration: Duration::from_secs(120) })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lock_expires() {
+        let store = HashmapAccountLockoutStore::default();
+        let email = email();
+        let policy = LockoutPolicy::new(1, Duration::from_millis(50), Duration::from_secs(1), Duration::from_secs(3600));
+
+        store.record_failure(&email, &policy).await.unwrap();
+        assert!(matches!(store.status(&email).await, Ok(LockoutStatus::Locked { .. })));
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+
+        assert_eq!(store.status(&email).await, Ok(LockoutStatus::Unlocked));
+    }
+
+    #[tokio::test]
+    async fn test_failures_are_forgotten_after_reset_after() {
+        let store = HashmapAccountLockoutStore::default();
+        let email = email();
+        let policy = LockoutPolicy::new(2, Duration::from_secs(60), Duration::from_secs(600), Duration::from_millis(50));
+
+        store.record_failure(&email, &policy).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(60)).await;
+
+        assert_eq!(store.record_failure(&email, &policy).await, Ok(FailedLoginOutcome::Counted { failed_attempts: 1 }));
+    }
+
+    #[tokio::test]
+    async fn test_evicts_accounts_once_failures_are_forgotten_and_unlocked() {
+        let store = HashmapAccountLockoutStore::default();
+        let counted = email();
+        let locked = Email::parse(SecretString::new("locked@example.com".to_owned().into_boxed_str())).unwrap();
+
+        store.record_failure(&counted, &LockoutPolicy::new(3, Duration::from_secs(60), Duration::from_secs(600), Duration::from_millis(50))).await.unwrap();
+        store.record_failure(&locked, &LockoutPolicy::new(1, Duration::from_secs(60), Duration::from_secs(600), Duration::from_millis(50))).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(60)).await;
+
+        // The lock outlives `reset_after`
+        assert_eq!(store.evict_expired().await, 1);
+        assert!(matches!(store.status(&locked).await, Ok(LockoutStatus::Locked { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_reset_unlocks() {
+        let store = HashmapAccountLockoutStore::default();
+        let email = email();
+
+        for _ in 0..3 {
+            store.record_failure(&email, &policy()).await.unwrap();
+        }
+        store.reset(&email).await.unwrap();
+
+        assert_eq!(store.status(&email).await, Ok(LockoutStatus::Unlocked));
+        assert_eq!(store.record_failure(&email, &policy()).await, Ok(FailedLoginOutcome::Counted { failed_attempts: 1 }));
+    }
+}
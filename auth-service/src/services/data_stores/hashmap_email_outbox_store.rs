@@ -0,0 +1,219 @@
+use crate::domain::{
+    data_stores::{EmailOutboxStore, EmailOutboxStoreError},
+    OutboxEmail,
+};
+use crate::services::memory_purger::ExpiringStore;
+use crate::utils::constants::defaults;
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+pub struct HashmapEmailOutboxStore {
+    emails: RwLock<HashMap<Uuid, Entry>>,
+    // Age at which sent and dead-lettered emails are dropped
+    retention: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutboxStatus {
+    Pending,
+    Sent,
+    Dead,
+}
+
+struct Entry {
+    email: OutboxEmail,
+    status: OutboxStatus,
+    next_attempt_at: Instant,
+    last_error: Option<String>,
+    // When the email was sent or dead-lettered
+    finished_at: Option<Instant>,
+}
+
+impl Entry {
+    // Login codes and links don't outlive the delivery
+    fn finish(&mut self, status: OutboxStatus) {
+        self.status = status;
+        self.finished_at = Some(Instant::now());
+        self.email.html_body.clear();
+        self.email.text_body.clear();
+    }
+}
+
+impl HashmapEmailOutboxStore {
+    pub fn new(retention: Duration) -> Self {
+        Self {
+            emails: RwLock::new(HashMap::new()),
+            retention,
+        }
+    }
+
+    pub async fn ids(&self) -> Vec<Uuid> {
+        self.emails.read().await.keys().copied().collect()
+    }
+
+    // Lets tests check what happened to an email
+    pub async fn status(&self, id: &Uuid) -> Option<(OutboxStatus, u32, Option<String>)> {
+        self.emails.read().await
+            .get(id)
+            .map(|entry| (entry.status, entry.email.attempts, entry.last_error.clone()))
+    }
+
+    async fn update_entry(&self, id: &Uuid, update: impl FnOnce(&mut Entry)) -> Result<(), EmailOutboxStoreError> {
+        let mut emails = self.emails.write().await;
+        let entry = emails.get_mut(id).ok_or(EmailOutboxStoreError::EmailNotFound)?;
+        update(entry);
+        Ok(())
+    }
+}
+
+impl Default for HashmapEmailOutboxStore {
+    fn default() -> Self {
+        Self::new(defaults::EMAIL_OUTBOX_RETENTION)
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailOutboxStore for HashmapEmailOutboxStore {
+    async fn enqueue(&self, email: OutboxEmail) -> Result<(), EmailOutboxStoreError> {
+        self.emails.write().await.insert(email.id, Entry {
+            email,
+            status: OutboxStatus::Pending,
+            next_attempt_at: Instant::now(),
+            last_error: None,
+            finished_at: None,
+        });
+
+        Ok(())
+    }
+
+    async fn claim_next(&self, lease: Duration) -> Result<Option<OutboxEmail>, EmailOutboxStoreError> {
+        let now = Instant::now();
+
+        let mut emails = self.emails.write().await;
+        let claimed = emails
+            .values_mut()
+            .filter(|entry| entry.status == OutboxStatus::Pending && entry.next_attempt_at <= now)
+            .min_by_key(|entry| entry.next_attempt_at)
+            .map(|entry| {
+                entry.email.attempts += 1;
+                entry.next_attempt_at = now + lease;
+                entry.email.clone()
+            });
+
+        Ok(claimed)
+    }
+
+    async fn mark_sent(&self, id: Uuid) -> Result<(), EmailOutboxStoreError> {
+        self.update_entry(&id, |entry| {
+            entry.finish(OutboxStatus::Sent);
+            entry.last_error = None;
+        }).await
+    }
+
+    async fn schedule_retry(&self, id: Uuid, error: &str, retry_in: Duration) -> Result<(), EmailOutboxStoreError> {
+        self.update_entry(&id, |entry| {
+            entry.next_attempt_at = Instant::now() + retry_in;
+            entry.last_error = Some(error.to_owned());
+        }).await
+    }
+
+    async fn dead_letter(&self, id: Uuid, error: &str) -> Result<(), EmailOutboxStoreError> {
+        self.update_entry(&id, |entry| {
+            entry.finish(OutboxStatus::Dead);
+            entry.last_error = Some(error.to_owned());
+        }).await
+    }
+}
+
+#[async_trait::async_trait]
+impl ExpiringStore for HashmapEmailOutboxStore {
+    async fn evict_expired(&self) -> usize {
+        let now = Instant::now();
+
+        let mut emails = self.emails.write().await;
+        let before = emails.len();
+        emails.retain(|_, entry| entry.finished_at.is_none_or(|finished_at| finished_at + self.retention > now));
+
+        before - emails.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Email;
+    use secrecy::SecretString;
+
+    fn outbox_email(text_body: &str) -> OutboxEmail {
+        let recipient = Email::parse(SecretString::new("user@example.com".to_owned().into_boxed_str())).unwrap();
+        OutboxEmail::new(recipient, "Subject", "<p>Body</p>", text_body)
+    }
+
+    #[tokio::test]
+    async fn test_claim_next_hides_claimed_emails() {
+        let store = HashmapEmailOutboxStore::default();
+        store.enqueue(outbox_email("First")).await.unwrap();
+        store.enqueue(outbox_email("Second")).await.unwrap();
+
+        let first = store.claim_next(Duration::from_secs(60)).await.unwrap().unwrap();
+        assert_eq!(first.attempts, 1);
+
+        let second = store.claim_next(Duration::from_secs(60)).await.unwrap().unwrap();
+        assert_ne!(first.id, second.id);
+
+        assert!(store.claim_next(Duration::from_secs(60)).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_claim_next_returns_email_after_lease_expires() {
+        let store = HashmapEmailOutboxStore::default();
+        store.enqueue(outbox_email("Body")).await.unwrap();
+
+        store.claim_next(Duration::ZERO).await.unwrap();
+        let claimed = store.claim_next(Duration::ZERO).await.unwrap().unwrap();
+
+        assert_eq!(claimed.attempts, 2);
+    }
+
+    #[tokio::test]
+    async fn test_sent_and_dead_emails_are_not_claimed() {
+        let store = HashmapEmailOutboxStore::default();
+        let sent = outbox_email("Sent");
+        let dead = outbox_email("Dead");
+        store.enqueue(sent.clone()).await.unwrap();
+        store.enqueue(dead.clone()).await.unwrap();
+
+        store.mark_sent(sent.id).await.unwrap();
+        store.dead_letter(dead.id, "rejected").await.unwrap();
+
+        assert!(store.claim_next(Duration::ZERO).await.unwrap().is_none());
+        assert_eq!(store.status(&dead.id).await, Some((OutboxStatus::Dead, 0, Some("rejected".to_owned()))));
+    }
+
+    #[tokio::test]
+    async fn test_finished_emails_lose_their_bodies_and_expire() {
+        let store = HashmapEmailOutboxStore::new(Duration::ZERO);
+        let sent = outbox_email("Your code is 123456");
+        let pending = outbox_email("Pending");
+        store.enqueue(sent.clone()).await.unwrap();
+        store.enqueue(pending.clone()).await.unwrap();
+
+        store.mark_sent(sent.id).await.unwrap();
+        let body = store.emails.read().await[&sent.id].email.text_body.clone();
+        assert!(body.is_empty());
+
+        assert_eq!(store.evict_expired().await, 1);
+        assert_eq!(store.ids().await, vec![pending.id]);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_email() {
+        let store = HashmapEmailOutboxStore::default();
+
+        let result = store.mark_sent(Uuid::new_v4()).await;
+        assert_eq!(result, Err(EmailOutboxStoreError::EmailNotFound));
+    }
+}
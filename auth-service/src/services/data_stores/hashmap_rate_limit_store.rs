@@ -0,0 +1,144 @@
+use crate::domain::{
+    data_stores::{RateLimitStore, RateLimitStoreError},
+    RateLimitDecision, RateLimitPolicy,
+};
+use crate::services::memory_purger::ExpiringStore;
+use crate::utils::constants::defaults;
+
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+use super::expiring_map::ExpiringMap;
+
+// A bucket is only kept until it has refilled, after which it is no different
+// from a new one. Once full, the store drops the bucket closest to refilled.
+pub struct HashmapRateLimitStore {
+    buckets: Mutex<ExpiringMap<Bucket>>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl HashmapRateLimitStore {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            buckets: Mutex::new(ExpiringMap::new(capacity)),
+        }
+    }
+}
+
+impl Default for HashmapRateLimitStore {
+    fn default() -> Self {
+        Self::new(defaults::MEMORY_STORE_CAPACITY)
+    }
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for HashmapRateLimitStore {
+    async fn check(&self, key: &str, policy: &RateLimitPolicy) -> Result<RateLimitDecision, RateLimitStoreError> {
+        let now = Instant::now();
+        let capacity = policy.capacity as f64;
+        let interval = policy.refill_interval.as_secs_f64();
+
+        let mut buckets = self.buckets.lock().await;
+        let mut bucket = buckets
+            .remove(key)
+            .unwrap_or(Bucket { tokens: capacity, updated_at: now });
+
+        // Add back the tokens earned since the bucket was last used
+        let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed / interval).min(capacity);
+        bucket.updated_at = now;
+
+        let decision = if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            RateLimitDecision::Allowed
+        } else {
+            RateLimitDecision::Limited { retry_after: Duration::from_secs_f64((1.0 - bucket.tokens) * interval) }
+        };
+
+        let refilled_in = Duration::from_secs_f64((capacity - bucket.tokens) * interval);
+        buckets.insert(key.to_owned(), bucket, refilled_in);
+
+        Ok(decision)
+    }
+}
+
+#[async_trait::async_trait]
+impl ExpiringStore for HashmapRateLimitStore {
+    async fn evict_expired(&self) -> usize {
+        self.buckets.lock().await.evict_expired()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_check_allows_up_to_capacity() {
+        let store = HashmapRateLimitStore::default();
+        let policy = RateLimitPolicy::new(2, Duration::from_secs(60));
+
+        assert_eq!(store.check("key", &policy).await, Ok(RateLimitDecision::Allowed));
+        assert_eq!(store.check("key", &policy).await, Ok(RateLimitDecision::Allowed));
+
+        let decision = store.check("key", &policy).await.unwrap();
+        match decision {
+            RateLimitDecision::Limited { retry_after } => {
+                assert!(retry_after > Duration::from_secs(59));
+                assert!(retry_after <= Duration::from_secs(60));
+            },
+            RateLimitDecision::Allowed => panic!("expected request to be limited"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_keys_are_independent() {
+        let store = HashmapRateLimitStore::default();
+        let policy = RateLimitPolicy::new(1, Duration::from_secs(60));
+
+        assert_eq!(store.check("key1", &policy).await, Ok(RateLimitDecision::Allowed));
+        assert_eq!(store.check("key2", &policy).await, Ok(RateLimitDecision::Allowed));
+        assert_ne!(store.check("key1", &policy).await, Ok(RateLimitDecision::Allowed));
+    }
+
+    #[tokio::test]
+    async fn test_evicts_refilled_buckets() {
+        let store = HashmapRateLimitStore::default();
+
+        store.check("slow", &RateLimitPolicy::new(2, Duration::from_secs(60))).await.unwrap();
+        store.check("fast", &RateLimitPolicy::new(2, Duration::from_millis(20))).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(30)).await;
+
+        assert_eq!(store.evict_expired().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_full_store_drops_bucket_closest_to_refilled() {
+        let store = HashmapRateLimitStore::new(1);
+        let policy = RateLimitPolicy::new(1, Duration::from_secs(60));
+
+        assert_eq!(store.check("key1", &policy).await, Ok(RateLimitDecision::Allowed));
+        assert_eq!(store.check("key2", &policy).await, Ok(RateLimitDecision::Allowed));
+
+        // key1 made room for key2
+        assert_eq!(store.check("key1", &policy).await, Ok(RateLimitDecision::Allowed));
+    }
+
+    #[tokio::test]
+    async fn test_check_refills_over_time() {
+        let store = HashmapRateLimitStore::default();
+        let policy = RateLimitPolicy::new(1, Duration::from_millis(50));
+
+        assert_eq!(store.check("key", &policy).await, Ok(RateLimitDecision::Allowed));
+        assert_ne!(store.check("key", &policy).await, Ok(RateLimitDecision::Allowed));
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+
+        assert_eq!(store.check("key", &policy).await, Ok(RateLimitDecision::Allowed));
+    }
+}
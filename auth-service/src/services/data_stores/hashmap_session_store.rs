@@ -0,0 +1,173 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use tokio::sync::RwLock;
+
+use crate::domain::{
+    data_stores::{SessionStore, SessionStoreError},
+    Email,
+    Session,
+    SessionId,
+};
+use crate::services::memory_purger::ExpiringStore;
+use crate::utils::constants::defaults;
+
+use super::expiring_map::ExpiringMap;
+
+pub struct HashmapSessionStore {
+    sessions: RwLock<ExpiringMap<Session>>,
+}
+
+impl HashmapSessionStore {
+    pub fn new(capacity: usize) -> Self {
+        Self { sessions: RwLock::new(ExpiringMap::new(capacity)) }
+    }
+}
+
+impl Default for HashmapSessionStore {
+    fn default() -> Self {
+        Self::new(defaults::MEMORY_STORE_CAPACITY)
+    }
+}
+
+#[async_trait::async_trait]
+impl SessionStore for HashmapSessionStore {
+    async fn add_session(&self, session: Session, ttl: Duration) -> Result<(), SessionStoreError> {
+        self.sessions.write().await.insert(session.id.as_ref().to_owned(), session, ttl);
+
+        Ok(())
+    }
+
+    async fn get_session(&self, id: &SessionId) -> Result<Session, SessionStoreError> {
+        self.sessions.read().await
+            .get(id.as_ref())
+            .cloned()
+            .ok_or(SessionStoreError::SessionNotFound)
+    }
+
+    async fn touch_session(&self, id: &SessionId, last_seen: DateTime<Utc>, ttl: Duration) -> Result<(), SessionStoreError> {
+        let mut session = self.get_session(id).await?;
+        session.last_seen = last_seen;
+
+        self.add_session(session, ttl).await
+    }
+
+    async fn remove_session(&self, id: &SessionId) -> Result<(), SessionStoreError> {
+        self.sessions.write().await.remove(id.as_ref());
+
+        Ok(())
+    }
+
+    async fn list_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError> {
+        Ok(self.sessions.read().await
+            .values()
+            .filter(|session| session.email == *email)
+            .cloned()
+            .collect())
+    }
+}
+
+#[async_trait::async_trait]
+impl ExpiringStore for HashmapSessionStore {
+    async fn evict_expired(&self) -> usize {
+        self.sessions.write().await.evict_expired()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::SecretString;
+
+    fn email(email: &str) -> Email {
+        Email::parse(SecretString::new(email.to_owned().into_boxed_str())).unwrap()
+    }
+
+    fn session() -> Session {
+        Session::new(email("test@example.com"), Some("127.0.0.1".parse().unwrap()), Some("curl/8.0".to_owned()))
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_session() {
+        let store = HashmapSessionStore::default();
+        let session = session();
+
+        store.add_session(session.clone(), Duration::from_secs(60)).await.unwrap();
+
+        assert_eq!(store.get_session(&session.id).await, Ok(session));
+    }
+
+    #[tokio::test]
+    async fn test_get_unknown_session() {
+        let store = HashmapSessionStore::default();
+
+        assert_eq!(store.get_session(&SessionId::default()).await, Err(SessionStoreError::SessionNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_expired_session_is_gone() {
+        let store = HashmapSessionStore::default();
+        let session = session();
+
+        store.add_session(session.clone(), Duration::ZERO).await.unwrap();
+
+        assert_eq!(store.get_session(&session.id).await, Err(SessionStoreError::SessionNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_expired_session_is_evicted() {
+        let store = HashmapSessionStore::default();
+        store.add_session(session(), Duration::ZERO).await.unwrap();
+        store.add_session(session(), Duration::from_secs(60)).await.unwrap();
+
+        assert_eq!(store.evict_expired().await, 1);
+        assert_eq!(store.sessions.read().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_touch_session() {
+        let store = HashmapSessionStore::default();
+        let session = session();
+        store.add_session(session.clone(), Duration::from_secs(60)).await.unwrap();
+
+        let last_seen = session.created_at + chrono::Duration::seconds(30);
+        store.touch_session(&session.id, last_seen, Duration::from_secs(60)).await.unwrap();
+
+        assert_eq!(store.get_session(&session.id).await.unwrap().last_seen, last_seen);
+    }
+
+    #[tokio::test]
+    async fn test_remove_session() {
+        let store = HashmapSessionStore::default();
+        let session = session();
+        store.add_session(session.clone(), Duration::from_secs(60)).await.unwrap();
+
+        store.remove_session(&session.id).await.unwrap();
+
+        assert_eq!(store.get_session(&session.id).await, Err(SessionStoreError::SessionNotFound));
+        assert_eq!(store.touch_session(&session.id, Utc::now(), Duration::from_secs(60)).await, Err(SessionStoreError::SessionNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_list_sessions_of_user() {
+        let store = HashmapSessionStore::default();
+        let first = session();
+        let second = session();
+        let expired = session();
+        let other_user = Session::new(email("other@example.com"), None, None);
+        store.add_session(first.clone(), Duration::from_secs(60)).await.unwrap();
+        store.add_session(second.clone(), Duration::from_secs(60)).await.unwrap();
+        store.add_session(expired, Duration::ZERO).await.unwrap();
+        store.add_session(other_user, Duration::from_secs(60)).await.unwrap();
+
+        let mut ids: Vec<String> = store.list_sessions(&email("test@example.com")).await.unwrap()
+            .into_iter()
+            .map(|session| session.id.as_ref().to_owned())
+            .collect();
+        ids.sort();
+        let mut expected = vec![first.id.as_ref().to_owned(), second.id.as_ref().to_owned()];
+        expected.sort();
+
+        assert_eq!(ids, expected);
+    }
+}
@@ -0,0 +1,167 @@
+use crate::{
+    domain::{Email, LoginAttemptId, TwoFACode, TwoFACodePurpose, data_stores::TwoFACodeStore, data_stores::TwoFACodeStoreError},
+    services::memory_purger::ExpiringStore,
+    utils::{auth::TWO_FA_CODE_TTL_SECONDS, constants::defaults},
+};
+
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+use super::expiring_map::ExpiringMap;
+
+// Codes go stale after the same time as in the Redis store
+pub struct HashmapTwoFACodeStore {
+    codes: RwLock<ExpiringMap<StoredCode>>,
+    ttl: Duration,
+}
+
+struct StoredCode {
+    login_attempt_id: LoginAttemptId,
+    code: TwoFACode,
+    failed_attempts: u32,
+}
+
+impl HashmapTwoFACodeStore {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            codes: RwLock::new(ExpiringMap::new(capacity)),
+            ttl: Duration::from_secs(TWO_FA_CODE_TTL_SECONDS as u64),
+        }
+    }
+
+    pub fn with_ttl(self, ttl: Duration) -> Self {
+        Self { ttl, ..self }
+    }
+}
+
+impl Default for HashmapTwoFACodeStore {
+    fn default() -> Self {
+        Self::new(defaults::MEMORY_STORE_CAPACITY)
+    }
+}
+
+#[async_trait::async_trait]
+impl TwoFACodeStore for HashmapTwoFACodeStore {
+    async fn add_code(
+        &self,
+        email: Email,
+        purpose: TwoFACodePurpose,
+        login_attempt_id: LoginAttemptId,
+        code: TwoFACode,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let stored = StoredCode { login_attempt_id, code, failed_attempts: 0 };
+        self.codes.write().await.insert(get_key(&email, purpose), stored, self.ttl);
+
+        Ok(())
+    }
+
+    // Removing a code that's gone already is fine, like in the other stores
+    async fn remove_code(&self, email: &Email, purpose: TwoFACodePurpose) -> Result<(), TwoFACodeStoreError> {
+        self.codes.write().await.remove(&get_key(email, purpose));
+
+        Ok(())
+    }
+
+    async fn get_code(&self, email: &Email, purpose: TwoFACodePurpose) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
+        match self.codes.read().await.get(&get_key(email, purpose)) {
+            Some(stored) => Ok((stored.login_attempt_id.clone(), stored.code.clone())),
+            None => Err(TwoFACodeStoreError::LoginAttempIdNotFound),
+        }
+    }
+
+    async fn record_failed_attempt(&self, email: &Email, purpose: TwoFACodePurpose) -> Result<u32, TwoFACodeStoreError> {
+        match self.codes.write().await.get_mut(&get_key(email, purpose)) {
+            Some(stored) => {
+                stored.failed_attempts += 1;
+                Ok(stored.failed_attempts)
+            }
+            None => Err(TwoFACodeStoreError::LoginAttempIdNotFound),
+        }
+    }
+}
+
+fn get_key(email: &Email, purpose: TwoFACodePurpose) -> String {
+    format!("{}:{}", purpose.as_str(), email.as_ref())
+}
+
+#[async_trait::async_trait]
+impl ExpiringStore for HashmapTwoFACodeStore {
+    async fn evict_expired(&self) -> usize {
+        self.codes.write().await.evict_expired()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::SecretString;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_add_code() {
+        let two_fa_codes = HashmapTwoFACodeStore::default();
+
+        let email_secret = SecretString::new("test@example.com".to_owned().into_boxed_str());
+        let email = Email::parse(email_secret).unwrap();
+        let login_attempt_id = LoginAttemptId::default();
+        let code = TwoFACode::default();
+
+
+        let result = two_fa_codes.add_code(email.clone(), TwoFACodePurpose::Login, login_attempt_id, code).await;
+
+        assert!(result.is_ok());
+        assert_eq!(two_fa_codes.codes.read().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_remove_code() {
+        let two_fa_codes = HashmapTwoFACodeStore::default();
+
+        let email_secret = SecretString::new("test@example.com".to_owned().into_boxed_str());
+        let email = Email::parse(email_secret).unwrap();
+        let login_attempt_id = LoginAttemptId::default();
+        let code = TwoFACode::default();
+
+        let result = two_fa_codes.add_code(email.clone(), TwoFACodePurpose::Login, login_attempt_id, code).await;
+
+        assert!(result.is_ok());
+        assert_eq!(two_fa_codes.codes.read().await.len(), 1);
+
+        let result = two_fa_codes.remove_code(&email, TwoFACodePurpose::Login).await;
+        assert!(result.is_ok());
+        assert_eq!(two_fa_codes.codes.read().await.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_get_code() {
+        let two_fa_codes = HashmapTwoFACodeStore::default();
+
+        let email_secret = SecretString::new("test@example.com".to_owned().into_boxed_str());
+        let email = Email::parse(email_secret).unwrap();
+        let login_attempt_id = LoginAttemptId::default();
+        let code = TwoFACode::default();
+
+        let result = two_fa_codes.add_code(email.clone(), TwoFACodePurpose::Login, login_attempt_id.clone(), code.clone()).await;
+
+        assert!(result.is_ok());
+        assert_eq!(two_fa_codes.codes.read().await.len(), 1);
+
+        let (login_attempt_id2, code2) = two_fa_codes.get_code(&email, TwoFACodePurpose::Login).await.unwrap();
+        assert_eq!(login_attempt_id, login_attempt_id2);
+        assert_eq!(code, code2);
+    }
+
+    #[tokio::test]
+    async fn test_expired_code_is_evicted() {
+        let two_fa_codes = HashmapTwoFACodeStore::default().with_ttl(Duration::ZERO);
+
+        let email_secret = SecretString::new("test@example.com".to_owned().into_boxed_str());
+        let email = Email::parse(email_secret).unwrap();
+
+        two_fa_codes.add_code(email.clone(), TwoFACodePurpose::Login, LoginAttemptId::default(), TwoFACode::default()).await.unwrap();
+
+        assert_eq!(two_fa_codes.get_code(&email, TwoFACodePurpose::Login).await.err(), Some(TwoFACodeStoreError::LoginAttempIdNotFound));
+        assert_eq!(two_fa_codes.evict_expired().await, 1);
+        assert_eq!(two_fa_codes.codes.read().await.len(), 0);
+    }
+}
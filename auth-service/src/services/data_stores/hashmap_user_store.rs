@@ -1,32 +1,43 @@
-use crate::domain::{data_stores::UserStore, data_stores::UserStoreError, User, Email};
+use crate::domain::{data_stores::UserStore, data_stores::UserStoreError, User, Email, EmailChangeRevert};
 use color_eyre::eyre::Result;
 use secrecy::SecretString;
 
 use std::collections::HashMap;
+use tokio::sync::RwLock;
 
 
 #[derive(Default)]
 pub struct HashmapUserStore {
-    users: HashMap<Email, User>
+    users: RwLock<HashMap<Email, User>>,
+    // Confirmed email changes by ID. Locked after `users` when both are needed.
+    email_changes: RwLock<HashMap<String, EmailChange>>,
+}
+
+struct EmailChange {
+    from: Email,
+    to: Email,
+    ended: bool,
 }
 
 #[async_trait::async_trait]
 impl UserStore for HashmapUserStore {
-    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError> {
-        let user_exists = self.users.contains_key(&user.email);
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError> {
+        let mut users = self.users.write().await;
+        let user_exists = users.contains_key(&user.email);
         match user_exists {
             true => {
                 Err(UserStoreError::UserAlreadyExists)
             },
             false => {
-                self.users.insert(user.email.clone(), user);
+                users.insert(user.email.clone(), user);
                 Ok(())
             },
         }
     }
 
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
-        let user = self.users.get(email).ok_or(UserStoreError::UserNotFound)?;
+        let users = self.users.read().await;
+        let user = users.get(email).ok_or(UserStoreError::UserNotFound)?;
         Ok(user.clone())
     }
     
@@ -39,6 +50,68 @@ impl UserStore for HashmapUserStore {
             .await
             .map_err(|_| UserStoreError::InvalidCredentials)
     }
+
+    async fn confirm_email_change(&self, change_id: &str, current_email: &Email, new_email: Email) -> Result<(), UserStoreError> {
+        let mut users = self.users.write().await;
+        let mut email_changes = self.email_changes.write().await;
+
+        move_user(&mut users, current_email, new_email.clone())?;
+
+        // The change that gave the account its old address can't be reverted anymore
+        for change in email_changes.values_mut() {
+            if change.to == *current_email {
+                change.ended = true;
+            }
+        }
+
+        let change = EmailChange { from: current_email.clone(), to: new_email, ended: false };
+        email_changes.insert(change_id.to_owned(), change);
+
+        Ok(())
+    }
+
+    async fn revert_email_change(&self, change_id: &str) -> Result<EmailChangeRevert, UserStoreError> {
+        let mut users = self.users.write().await;
+        let mut email_changes = self.email_changes.write().await;
+
+        let Some(change) = email_changes.get_mut(change_id) else {
+            return Ok(EmailChangeRevert::NotConfirmed);
+        };
+        if change.ended {
+            return Ok(EmailChangeRevert::Ended);
+        }
+
+        move_user(&mut users, &change.to, change.from.clone())?;
+        change.ended = true;
+
+        Ok(EmailChangeRevert::Reverted)
+    }
+
+    async fn mark_email_undeliverable(&self, email: &Email) -> Result<(), UserStoreError> {
+        let mut users = self.users.write().await;
+        let user = users.get_mut(email).ok_or(UserStoreError::UserNotFound)?;
+        user.email_undeliverable = true;
+        Ok(())
+    }
+}
+
+fn move_user(users: &mut HashMap<Email, User>, current_email: &Email, new_email: Email) -> Result<(), UserStoreError> {
+    if !users.contains_key(current_email) {
+        return Err(UserStoreError::UserNotFound);
+    }
+
+    // Changing to the current address changes nothing, like in the SQL stores
+    if new_email != *current_email && users.contains_key(&new_email) {
+        return Err(UserStoreError::UserAlreadyExists);
+    }
+
+    let mut user = users.remove(current_email).ok_or(UserStoreError::UserNotFound)?;
+    user.email = new_email.clone();
+    // The new address hasn't bounced yet
+    user.email_undeliverable = false;
+    users.insert(new_email, user);
+
+    Ok(())
 }
 
 #[cfg(test)]
@@ -50,7 +123,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_add_user() {
-        let mut users = HashmapUserStore::default();
+        let users = HashmapUserStore::default();
 
         let email_secret = SecretString::new("text@example.com".to_owned().into_boxed_str());
         let email = Email::parse(email_secret).unwrap();
@@ -69,7 +142,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_get_user() {
-        let mut users = HashmapUserStore::default();
+        let users = HashmapUserStore::default();
 
         let email_secret = SecretString::new("text@example.com".to_owned().into_boxed_str());
         let email = Email::parse(email_secret).unwrap();
@@ -92,7 +165,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_validate_user() {
-        let mut users = HashmapUserStore::default();
+        let users = HashmapUserStore::default();
 
         let email_secret = SecretString::new("text@example.com".to_owned().into_boxed_str());
         let email = Email::parse(email_secret).unwrap();
@@ -116,4 +189,62 @@ mod tests {
         assert_eq!(validate_user2, Err(UserStoreError::InvalidCredentials));
         assert_eq!(validate_user3, Err(UserStoreError::UserNotFound));
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_confirm_email_change() {
+        let users = HashmapUserStore::default();
+
+        let email_secret = SecretString::new("text@example.com".to_owned().into_boxed_str());
+        let email = Email::parse(email_secret).unwrap();
+
+        let password_secret = SecretString::new("1234ABCD".to_owned().into_boxed_str());
+        let password = HashedPassword::parse(password_secret).await.unwrap();
+        let new_user = User::new(email.clone(), password.clone(), false);
+
+        let other_email_secret = SecretString::new("other@example.com".to_owned().into_boxed_str());
+        let other_email = Email::parse(other_email_secret).unwrap();
+        let other_user = User::new(other_email.clone(), password, false);
+
+        let _ = users.add_user(new_user).await;
+        let _ = users.add_user(other_user).await;
+
+        let new_email_secret = SecretString::new("new@example.com".to_owned().into_boxed_str());
+        let new_email = Email::parse(new_email_secret).unwrap();
+
+        let update_email1 = users.confirm_email_change("change1", &email, new_email.clone()).await;
+        let update_email2 = users.confirm_email_change("change2", &email, new_email.clone()).await;
+        let update_email3 = users.confirm_email_change("change3", &new_email, other_email).await;
+
+        assert_eq!(update_email1, Ok(()));
+        assert_eq!(update_email2, Err(UserStoreError::UserNotFound));
+        assert_eq!(update_email3, Err(UserStoreError::UserAlreadyExists));
+
+        assert_eq!(users.get_user(&email).await, Err(UserStoreError::UserNotFound));
+        assert_eq!(users.get_user(&new_email).await.unwrap().email, new_email);
+    }
+
+    #[tokio::test]
+    async fn test_mark_email_undeliverable() {
+        let users = HashmapUserStore::default();
+
+        let email_secret = SecretString::new("text@example.com".to_owned().into_boxed_str());
+        let email = Email::parse(email_secret).unwrap();
+
+        let password_secret = SecretString::new("1234ABCD".to_owned().into_boxed_str());
+        let password = HashedPassword::parse(password_secret).await.unwrap();
+        let _ = users.add_user(User::new(email.clone(), password, false)).await;
+
+        let mark1 = users.mark_email_undeliverable(&email).await;
+        assert_eq!(mark1, Ok(()));
+        assert!(users.get_user(&email).await.unwrap().email_undeliverable);
+
+        // Moving to a new address clears the flag
+        let new_email_secret = SecretString::new("new@example.com".to_owned().into_boxed_str());
+        let new_email = Email::parse(new_email_secret).unwrap();
+        users.confirm_email_change("change1", &email, new_email.clone()).await.unwrap();
+        assert!(!users.get_user(&new_email).await.unwrap().email_undeliverable);
+
+        let mark2 = users.mark_email_undeliverable(&email).await;
+        assert_eq!(mark2, Err(UserStoreError::UserNotFound));
+    }
+}
@@ -1,54 +1,88 @@
 use crate::domain::{data_stores::BannedTokenStore, data_stores::BannedTokenStoreError};
+use crate::services::memory_purger::ExpiringStore;
+use crate::utils::constants::defaults;
+use color_eyre::eyre::eyre;
 use secrecy::{ExposeSecret, SecretString};
 
-use std::collections::HashSet;
+use std::time::Duration;
+use tokio::sync::RwLock;
 
-#[derive(Default)]
+use super::expiring_map::ExpiringMap;
+
+// Tokens are kept until they would have expired anyway, like in the Redis store.
+// Dropping a ban early would let the token back in, so a full store refuses new bans.
 pub struct HashsetBannedTokenStore {
-    tokens: HashSet<String>,
+    tokens: RwLock<ExpiringMap<()>>,
+}
+
+impl HashsetBannedTokenStore {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            tokens: RwLock::new(ExpiringMap::new(capacity)),
+        }
+    }
+}
+
+impl Default for HashsetBannedTokenStore {
+    fn default() -> Self {
+        Self::new(defaults::MEMORY_STORE_CAPACITY)
+    }
 }
 
 #[async_trait::async_trait]
 impl BannedTokenStore for HashsetBannedTokenStore {
-    async fn add_token(&mut self, token: &SecretString) -> Result<(), BannedTokenStoreError> {
-        self.tokens.insert(token.expose_secret().into());
+    async fn add_token(&self, token: &SecretString, ttl: Duration) -> Result<(), BannedTokenStoreError> {
+        if !self.tokens.write().await.try_insert(token.expose_secret().into(), (), ttl) {
+            tracing::error!("Banned token store is full");
+            return Err(BannedTokenStoreError::UnexpectedError(eyre!("banned token store is full")));
+        }
 
         Ok(())
     }
 
     async fn check_token(&self, token: &SecretString) -> Result<bool, BannedTokenStoreError> {        
-        Ok(self.tokens.contains(token.expose_secret()))
+        Ok(self.tokens.read().await.get(token.expose_secret()).is_some())
     }   
 }
 
+#[async_trait::async_trait]
+impl ExpiringStore for HashsetBannedTokenStore {
+    async fn evict_expired(&self) -> usize {
+        self.tokens.write().await.evict_expired()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const MINUTE: Duration = Duration::from_secs(60);
+
     #[tokio::test]
     async fn test_add_token() {
-        let mut banned_tokens = HashsetBannedTokenStore::default();
+        let banned_tokens = HashsetBannedTokenStore::default();
 
         let token1 = SecretString::new("token1".to_owned().into_boxed_str());
         let token2 = SecretString::new("token2".to_owned().into_boxed_str());
 
-        banned_tokens.add_token(&token1).await.unwrap();
-        banned_tokens.add_token(&token2).await.unwrap();
-        banned_tokens.add_token(&token2).await.unwrap();
+        banned_tokens.add_token(&token1, MINUTE).await.unwrap();
+        banned_tokens.add_token(&token2, MINUTE).await.unwrap();
+        banned_tokens.add_token(&token2, MINUTE).await.unwrap();
 
-        assert!(banned_tokens.tokens.contains(token1.expose_secret()));
-        assert!(banned_tokens.tokens.contains(token2.expose_secret()));
+        assert!(banned_tokens.tokens.read().await.get(token1.expose_secret()).is_some());
+        assert!(banned_tokens.tokens.read().await.get(token2.expose_secret()).is_some());
+        assert_eq!(banned_tokens.tokens.read().await.len(), 2);
     }
 
     #[tokio::test]
     async fn test_get_existing_token() {
-        let mut banned_tokens = HashsetBannedTokenStore::default();
+        let banned_tokens = HashsetBannedTokenStore::default();
 
         let token1 = SecretString::new("token1".to_owned().into_boxed_str());
         let token2 = SecretString::new("".to_owned().into_boxed_str());
 
-        banned_tokens.add_token(&token1).await.unwrap();
-        banned_tokens.add_token(&token2).await.unwrap();
+        banned_tokens.add_token(&token1, MINUTE).await.unwrap();
+        banned_tokens.add_token(&token2, MINUTE).await.unwrap();
 
         assert_eq!(banned_tokens.check_token(&token1).await, Ok(true));
         assert_eq!(banned_tokens.check_token(&token2).await, Ok(true));
@@ -64,4 +98,33 @@ mod tests {
         assert_eq!(banned_tokens.check_token(&token1).await, Ok(false));
         assert_eq!(banned_tokens.check_token(&token2).await, Ok(false));
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_full_store_never_forgets_a_live_ban() {
+        let banned_tokens = HashsetBannedTokenStore::new(2);
+        let token = |name: &str| SecretString::new(name.to_owned().into_boxed_str());
+
+        banned_tokens.add_token(&token("expired"), Duration::ZERO).await.unwrap();
+        banned_tokens.add_token(&token("sooner"), MINUTE).await.unwrap();
+        banned_tokens.add_token(&token("later"), MINUTE * 2).await.unwrap();
+
+        let result = banned_tokens.add_token(&token("new"), MINUTE * 3).await;
+        assert!(matches!(result, Err(BannedTokenStoreError::UnexpectedError(_))));
+
+        assert_eq!(banned_tokens.check_token(&token("sooner")).await, Ok(true));
+        assert_eq!(banned_tokens.check_token(&token("later")).await, Ok(true));
+        assert_eq!(banned_tokens.check_token(&token("new")).await, Ok(false));
+    }
+
+    #[tokio::test]
+    async fn test_expired_token_is_evicted() {
+        let banned_tokens = HashsetBannedTokenStore::default();
+        let token = SecretString::new("token1".to_owned().into_boxed_str());
+
+        banned_tokens.add_token(&token, Duration::ZERO).await.unwrap();
+
+        assert_eq!(banned_tokens.check_token(&token).await, Ok(false));
+        assert_eq!(banned_tokens.evict_expired().await, 1);
+        assert_eq!(banned_tokens.tokens.read().await.len(), 0);
+    }
+}
@@ -1,6 +1,39 @@
+mod expiring_map;
 pub mod hashmap_user_store;
 pub mod hashset_banned_token_store;
-pub mod hahsmap_two_fa_code_store;
+pub mod hashmap_two_fa_code_store;
+pub mod hashmap_rate_limit_store;
+pub mod hashmap_email_outbox_store;
+pub mod hashmap_account_lockout_store;
+pub mod hashmap_session_store;
 pub mod postgres_user_store;
+pub mod postgres_email_outbox_store;
+pub mod postgres_account_lockout_store;
+pub mod postgres_banned_token_store;
+pub mod postgres_two_fa_code_store;
+pub mod postgres_session_store;
+pub mod sqlite_user_store;
+pub mod sqlite_banned_token_store;
+pub mod sqlite_two_fa_code_store;
 pub mod redis_banned_token_store;
-pub mod redis_two_fa_code_store;
\ No newline at end of file
+pub mod redis_two_fa_code_store;
+pub mod redis_rate_limit_store;
+pub mod redis_account_lockout_store;
+pub mod redis_session_store;
+// A migrated in-memory database, private to one test
+#[cfg(test)]
+pub(crate) async fn sqlite_test_pool() -> sqlx::SqlitePool {
+    // Every connection to `:memory:` opens its own database
+    let pool = sqlx::sqlite::SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .expect("Failed to open SQLite database");
+
+    sqlx::migrate!("./migrations_sqlite")
+        .run(&pool)
+        .await
+        .expect("Failed to run SQLite migrations");
+
+    pool
+}
@@ -0,0 +1,123 @@
+use sqlx::PgPool;
+use std::time::Duration;
+
+use crate::domain::{
+    data_stores::{AccountLockoutStore, AccountLockoutStoreError},
+    Email, FailedLoginOutcome, LockoutPolicy, LockoutStatus,
+};
+
+#[derive(Debug)]
+pub struct PostgresAccountLockoutStore {
+    pool: PgPool,
+}
+
+impl PostgresAccountLockoutStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl AccountLockoutStore for PostgresAccountLockoutStore {
+    #[tracing::instrument(name = "Getting lockout status from PostgreSQL", skip_all)]
+    async fn status(&self, email: &Email) -> Result<LockoutStatus, AccountLockoutStoreError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT EXTRACT(EPOCH FROM locked_until - now())::FLOAT8 AS "retry_after_secs!"
+            FROM account_lockouts
+            WHERE email = $1 AND locked_until > now()
+            "#,
+            email.as_ref(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AccountLockoutStoreError::UnexpectedError(e.into()))?;
+
+        match row {
+            Some(row) => Ok(LockoutStatus::Locked { retry_after: Duration::from_secs_f64(row.retry_after_secs.max(0.0)) }),
+            None => Ok(LockoutStatus::Unlocked),
+        }
+    }
+
+    #[tracing::instrument(name = "Recording failed login in PostgreSQL", skip_all)]
+    async fn record_failure(&self, email: &Email, policy: &LockoutPolicy) -> Result<FailedLoginOutcome, AccountLockoutStoreError> {
+        let mut transaction = self.pool
+            .begin()
+            .await
+            .map_err(|e| AccountLockoutStoreError::UnexpectedError(e.into()))?;
+
+        // The upsert keeps the row locked until commit, so concurrent failures
+        // for the same account are counted one after the other
+        let row = sqlx::query!(
+            r#"
+            INSERT INTO account_lockouts (email, failed_attempts, last_failure_at)
+            VALUES ($1, 1, now())
+            ON CONFLICT (email) DO UPDATE SET
+                failed_attempts = CASE
+                    WHEN account_lockouts.last_failure_at < now() - make_interval(secs => $2) THEN 1
+                    ELSE account_lockouts.failed_attempts + 1
+                END,
+                lockouts = CASE
+                    WHEN account_lockouts.last_failure_at < now() - make_interval(secs => $2) THEN 0
+                    ELSE account_lockouts.lockouts
+                END,
+                last_failure_at = now()
+            RETURNING failed_attempts, lockouts
+            "#,
+            email.as_ref(),
+            policy.reset_after.as_secs_f64(),
+        )
+        .fetch_one(&mut *transaction)
+        .await
+        .map_err(|e| AccountLockoutStoreError::UnexpectedError(e.into()))?;
+
+        let failed_attempts = row.failed_attempts as u32;
+
+        if failed_attempts < policy.threshold {
+            transaction.commit()
+                .await
+                .map_err(|e| AccountLockoutStoreError::UnexpectedError(e.into()))?;
+
+            return Ok(FailedLoginOutcome::Counted { failed_attempts });
+        }
+
+        let lockouts = row.lockouts as u32 + 1;
+        let lock_duration = policy.lockout_duration(lockouts);
+
+        sqlx::query!(
+            r#"
+            UPDATE account_lockouts
+            SET failed_attempts = 0, lockouts = $2, locked_until = now() + make_interval(secs => $3)
+            WHERE email = $1
+            "#,
+            email.as_ref(),
+            lockouts as i32,
+            lock_duration.as_secs_f64(),
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| AccountLockoutStoreError::UnexpectedError(e.into()))?;
+
+        transaction.commit()
+            .await
+            .map_err(|e| AccountLockoutStoreError::UnexpectedError(e.into()))?;
+
+        Ok(FailedLoginOutcome::Locked { lock_duration })
+    }
+
+    #[tracing::instrument(name = "Resetting account lockout in PostgreSQL", skip_all)]
+    async fn reset(&self, email: &Email) -> Result<(), AccountLockoutStoreError> {
+        sqlx::query!(
+            r#"
+            DELETE FROM account_lockouts
+            WHERE email = $1
+            "#,
+            email.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AccountLockoutStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+}
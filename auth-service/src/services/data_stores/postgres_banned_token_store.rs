@@ -0,0 +1,63 @@
+use std::time::Duration;
+
+use secrecy::{ExposeSecret, SecretString};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+
+use crate::domain::data_stores::{BannedTokenStore, BannedTokenStoreError};
+
+// Tokens stop mattering once they would have expired anyway. Expired rows are
+// ignored here and deleted by the purge task.
+#[derive(Debug)]
+pub struct PostgresBannedTokenStore {
+    pool: PgPool,
+}
+
+impl PostgresBannedTokenStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl BannedTokenStore for PostgresBannedTokenStore {
+    #[tracing::instrument(name = "Banning token in PostgreSQL", skip_all)]
+    async fn add_token(&self, token: &SecretString, ttl: Duration) -> Result<(), BannedTokenStoreError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO banned_tokens (token_hash, expires_at)
+            VALUES ($1, now() + make_interval(secs => $2))
+            ON CONFLICT (token_hash) DO UPDATE SET expires_at = EXCLUDED.expires_at
+            "#,
+            hash_token(token),
+            ttl.as_secs_f64(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Checking banned token in PostgreSQL", skip_all)]
+    async fn check_token(&self, token: &SecretString) -> Result<bool, BannedTokenStoreError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM banned_tokens WHERE token_hash = $1 AND expires_at > now()
+            ) AS "banned!"
+            "#,
+            hash_token(token),
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(row.banned)
+    }
+}
+
+// JWTs are long, their hash makes a short key and keeps them out of the database
+pub(crate) fn hash_token(token: &SecretString) -> String {
+    hex::encode(Sha256::digest(token.expose_secret().as_bytes()))
+}
@@ -0,0 +1,145 @@
+use sqlx::PgPool;
+use secrecy::SecretString;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::domain::{
+    data_stores::{EmailOutboxStore, EmailOutboxStoreError},
+    Email, OutboxEmail,
+};
+
+#[derive(Debug)]
+pub struct PostgresEmailOutboxStore {
+    pool: PgPool,
+}
+
+impl PostgresEmailOutboxStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailOutboxStore for PostgresEmailOutboxStore {
+    #[tracing::instrument(name = "Adding email to outbox in PostgreSQL", skip_all)]
+    async fn enqueue(&self, email: OutboxEmail) -> Result<(), EmailOutboxStoreError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO email_outbox (id, recipient, subject, html_body, text_body)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            email.id,
+            email.recipient.as_ref(),
+            email.subject,
+            email.html_body,
+            email.text_body,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailOutboxStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Claiming next due email from outbox in PostgreSQL", skip_all)]
+    async fn claim_next(&self, lease: Duration) -> Result<Option<OutboxEmail>, EmailOutboxStoreError> {
+        // SKIP LOCKED lets several workers poll the outbox without claiming the same email
+        let row = sqlx::query!(
+            r#"
+            UPDATE email_outbox
+            SET attempts = attempts + 1, next_attempt_at = now() + make_interval(secs => $1)
+            WHERE id = (
+                SELECT id FROM email_outbox
+                WHERE status = 'pending' AND next_attempt_at <= now()
+                ORDER BY next_attempt_at
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, recipient, subject, html_body, text_body, attempts
+            "#,
+            lease.as_secs_f64(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| EmailOutboxStoreError::UnexpectedError(e.into()))?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+
+        let recipient = Email::parse(SecretString::new(row.recipient.into_boxed_str()))
+            .map_err(EmailOutboxStoreError::UnexpectedError)?;
+
+        Ok(Some(OutboxEmail {
+            id: row.id,
+            recipient,
+            subject: row.subject,
+            html_body: row.html_body,
+            text_body: row.text_body,
+            attempts: row.attempts as u32,
+        }))
+    }
+
+    #[tracing::instrument(name = "Marking outbox email as sent in PostgreSQL", skip_all)]
+    async fn mark_sent(&self, id: Uuid) -> Result<(), EmailOutboxStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE email_outbox
+            SET status = 'sent', sent_at = now(), last_error = NULL, html_body = '', text_body = ''
+            WHERE id = $1
+            "#,
+            id,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailOutboxStoreError::UnexpectedError(e.into()))?;
+
+        match result.rows_affected() {
+            0 => Err(EmailOutboxStoreError::EmailNotFound),
+            _ => Ok(()),
+        }
+    }
+
+    #[tracing::instrument(name = "Scheduling outbox email retry in PostgreSQL", skip_all)]
+    async fn schedule_retry(&self, id: Uuid, error: &str, retry_in: Duration) -> Result<(), EmailOutboxStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE email_outbox
+            SET next_attempt_at = now() + make_interval(secs => $2), last_error = $3
+            WHERE id = $1
+            "#,
+            id,
+            retry_in.as_secs_f64(),
+            error,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailOutboxStoreError::UnexpectedError(e.into()))?;
+
+        match result.rows_affected() {
+            0 => Err(EmailOutboxStoreError::EmailNotFound),
+            _ => Ok(()),
+        }
+    }
+
+    #[tracing::instrument(name = "Dead-lettering outbox email in PostgreSQL", skip_all)]
+    async fn dead_letter(&self, id: Uuid, error: &str) -> Result<(), EmailOutboxStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE email_outbox
+            SET status = 'dead', dead_at = now(), last_error = $2, html_body = '', text_body = ''
+            WHERE id = $1
+            "#,
+            id,
+            error,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailOutboxStoreError::UnexpectedError(e.into()))?;
+
+        match result.rows_affected() {
+            0 => Err(EmailOutboxStoreError::EmailNotFound),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use std::net::IpAddr;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::Context;
+use secrecy::SecretString;
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{SessionStore, SessionStoreError},
+    Email,
+    Session,
+    SessionId,
+};
+
+// Ended sessions are ignored here and deleted by the purge task
+#[derive(Debug)]
+pub struct PostgresSessionStore {
+    pool: PgPool,
+}
+
+impl PostgresSessionStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl SessionStore for PostgresSessionStore {
+    #[tracing::instrument(name = "Adding session to PostgreSQL", skip_all)]
+    async fn add_session(&self, session: Session, ttl: Duration) -> Result<(), SessionStoreError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO sessions (id, email, created_at, last_seen, ip, user_agent, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, now() + make_interval(secs => $7))
+            ON CONFLICT (id) DO UPDATE SET
+                last_seen = EXCLUDED.last_seen,
+                expires_at = EXCLUDED.expires_at
+            "#,
+            session.id.as_ref(),
+            session.email.as_ref(),
+            session.created_at,
+            session.last_seen,
+            session.ip.map(|ip| ip.to_string()),
+            session.user_agent,
+            ttl.as_secs_f64(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving session from PostgreSQL", skip_all)]
+    async fn get_session(&self, id: &SessionId) -> Result<Session, SessionStoreError> {
+        let row = sqlx::query_as!(
+            SessionRow,
+            r#"
+            SELECT id, email, created_at, last_seen, ip, user_agent
+            FROM sessions
+            WHERE id = $1 AND expires_at > now()
+            "#,
+            id.as_ref(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?
+        .ok_or(SessionStoreError::SessionNotFound)?;
+
+        row.into_session()
+    }
+
+    #[tracing::instrument(name = "Touching session in PostgreSQL", skip_all)]
+    async fn touch_session(&self, id: &SessionId, last_seen: DateTime<Utc>, ttl: Duration) -> Result<(), SessionStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE sessions
+            SET last_seen = $2, expires_at = now() + make_interval(secs => $3)
+            WHERE id = $1 AND expires_at > now()
+            "#,
+            id.as_ref(),
+            last_seen,
+            ttl.as_secs_f64(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+
+        match result.rows_affected() {
+            0 => Err(SessionStoreError::SessionNotFound),
+            _ => Ok(()),
+        }
+    }
+
+    #[tracing::instrument(name = "Removing session from PostgreSQL", skip_all)]
+    async fn remove_session(&self, id: &SessionId) -> Result<(), SessionStoreError> {
+        sqlx::query!(
+            r#"
+            DELETE FROM sessions
+            WHERE id = $1
+            "#,
+            id.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Listing sessions from PostgreSQL", skip_all)]
+    async fn list_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError> {
+        let rows = sqlx::query_as!(
+            SessionRow,
+            r#"
+            SELECT id, email, created_at, last_seen, ip, user_agent
+            FROM sessions
+            WHERE email = $1 AND expires_at > now()
+            "#,
+            email.as_ref(),
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+
+        rows.into_iter().map(SessionRow::into_session).collect()
+    }
+}
+
+struct SessionRow {
+    id: String,
+    email: String,
+    created_at: DateTime<Utc>,
+    last_seen: DateTime<Utc>,
+    ip: Option<String>,
+    user_agent: Option<String>,
+}
+
+impl SessionRow {
+    fn into_session(self) -> Result<Session, SessionStoreError> {
+        let id = SessionId::parse(self.id)
+            .map_err(SessionStoreError::UnexpectedError)?;
+        let email = Email::parse(SecretString::new(self.email.into_boxed_str()))
+            .map_err(SessionStoreError::UnexpectedError)?;
+        let ip = self.ip
+            .map(|ip| ip.parse::<IpAddr>())
+            .transpose()
+            .wrap_err("invalid session IP address")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        Ok(Session {
+            id,
+            email,
+            created_at: self.created_at,
+            last_seen: self.last_seen,
+            ip,
+            user_agent: self.user_agent,
+        })
+    }
+}
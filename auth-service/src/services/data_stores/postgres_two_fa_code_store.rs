@@ -0,0 +1,122 @@
+use std::time::Duration;
+
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{TwoFACodeStore, TwoFACodeStoreError},
+    Email, LoginAttemptId, TwoFACode, TwoFACodePurpose,
+};
+use crate::utils::auth::TWO_FA_CODE_TTL_SECONDS;
+
+// Expired codes are ignored here and deleted by the purge task
+#[derive(Debug)]
+pub struct PostgresTwoFACodeStore {
+    pool: PgPool,
+    ttl: Duration,
+}
+
+impl PostgresTwoFACodeStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool, ttl: Duration::from_secs(TWO_FA_CODE_TTL_SECONDS as u64) }
+    }
+
+    pub fn with_ttl(self, ttl: Duration) -> Self {
+        Self { ttl, ..self }
+    }
+}
+
+#[async_trait::async_trait]
+impl TwoFACodeStore for PostgresTwoFACodeStore {
+    #[tracing::instrument(name = "Adding 2FA code to PostgreSQL", skip_all)]
+    async fn add_code(
+        &self,
+        email: Email,
+        purpose: TwoFACodePurpose,
+        login_attempt_id: LoginAttemptId,
+        code: TwoFACode,
+    ) -> Result<(), TwoFACodeStoreError> {
+        // A new code replaces the previous one and its failed attempts, like in the Redis store
+        sqlx::query!(
+            r#"
+            INSERT INTO two_fa_codes (email, purpose, login_attempt_id, code, expires_at)
+            VALUES ($1, $2, $3, $4, now() + make_interval(secs => $5))
+            ON CONFLICT (email, purpose) DO UPDATE SET
+                login_attempt_id = EXCLUDED.login_attempt_id,
+                code = EXCLUDED.code,
+                failed_attempts = 0,
+                expires_at = EXCLUDED.expires_at
+            "#,
+            email.as_ref(),
+            purpose.as_str(),
+            login_attempt_id.as_ref(),
+            code.as_ref(),
+            self.ttl.as_secs_f64(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Removing 2FA code from PostgreSQL", skip_all)]
+    async fn remove_code(&self, email: &Email, purpose: TwoFACodePurpose) -> Result<(), TwoFACodeStoreError> {
+        sqlx::query!(
+            r#"
+            DELETE FROM two_fa_codes
+            WHERE email = $1 AND purpose = $2
+            "#,
+            email.as_ref(),
+            purpose.as_str(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving 2FA code from PostgreSQL", skip_all)]
+    async fn get_code(&self, email: &Email, purpose: TwoFACodePurpose) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT login_attempt_id, code
+            FROM two_fa_codes
+            WHERE email = $1 AND purpose = $2 AND expires_at > now()
+            "#,
+            email.as_ref(),
+            purpose.as_str(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?
+        .ok_or(TwoFACodeStoreError::LoginAttempIdNotFound)?;
+
+        let login_attempt_id = LoginAttemptId::parse(row.login_attempt_id)
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+        let code = TwoFACode::parse(row.code)
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        Ok((login_attempt_id, code))
+    }
+
+    #[tracing::instrument(name = "Recording failed 2FA attempt in PostgreSQL", skip_all)]
+    async fn record_failed_attempt(&self, email: &Email, purpose: TwoFACodePurpose) -> Result<u32, TwoFACodeStoreError> {
+        let failed_attempts = sqlx::query_scalar!(
+            r#"
+            UPDATE two_fa_codes
+            SET failed_attempts = failed_attempts + 1
+            WHERE email = $1 AND purpose = $2 AND expires_at > now()
+            RETURNING failed_attempts
+            "#,
+            email.as_ref(),
+            purpose.as_str(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?
+        .ok_or(TwoFACodeStoreError::LoginAttempIdNotFound)?;
+
+        Ok(failed_attempts as u32)
+    }
+}
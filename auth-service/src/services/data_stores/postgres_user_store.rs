@@ -4,7 +4,7 @@ use secrecy::SecretString;
 
 use crate::domain::{
     data_stores::{UserStore, UserStoreError},
-    Email, HashedPassword, User,
+    Email, EmailChangeRevert, HashedPassword, User,
 };
 
 #[derive(Debug)]
@@ -21,7 +21,7 @@ impl PostgresUserStore {
 #[async_trait::async_trait]
 impl UserStore for PostgresUserStore {
     #[tracing::instrument(name = "Adding user to PostgreSQL", skip_all)]
-    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError> {
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError> {
         // Add user to database. 
         // If user already exists then do nothing and return NONE
         let result = sqlx::query!(
@@ -50,7 +50,7 @@ impl UserStore for PostgresUserStore {
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
         let result = sqlx::query!(
             r#"
-            SELECT email, password_hash, requires_2fa
+            SELECT email, password_hash, requires_2fa, email_undeliverable
             FROM users
             WHERE email = $1
             "#,
@@ -69,7 +69,8 @@ impl UserStore for PostgresUserStore {
                 let password_secret = SecretString::new(row.password_hash.into_boxed_str());
                 let password = HashedPassword::parse_password_hash(password_secret)
                     .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
-                let user = User::new(email, password, row.requires_2fa);
+                let mut user = User::new(email, password, row.requires_2fa);
+                user.email_undeliverable = row.email_undeliverable;
                 Ok(user)
             },
             None => Err(UserStoreError::UserNotFound),
@@ -87,4 +88,152 @@ impl UserStore for PostgresUserStore {
             .await
             .map_err(|_| UserStoreError::InvalidCredentials)
     }
-}
\ No newline at end of file
+
+    #[tracing::instrument(name = "Confirming email change in PostgreSQL", skip_all)]
+    async fn confirm_email_change(&self, change_id: &str, current_email: &Email, new_email: Email) -> Result<(), UserStoreError> {
+        let mut transaction = self.pool
+            .begin()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET email = $2, email_undeliverable = FALSE
+            WHERE email = $1
+            RETURNING email
+            "#,
+            current_email.as_ref(),
+            new_email.as_ref(),
+        )
+        .fetch_optional(&mut *transaction)
+        .await
+        .map_err(map_update_email_error)?;
+
+        if result.is_none() {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        // The change that gave the account its old address can't be reverted anymore
+        sqlx::query!(
+            r#"
+            UPDATE email_changes
+            SET ended_at = now()
+            WHERE to_email = $1 AND ended_at IS NULL
+            "#,
+            current_email.as_ref(),
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO email_changes (id, from_email, to_email)
+            VALUES ($1, $2, $3)
+            "#,
+            change_id,
+            current_email.as_ref(),
+            new_email.as_ref(),
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        transaction.commit()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "Reverting email change in PostgreSQL", skip_all)]
+    async fn revert_email_change(&self, change_id: &str) -> Result<EmailChangeRevert, UserStoreError> {
+        let mut transaction = self.pool
+            .begin()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        // Locked until commit, so the change is reverted at most once
+        let change = sqlx::query!(
+            r#"
+            SELECT from_email, to_email, ended_at
+            FROM email_changes
+            WHERE id = $1
+            FOR UPDATE
+            "#,
+            change_id,
+        )
+        .fetch_optional(&mut *transaction)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        let Some(change) = change else {
+            return Ok(EmailChangeRevert::NotConfirmed);
+        };
+        if change.ended_at.is_some() {
+            return Ok(EmailChangeRevert::Ended);
+        }
+
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET email = $2, email_undeliverable = FALSE
+            WHERE email = $1
+            "#,
+            change.to_email,
+            change.from_email,
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(map_update_email_error)?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE email_changes
+            SET ended_at = now()
+            WHERE id = $1
+            "#,
+            change_id,
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        transaction.commit()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        Ok(EmailChangeRevert::Reverted)
+    }
+
+    #[tracing::instrument(name = "Marking user email as undeliverable in PostgreSQL", skip_all)]
+    async fn mark_email_undeliverable(&self, email: &Email) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET email_undeliverable = TRUE
+            WHERE email = $1
+            "#,
+            email.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        match result.rows_affected() {
+            0 => Err(UserStoreError::UserNotFound),
+            _ => Ok(()),
+        }
+    }
+}
+
+// Another account already holding the address violates the primary key
+fn map_update_email_error(e: sqlx::Error) -> UserStoreError {
+    match e {
+        sqlx::Error::Database(ref db_err) if db_err.is_unique_violation() => UserStoreError::UserAlreadyExists,
+        _ => UserStoreError::UnexpectedError(e.into()),
+    }
+}
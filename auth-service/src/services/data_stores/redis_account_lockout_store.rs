@@ -0,0 +1,110 @@
+use std::time::Duration;
+
+use color_eyre::eyre::Context;
+use redis::{AsyncCommands, Script};
+
+use crate::domain::{
+    data_stores::{AccountLockoutStore, AccountLockoutStoreError},
+    Email, FailedLoginOutcome, LockoutPolicy, LockoutStatus,
+};
+use crate::services::redis_connection::{hash_tagged_key, RedisConnection};
+
+// Failure counters live in a hash that expires `reset_after` after the last failure.
+// A lock is a separate key that expires when the lock ends.
+pub struct RedisAccountLockoutStore {
+    conn: RedisConnection,
+}
+
+impl RedisAccountLockoutStore {
+    pub fn new(conn: RedisConnection) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl AccountLockoutStore for RedisAccountLockoutStore {
+    #[tracing::instrument(name = "Get_Lockout_Status", skip_all)]
+    async fn status(&self, email: &Email) -> Result<LockoutStatus, AccountLockoutStoreError> {
+        let mut conn = self.conn.clone();
+
+        // -2 if the key doesn't exist, -1 if it has no expiry
+        let ttl_ms: i64 = conn.pttl(get_lock_key(email))
+            .await
+            .wrap_err("failed to get account lock from Redis")
+            .map_err(AccountLockoutStoreError::UnexpectedError)?;
+
+        match ttl_ms {
+            ms if ms > 0 => Ok(LockoutStatus::Locked { retry_after: Duration::from_millis(ms as u64) }),
+            _ => Ok(LockoutStatus::Unlocked),
+        }
+    }
+
+    #[tracing::instrument(name = "Record_Failed_Login", skip_all)]
+    async fn record_failure(&self, email: &Email, policy: &LockoutPolicy) -> Result<FailedLoginOutcome, AccountLockoutStoreError> {
+        let mut conn = self.conn.clone();
+
+        // Counting and locking happen in one script so concurrent failures can't
+        // both reach the threshold
+        let (failed_attempts, lock_duration_ms): (u32, u64) = Script::new(RECORD_FAILURE_SCRIPT)
+            .key(get_failures_key(email))
+            .key(get_lock_key(email))
+            .arg(policy.threshold)
+            .arg(policy.reset_after.as_millis() as u64)
+            .arg(policy.base_duration.as_millis() as u64)
+            .arg(policy.max_duration.as_millis() as u64)
+            .invoke_async(&mut conn)
+            .await
+            .wrap_err("failed to record failed login in Redis")
+            .map_err(AccountLockoutStoreError::UnexpectedError)?;
+
+        match lock_duration_ms {
+            0 => Ok(FailedLoginOutcome::Counted { failed_attempts }),
+            ms => Ok(FailedLoginOutcome::Locked { lock_duration: Duration::from_millis(ms) }),
+        }
+    }
+
+    #[tracing::instrument(name = "Reset_Account_Lockout", skip_all)]
+    async fn reset(&self, email: &Email) -> Result<(), AccountLockoutStoreError> {
+        let mut conn = self.conn.clone();
+
+        conn.del(&[get_failures_key(email), get_lock_key(email)])
+            .await
+            .wrap_err("failed to reset account lockout in Redis")
+            .map_err(AccountLockoutStoreError::UnexpectedError)
+    }
+}
+
+// Returns the failed attempts so far and how long the account was locked for,
+// which is 0 unless this failure locked it. Mirrors `LockoutPolicy::lockout_duration`.
+const RECORD_FAILURE_SCRIPT: &str = r"
+local threshold = tonumber(ARGV[1])
+local reset_after = tonumber(ARGV[2])
+local base = tonumber(ARGV[3])
+local max = tonumber(ARGV[4])
+
+local failed = redis.call('HINCRBY', KEYS[1], 'failed_attempts', 1)
+local lock_duration = 0
+
+if failed >= threshold then
+    local lockouts = redis.call('HINCRBY', KEYS[1], 'lockouts', 1)
+    redis.call('HSET', KEYS[1], 'failed_attempts', 0)
+    lock_duration = math.floor(math.min(max, base * 2 ^ math.min(lockouts - 1, 31)))
+    redis.call('SET', KEYS[2], 1, 'PX', lock_duration)
+end
+
+redis.call('PEXPIRE', KEYS[1], math.max(reset_after, lock_duration))
+
+return {failed, lock_duration}
+";
+
+// Both keys of a user hash to the same slot, since the script uses them together
+const FAILURES_KEY_PREFIX: &str = "account_lockout:failures:";
+const LOCK_KEY_PREFIX: &str = "account_lockout:lock:";
+
+fn get_failures_key(email: &Email) -> String {
+    hash_tagged_key(FAILURES_KEY_PREFIX, email.as_ref())
+}
+
+fn get_lock_key(email: &Email) -> String {
+    hash_tagged_key(LOCK_KEY_PREFIX, email.as_ref())
+}
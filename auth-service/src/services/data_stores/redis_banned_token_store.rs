@@ -1,24 +1,21 @@
-use std::sync::Arc;
+use std::time::Duration;
 
-use redis::{Commands, Connection};
-use tokio::sync::RwLock;
+use redis::AsyncCommands;
 use color_eyre::eyre::{Context, Result};
 use secrecy::{ExposeSecret, SecretString};
 
 use crate::{
     domain::data_stores::{BannedTokenStore, BannedTokenStoreError},
-    utils::auth::TOKEN_TTL_SECONDS,
+    services::redis_connection::{hash_tagged_key, RedisConnection},
 };
 
+// Clones of `RedisConnection` share the connection, so every call uses its own clone
 pub struct RedisBannedTokenStore {
-    // TODO: Does this need to be in side an Arc<RwLock<...>> ? May be redundant since the banned token store
-    // is already inside a Arc<RwLock<>>. Try implementing this without it. May need to change `check_token`
-    // method to take in &mut self.
-    conn: Arc<RwLock<Connection>>,
+    conn: RedisConnection,
 }
 
 impl RedisBannedTokenStore {
-    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+    pub fn new(conn: RedisConnection) -> Self {
         Self { conn }
     }
 }
@@ -26,13 +23,15 @@ impl RedisBannedTokenStore {
 #[async_trait::async_trait]
 impl BannedTokenStore for RedisBannedTokenStore {
     #[tracing::instrument(name = "Add_Token", skip_all)]
-    async fn add_token(&mut self, token: &SecretString) -> Result<(), BannedTokenStoreError> {
+    async fn add_token(&self, token: &SecretString, ttl: Duration) -> Result<(), BannedTokenStoreError> {
         let key = get_key(token);
 
-        let mut conn_lock = self.conn.write().await;
+        let mut conn = self.conn.clone();
 
-        conn_lock
-            .set_ex(key, token.expose_secret(), TOKEN_TTL_SECONDS as u64)
+        // Redis expires keys in whole seconds, at least one
+        conn
+            .set_ex(key, token.expose_secret(), ttl.as_secs().max(1))
+            .await
             .wrap_err("failed to set banned token in Redis")
             .map_err(BannedTokenStoreError::UnexpectedError)
     }
@@ -41,8 +40,9 @@ impl BannedTokenStore for RedisBannedTokenStore {
     async fn check_token(&self, token: &SecretString) -> Result<bool, BannedTokenStoreError> {
         let key = get_key(token);
 
-        let mut conn_lock = self.conn.write().await;
-        conn_lock.exists(key)
+        let mut conn = self.conn.clone();
+        conn.exists(key)
+            .await
             .wrap_err("failed to check if token exists in Redis")
             .map_err(BannedTokenStoreError::UnexpectedError)
     }
@@ -52,5 +52,5 @@ impl BannedTokenStore for RedisBannedTokenStore {
 const BANNED_TOKEN_KEY_PREFIX: &str = "banned_token:";
 
 fn get_key(token: &SecretString) -> String {
-    format!("{}{}", BANNED_TOKEN_KEY_PREFIX, token.expose_secret())
+    hash_tagged_key(BANNED_TOKEN_KEY_PREFIX, token.expose_secret())
 }
\ No newline at end of file
@@ -0,0 +1,86 @@
+use std::time::Duration;
+
+use redis::Script;
+use color_eyre::eyre::Context;
+
+use crate::domain::{
+    data_stores::{RateLimitStore, RateLimitStoreError},
+    RateLimitDecision, RateLimitPolicy,
+};
+use crate::services::redis_connection::{hash_tagged_key, RedisConnection};
+
+pub struct RedisRateLimitStore {
+    conn: RedisConnection,
+}
+
+impl RedisRateLimitStore {
+    pub fn new(conn: RedisConnection) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for RedisRateLimitStore {
+    #[tracing::instrument(name = "Check_Rate_Limit", skip_all)]
+    async fn check(&self, key: &str, policy: &RateLimitPolicy) -> Result<RateLimitDecision, RateLimitStoreError> {
+        let key = get_key(key);
+        let refill_interval_ms = policy.refill_interval.as_millis().max(1) as u64;
+
+        let mut conn = self.conn.clone();
+
+        // The bucket is updated in a script so concurrent requests from
+        // several replicas can't take the same token
+        let retry_after_ms: u64 = Script::new(TOKEN_BUCKET_SCRIPT)
+            .key(key)
+            .arg(policy.capacity)
+            .arg(refill_interval_ms)
+            .invoke_async(&mut conn)
+            .await
+            .wrap_err("failed to check rate limit in Redis")
+            .map_err(RateLimitStoreError::UnexpectedError)?;
+
+        match retry_after_ms {
+            0 => Ok(RateLimitDecision::Allowed),
+            ms => Ok(RateLimitDecision::Limited { retry_after: Duration::from_millis(ms) }),
+        }
+    }
+}
+
+// Returns 0 if a token was taken, otherwise the number of milliseconds until
+// the next token is available. Uses the Redis clock so all replicas agree.
+const TOKEN_BUCKET_SCRIPT: &str = r"
+local capacity = tonumber(ARGV[1])
+local interval = tonumber(ARGV[2])
+
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'updated_at')
+local tokens = tonumber(bucket[1])
+local updated_at = tonumber(bucket[2])
+
+if tokens == nil or updated_at == nil then
+    tokens = capacity
+    updated_at = now
+end
+
+tokens = math.min(capacity, tokens + (now - updated_at) / interval)
+
+local retry_after = 0
+if tokens >= 1 then
+    tokens = tokens - 1
+else
+    retry_after = math.ceil((1 - tokens) * interval)
+end
+
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'updated_at', now)
+redis.call('PEXPIRE', KEYS[1], capacity * interval)
+
+return retry_after
+";
+
+const RATE_LIMIT_KEY_PREFIX: &str = "rate_limit:";
+
+fn get_key(key: &str) -> String {
+    hash_tagged_key(RATE_LIMIT_KEY_PREFIX, key)
+}
@@ -0,0 +1,206 @@
+use std::net::IpAddr;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{Context, ContextCompat};
+use redis::AsyncCommands;
+use secrecy::SecretString;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::{
+    data_stores::{SessionStore, SessionStoreError},
+    Email,
+    Session,
+    SessionId,
+};
+use crate::services::redis_connection::{hash_tagged_key, RedisConnection};
+
+pub struct RedisSessionStore {
+    conn: RedisConnection,
+}
+
+impl RedisSessionStore {
+    pub fn new(conn: RedisConnection) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl SessionStore for RedisSessionStore {
+    #[tracing::instrument(name = "Add_Session", skip_all)]
+    async fn add_session(&self, session: Session, ttl: Duration) -> Result<(), SessionStoreError> {
+        let key = get_key(&session.id);
+        let index_key = get_index_key(&session.email);
+        let ttl = ttl.as_secs().max(1);
+        let record = serde_json::to_string(&SessionRecord::from(&session))
+            .wrap_err("failed to serialize session")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        let mut conn = self.conn.clone();
+
+        // The session and the index can be in different cluster slots, so they can't
+        // be written in one transaction. The index goes first: an ID without a session
+        // is pruned when sessions are listed, a session missing from the index isn't.
+        //
+        // The index lives as long as the user's longest session. EXPIRE NX gives a new
+        // index a TTL, EXPIRE GT only ever extends it.
+        redis::pipe()
+            .atomic()
+            .sadd(&index_key, session.id.as_ref()).ignore()
+            .cmd("EXPIRE").arg(&index_key).arg(ttl).arg("NX").ignore()
+            .cmd("EXPIRE").arg(&index_key).arg(ttl).arg("GT").ignore()
+            .query_async::<()>(&mut conn)
+            .await
+            .wrap_err("failed to add session to index in Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        conn.set_ex(key, record, ttl)
+            .await
+            .wrap_err("failed to set session in Redis")
+            .map_err(SessionStoreError::UnexpectedError)
+    }
+
+    #[tracing::instrument(name = "Get_Session", skip_all)]
+    async fn get_session(&self, id: &SessionId) -> Result<Session, SessionStoreError> {
+        let key = get_key(id);
+
+        let mut conn = self.conn.clone();
+
+        let record: Option<String> = conn.get(key)
+            .await
+            .wrap_err("failed to get session from Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+        let record = record.ok_or(SessionStoreError::SessionNotFound)?;
+
+        let record: SessionRecord = serde_json::from_str(&record)
+            .wrap_err("failed to deserialize session")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        record.into_session(id.clone())
+    }
+
+    #[tracing::instrument(name = "Touch_Session", skip_all)]
+    async fn touch_session(&self, id: &SessionId, last_seen: DateTime<Utc>, ttl: Duration) -> Result<(), SessionStoreError> {
+        let mut session = self.get_session(id).await?;
+        session.last_seen = last_seen;
+
+        self.add_session(session, ttl).await
+    }
+
+    #[tracing::instrument(name = "Remove_Session", skip_all)]
+    async fn remove_session(&self, id: &SessionId) -> Result<(), SessionStoreError> {
+        let session = match self.get_session(id).await {
+            Ok(session) => session,
+            Err(SessionStoreError::SessionNotFound) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        let mut conn = self.conn.clone();
+
+        // Session first, so a failure in between leaves an ID that gets pruned later
+        let _: () = conn.del(get_key(id))
+            .await
+            .wrap_err("failed to delete session from Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        conn.srem(get_index_key(&session.email), id.as_ref())
+            .await
+            .wrap_err("failed to remove session from index in Redis")
+            .map_err(SessionStoreError::UnexpectedError)
+    }
+
+    #[tracing::instrument(name = "List_Sessions", skip_all)]
+    async fn list_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError> {
+        let index_key = get_index_key(email);
+
+        let mut conn = self.conn.clone();
+
+        let ids: Vec<String> = conn
+            .smembers(&index_key)
+            .await
+            .wrap_err("failed to get session index from Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        let mut sessions = Vec::with_capacity(ids.len());
+        let mut ended = Vec::new();
+        for id in ids {
+            let Ok(session_id) = SessionId::parse(id.clone()) else {
+                ended.push(id);
+                continue;
+            };
+
+            match self.get_session(&session_id).await {
+                Ok(session) => sessions.push(session),
+                // The session expired, its ID is still in the index
+                Err(SessionStoreError::SessionNotFound) => ended.push(id),
+                Err(e) => return Err(e),
+            }
+        }
+
+        if !ended.is_empty() {
+            let _: () = conn
+                .srem(&index_key, ended)
+                .await
+                .wrap_err("failed to prune session index in Redis")
+                .map_err(SessionStoreError::UnexpectedError)?;
+        }
+
+        Ok(sessions)
+    }
+}
+
+// How a session is stored in Redis. Times are Unix timestamps in milliseconds.
+#[derive(Serialize, Deserialize)]
+struct SessionRecord {
+    email: String,
+    created_at: i64,
+    last_seen: i64,
+    ip: Option<IpAddr>,
+    user_agent: Option<String>,
+}
+
+impl From<&Session> for SessionRecord {
+    fn from(session: &Session) -> Self {
+        Self {
+            email: session.email.as_ref().to_owned(),
+            created_at: session.created_at.timestamp_millis(),
+            last_seen: session.last_seen.timestamp_millis(),
+            ip: session.ip,
+            user_agent: session.user_agent.clone(),
+        }
+    }
+}
+
+impl SessionRecord {
+    fn into_session(self, id: SessionId) -> Result<Session, SessionStoreError> {
+        let email = Email::parse(SecretString::new(self.email.into_boxed_str()))
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        Ok(Session {
+            id,
+            email,
+            created_at: timestamp(self.created_at)?,
+            last_seen: timestamp(self.last_seen)?,
+            ip: self.ip,
+            user_agent: self.user_agent,
+        })
+    }
+}
+
+fn timestamp(millis: i64) -> Result<DateTime<Utc>, SessionStoreError> {
+    DateTime::from_timestamp_millis(millis)
+        .wrap_err("invalid session timestamp")
+        .map_err(SessionStoreError::UnexpectedError)
+}
+
+const SESSION_KEY_PREFIX: &str = "session:";
+const SESSION_INDEX_KEY_PREFIX: &str = "user_sessions:";
+
+fn get_key(id: &SessionId) -> String {
+    hash_tagged_key(SESSION_KEY_PREFIX, id.as_ref())
+}
+
+// Set of the IDs of a user's sessions
+fn get_index_key(email: &Email) -> String {
+    hash_tagged_key(SESSION_INDEX_KEY_PREFIX, email.as_ref())
+}
@@ -1,24 +1,32 @@
-use std::sync::Arc;
+use std::time::Duration;
 
 use color_eyre::eyre::Context;
-use redis::{Commands, Connection};
+use redis::{AsyncCommands, Script};
 use serde::{Deserialize, Serialize};
-use tokio::sync::RwLock;
 
 use crate::domain::{
     data_stores::{TwoFACodeStore, TwoFACodeStoreError},
     TwoFACode, 
+    TwoFACodePurpose,
     LoginAttemptId, 
     Email,
 };
+use crate::services::redis_connection::{hash_tagged_key, RedisConnection};
+use crate::utils::auth::TWO_FA_CODE_TTL_SECONDS;
 
 pub struct RedisTwoFACodeStore {
-    conn: Arc<RwLock<Connection>>,
+    conn: RedisConnection,
+    ttl: Duration,
 }
 
 impl RedisTwoFACodeStore {
-    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
-        Self { conn }
+    pub fn new(conn: RedisConnection) -> Self {
+        Self { conn, ttl: Duration::from_secs(TWO_FA_CODE_TTL_SECONDS as u64) }
+    }
+
+    // Redis expires keys in whole seconds, at least one
+    pub fn with_ttl(self, ttl: Duration) -> Self {
+        Self { ttl, ..self }
     }
 }
 
@@ -26,8 +34,9 @@ impl RedisTwoFACodeStore {
 impl TwoFACodeStore for RedisTwoFACodeStore {
     #[tracing::instrument(name = "Add_2FA_Code", skip_all)]
     async fn add_code(
-        &mut self,
+        &self,
         email: Email,
+        purpose: TwoFACodePurpose,
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
     ) -> Result<(), TwoFACodeStoreError> {
@@ -38,32 +47,38 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
         // Return TwoFACodeStoreError::UnexpectedError if serialization fails.
         // 4. Call the set_ex command on the Redis connection to set a new key/value pair with an expiration time (TTL). 
         // The value should be the serialized 2FA tuple.
-        // The expiration time should be set to TEN_MINUTES_IN_SECONDS.
+        // The expiration time should be set to TWO_FA_CODE_TTL_SECONDS.
         // Return TwoFACodeStoreError::UnexpectedError if casting fails or the call to set_ex fails.
-        let key = get_key(&email);
+        let key = get_key(&email, purpose);
 
         let two_fa_tuple = TwoFATuple(login_attempt_id.as_ref().to_owned(), code.as_ref().to_owned());
         let two_fa_tuple = serde_json::to_string(&two_fa_tuple)
             .wrap_err("failed to serialize 2FA tuple")
             .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
-        let mut conn_lock = self.conn.write().await;
+        let mut conn = self.conn.clone();
 
-        conn_lock.set_ex(key, two_fa_tuple, TEN_MINUTES_IN_SECONDS)
+        // A new code starts without failed attempts. Both keys share a hash tag,
+        // so they can be written in one transaction.
+        redis::pipe()
+            .atomic()
+            .set_ex(key, two_fa_tuple, self.ttl.as_secs().max(1)).ignore()
+            .del(get_failures_key(&email, purpose)).ignore()
+            .query_async::<()>(&mut conn)
+            .await
             .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))
     }
 
     #[tracing::instrument(name = "Remove_2FA_Code", skip_all)]
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+    async fn remove_code(&self, email: &Email, purpose: TwoFACodePurpose) -> Result<(), TwoFACodeStoreError> {
         // TODO:
         // 1. Create a new key using the get_key helper function.
         // 2. Call the del command on the Redis connection to delete the 2FA code entry. 
         // Return TwoFACodeStoreError::UnexpectedError if the operation fails.
-        let key = get_key(email);
-
-        let mut conn_lock = self.conn.write().await;
+        let mut conn = self.conn.clone();
 
-        conn_lock.del(key)
+        conn.del(&[get_key(email, purpose), get_failures_key(email, purpose)])
+            .await
             .wrap_err("failed to delete 2FA code from Redis")
             .map_err(TwoFACodeStoreError::UnexpectedError)
     }
@@ -72,20 +87,24 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
     async fn get_code(
         &self,
         email: &Email,
+        purpose: TwoFACodePurpose,
     ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
         // TODO:
         // 1. Create a new key using the get_key helper function.
         // 2. Call the get command on the Redis connection to get the value stored for the key. 
-        // Return TwoFACodeStoreError::LoginAttemptIdNotFound if the operation fails.
+        // Return TwoFACodeStoreError::LoginAttemptIdNotFound if there is no code.
         // If the operation succeeds, call serde_json::from_str to parse the JSON string into a TwoFATuple. 
         // Then, parse the login attempt ID string and 2FA code string into a LoginAttemptId and TwoFACode type respectively.
         // Return TwoFACodeStoreError::UnexpectedError if parsing fails.
-        let key = get_key(email);
+        let key = get_key(email, purpose);
 
-        let mut conn_lock = self.conn.write().await;
+        let mut conn = self.conn.clone();
 
-        let val: String = conn_lock.get(key)
-            .map_err(|_| TwoFACodeStoreError::LoginAttempIdNotFound)?;
+        let val: Option<String> = conn.get(key)
+            .await
+            .wrap_err("failed to get 2FA code from Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+        let val = val.ok_or(TwoFACodeStoreError::LoginAttempIdNotFound)?;
 
         let two_fa_tuple: TwoFATuple = serde_json::from_str(&val)
             .wrap_err("failed to deserialize 2FA tuple")
@@ -99,14 +118,47 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
 
         Ok((login_attempt_id, two_fa_code))
     }
+
+    #[tracing::instrument(name = "Record_Failed_2FA_Attempt", skip_all)]
+    async fn record_failed_attempt(&self, email: &Email, purpose: TwoFACodePurpose) -> Result<u32, TwoFACodeStoreError> {
+        let mut conn = self.conn.clone();
+
+        // Checked and counted in one script so a code removed meanwhile isn't counted
+        let failed_attempts: i64 = Script::new(RECORD_FAILURE_SCRIPT)
+            .key(get_key(email, purpose))
+            .key(get_failures_key(email, purpose))
+            .invoke_async(&mut conn)
+            .await
+            .wrap_err("failed to record failed 2FA attempt in Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        u32::try_from(failed_attempts).map_err(|_| TwoFACodeStoreError::LoginAttempIdNotFound)
+    }
 }
 
+// Returns the failed attempts so far, or -1 if there is no code. The counter
+// expires with the code.
+const RECORD_FAILURE_SCRIPT: &str = r"
+local ttl_ms = redis.call('PTTL', KEYS[1])
+if ttl_ms <= 0 then
+    return -1
+end
+
+local failed_attempts = redis.call('INCR', KEYS[2])
+redis.call('PEXPIRE', KEYS[2], ttl_ms)
+return failed_attempts
+";
+
 #[derive(Serialize, Deserialize)]
 struct TwoFATuple(pub String, pub String);
 
-const TEN_MINUTES_IN_SECONDS: u64 = 600;
 const TWO_FA_CODE_PREFIX: &str = "two_fa_code:";
+const TWO_FA_FAILURES_PREFIX: &str = "two_fa_failures:";
+
+fn get_key(email: &Email, purpose: TwoFACodePurpose) -> String {
+    hash_tagged_key(&format!("{}{}:", TWO_FA_CODE_PREFIX, purpose.as_str()), email.as_ref())
+}
 
-fn get_key(email: &Email) -> String {
-    format!("{}{}", TWO_FA_CODE_PREFIX, email.as_ref())
+fn get_failures_key(email: &Email, purpose: TwoFACodePurpose) -> String {
+    hash_tagged_key(&format!("{}{}:", TWO_FA_FAILURES_PREFIX, purpose.as_str()), email.as_ref())
 }
\ No newline at end of file
@@ -0,0 +1,94 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use secrecy::SecretString;
+use sqlx::SqlitePool;
+
+use super::postgres_banned_token_store::hash_token;
+use crate::domain::data_stores::{BannedTokenStore, BannedTokenStoreError};
+
+// Tokens are kept until they would have expired anyway. Expired rows are
+// ignored here and deleted by the purge task.
+#[derive(Debug)]
+pub struct SqliteBannedTokenStore {
+    pool: SqlitePool,
+}
+
+impl SqliteBannedTokenStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl BannedTokenStore for SqliteBannedTokenStore {
+    #[tracing::instrument(name = "Banning token in SQLite", skip_all)]
+    async fn add_token(&self, token: &SecretString, ttl: Duration) -> Result<(), BannedTokenStoreError> {
+        sqlx::query(
+            r#"
+            INSERT INTO banned_tokens (token_hash, expires_at)
+            VALUES (?, ?)
+            ON CONFLICT (token_hash) DO UPDATE SET expires_at = excluded.expires_at
+            "#,
+        )
+        .bind(hash_token(token))
+        // Expiry times are kept in whole seconds
+        .bind(Utc::now().timestamp() + ttl.as_secs() as i64)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Checking banned token in SQLite", skip_all)]
+    async fn check_token(&self, token: &SecretString) -> Result<bool, BannedTokenStoreError> {
+        sqlx::query_scalar(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM banned_tokens WHERE token_hash = ? AND expires_at > ?
+            )
+            "#,
+        )
+        .bind(hash_token(token))
+        .bind(Utc::now().timestamp())
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::data_stores::sqlite_test_pool;
+
+    #[tokio::test]
+    async fn test_add_and_check_token() {
+        let store = SqliteBannedTokenStore::new(sqlite_test_pool().await);
+        let token = SecretString::new("token1".to_owned().into_boxed_str());
+        let other = SecretString::new("token2".to_owned().into_boxed_str());
+
+        store.add_token(&token, Duration::from_secs(60)).await.unwrap();
+        store.add_token(&token, Duration::from_secs(60)).await.unwrap();
+
+        assert_eq!(store.check_token(&token).await, Ok(true));
+        assert_eq!(store.check_token(&other).await, Ok(false));
+    }
+
+    #[tokio::test]
+    async fn test_expired_token_is_not_banned() {
+        let pool = sqlite_test_pool().await;
+        let store = SqliteBannedTokenStore::new(pool.clone());
+        let token = SecretString::new("token1".to_owned().into_boxed_str());
+
+        sqlx::query("INSERT INTO banned_tokens (token_hash, expires_at) VALUES (?, ?)")
+            .bind(hash_token(&token))
+            .bind(Utc::now().timestamp() - 1)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert_eq!(store.check_token(&token).await, Ok(false));
+    }
+}
@@ -0,0 +1,176 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use sqlx::{Row, SqlitePool};
+
+use crate::domain::{
+    data_stores::{TwoFACodeStore, TwoFACodeStoreError},
+    Email, LoginAttemptId, TwoFACode, TwoFACodePurpose,
+};
+use crate::utils::auth::TWO_FA_CODE_TTL_SECONDS;
+
+// Expired codes are ignored here and deleted by the purge task
+#[derive(Debug)]
+pub struct SqliteTwoFACodeStore {
+    pool: SqlitePool,
+    ttl: Duration,
+}
+
+impl SqliteTwoFACodeStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool, ttl: Duration::from_secs(TWO_FA_CODE_TTL_SECONDS as u64) }
+    }
+
+    // Expiry times are kept in whole seconds
+    pub fn with_ttl(self, ttl: Duration) -> Self {
+        Self { ttl, ..self }
+    }
+}
+
+#[async_trait::async_trait]
+impl TwoFACodeStore for SqliteTwoFACodeStore {
+    #[tracing::instrument(name = "Adding 2FA code to SQLite", skip_all)]
+    async fn add_code(
+        &self,
+        email: Email,
+        purpose: TwoFACodePurpose,
+        login_attempt_id: LoginAttemptId,
+        code: TwoFACode,
+    ) -> Result<(), TwoFACodeStoreError> {
+        // A new code replaces the previous one and its failed attempts, like in the Redis store
+        sqlx::query(
+            r#"
+            INSERT INTO two_fa_codes (email, purpose, login_attempt_id, code, expires_at)
+            VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT (email, purpose) DO UPDATE SET
+                login_attempt_id = excluded.login_attempt_id,
+                code = excluded.code,
+                failed_attempts = 0,
+                expires_at = excluded.expires_at
+            "#,
+        )
+        .bind(email.as_ref())
+        .bind(purpose.as_str())
+        .bind(login_attempt_id.as_ref())
+        .bind(code.as_ref())
+        .bind(Utc::now().timestamp() + self.ttl.as_secs() as i64)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Removing 2FA code from SQLite", skip_all)]
+    async fn remove_code(&self, email: &Email, purpose: TwoFACodePurpose) -> Result<(), TwoFACodeStoreError> {
+        sqlx::query("DELETE FROM two_fa_codes WHERE email = ? AND purpose = ?")
+            .bind(email.as_ref())
+            .bind(purpose.as_str())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving 2FA code from SQLite", skip_all)]
+    async fn get_code(&self, email: &Email, purpose: TwoFACodePurpose) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
+        let row = sqlx::query(
+            r#"
+            SELECT login_attempt_id, code
+            FROM two_fa_codes
+            WHERE email = ? AND purpose = ? AND expires_at > ?
+            "#,
+        )
+        .bind(email.as_ref())
+        .bind(purpose.as_str())
+        .bind(Utc::now().timestamp())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?
+        .ok_or(TwoFACodeStoreError::LoginAttempIdNotFound)?;
+
+        let login_attempt_id: String = row.try_get("login_attempt_id")
+            .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+        let code: String = row.try_get("code")
+            .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+
+        let login_attempt_id = LoginAttemptId::parse(login_attempt_id)
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+        let code = TwoFACode::parse(code)
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        Ok((login_attempt_id, code))
+    }
+
+    #[tracing::instrument(name = "Recording failed 2FA attempt in SQLite", skip_all)]
+    async fn record_failed_attempt(&self, email: &Email, purpose: TwoFACodePurpose) -> Result<u32, TwoFACodeStoreError> {
+        let failed_attempts: i64 = sqlx::query_scalar(
+            r#"
+            UPDATE two_fa_codes
+            SET failed_attempts = failed_attempts + 1
+            WHERE email = ? AND purpose = ? AND expires_at > ?
+            RETURNING failed_attempts
+            "#,
+        )
+        .bind(email.as_ref())
+        .bind(purpose.as_str())
+        .bind(Utc::now().timestamp())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?
+        .ok_or(TwoFACodeStoreError::LoginAttempIdNotFound)?;
+
+        Ok(failed_attempts as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::data_stores::sqlite_test_pool;
+    use secrecy::SecretString;
+
+    fn email() -> Email {
+        Email::parse(SecretString::new("test@example.com".to_owned().into_boxed_str())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_add_replaces_previous_code() {
+        let store = SqliteTwoFACodeStore::new(sqlite_test_pool().await);
+
+        store.add_code(email(), TwoFACodePurpose::Login, LoginAttemptId::default(), TwoFACode::default()).await.unwrap();
+        let login_attempt_id = LoginAttemptId::default();
+        let code = TwoFACode::default();
+        store.add_code(email(), TwoFACodePurpose::Login, login_attempt_id.clone(), code.clone()).await.unwrap();
+
+        let (stored_id, stored_code) = store.get_code(&email(), TwoFACodePurpose::Login).await.unwrap();
+        assert_eq!(stored_id.as_ref(), login_attempt_id.as_ref());
+        assert_eq!(stored_code.as_ref(), code.as_ref());
+    }
+
+    #[tokio::test]
+    async fn test_remove_code() {
+        let store = SqliteTwoFACodeStore::new(sqlite_test_pool().await);
+
+        store.add_code(email(), TwoFACodePurpose::Login, LoginAttemptId::default(), TwoFACode::default()).await.unwrap();
+        store.remove_code(&email(), TwoFACodePurpose::Login).await.unwrap();
+
+        assert_eq!(store.get_code(&email(), TwoFACodePurpose::Login).await.err(), Some(TwoFACodeStoreError::LoginAttempIdNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_expired_code_is_not_found() {
+        let pool = sqlite_test_pool().await;
+        let store = SqliteTwoFACodeStore::new(pool.clone());
+
+        store.add_code(email(), TwoFACodePurpose::Login, LoginAttemptId::default(), TwoFACode::default()).await.unwrap();
+        sqlx::query("UPDATE two_fa_codes SET expires_at = ?")
+            .bind(Utc::now().timestamp() - 1)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert_eq!(store.get_code(&email(), TwoFACodePurpose::Login).await.err(), Some(TwoFACodeStoreError::LoginAttempIdNotFound));
+    }
+}
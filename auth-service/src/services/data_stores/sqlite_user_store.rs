@@ -0,0 +1,291 @@
+use chrono::Utc;
+use sqlx::{Row, SqlitePool, sqlite::SqliteRow};
+use color_eyre::eyre::Result;
+use secrecy::SecretString;
+
+use crate::domain::{
+    data_stores::{UserStore, UserStoreError},
+    Email, EmailChangeRevert, HashedPassword, User,
+};
+
+// The query! macros are checked against PostgreSQL, so the SQLite stores use
+// unchecked queries
+#[derive(Debug)]
+pub struct SqliteUserStore {
+    pool: SqlitePool,
+}
+
+impl SqliteUserStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl UserStore for SqliteUserStore {
+    #[tracing::instrument(name = "Adding user to SQLite", skip_all)]
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO users (email, password_hash, requires_2fa)
+            VALUES (?, ?, ?)
+            ON CONFLICT (email) DO NOTHING
+            "#,
+        )
+        .bind(user.email.as_ref())
+        .bind(user.password.as_ref())
+        .bind(user.requires_2fa)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        match result.rows_affected() {
+            0 => Err(UserStoreError::UserAlreadyExists),
+            _ => Ok(()),
+        }
+    }
+
+    #[tracing::instrument(name = "Retrieving user from SQLite", skip_all)]
+    async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
+        let row = sqlx::query(
+            r#"
+            SELECT email, password_hash, requires_2fa, email_undeliverable
+            FROM users
+            WHERE email = ?
+            "#,
+        )
+        .bind(email.as_ref())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .ok_or(UserStoreError::UserNotFound)?;
+
+        row_into_user(row)
+    }
+
+    #[tracing::instrument(name = "Validating user credentials in SQLite", skip_all)]
+    async fn validate_user(&self, email: &Email, raw_password: &str) -> Result<(), UserStoreError> {
+        let user = self.get_user(email).await?;
+
+        let password_secret = SecretString::new(raw_password.to_owned().into_boxed_str());
+        user.password
+            .verify_raw_password(&password_secret)
+            .await
+            .map_err(|_| UserStoreError::InvalidCredentials)
+    }
+
+    #[tracing::instrument(name = "Confirming email change in SQLite", skip_all)]
+    async fn confirm_email_change(&self, change_id: &str, current_email: &Email, new_email: Email) -> Result<(), UserStoreError> {
+        let now = Utc::now().timestamp();
+        let mut transaction = self.pool
+            .begin()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        let result = sqlx::query(
+            r#"
+            UPDATE users
+            SET email = ?, email_undeliverable = FALSE
+            WHERE email = ?
+            "#,
+        )
+        .bind(new_email.as_ref())
+        .bind(current_email.as_ref())
+        .execute(&mut *transaction)
+        .await
+        .map_err(map_update_email_error)?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        // The change that gave the account its old address can't be reverted anymore
+        sqlx::query("UPDATE email_changes SET ended_at = ? WHERE to_email = ? AND ended_at IS NULL")
+            .bind(now)
+            .bind(current_email.as_ref())
+            .execute(&mut *transaction)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        sqlx::query("INSERT INTO email_changes (id, from_email, to_email, confirmed_at) VALUES (?, ?, ?, ?)")
+            .bind(change_id)
+            .bind(current_email.as_ref())
+            .bind(new_email.as_ref())
+            .bind(now)
+            .execute(&mut *transaction)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        transaction.commit()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "Reverting email change in SQLite", skip_all)]
+    async fn revert_email_change(&self, change_id: &str) -> Result<EmailChangeRevert, UserStoreError> {
+        let mut transaction = self.pool
+            .begin()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        let change: Option<(String, String, Option<i64>)> = sqlx::query_as(
+            "SELECT from_email, to_email, ended_at FROM email_changes WHERE id = ?",
+        )
+        .bind(change_id)
+        .fetch_optional(&mut *transaction)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        let Some((from_email, to_email, ended_at)) = change else {
+            return Ok(EmailChangeRevert::NotConfirmed);
+        };
+        if ended_at.is_some() {
+            return Ok(EmailChangeRevert::Ended);
+        }
+
+        let result = sqlx::query("UPDATE users SET email = ?, email_undeliverable = FALSE WHERE email = ?")
+            .bind(from_email)
+            .bind(to_email)
+            .execute(&mut *transaction)
+            .await
+            .map_err(map_update_email_error)?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        sqlx::query("UPDATE email_changes SET ended_at = ? WHERE id = ?")
+            .bind(Utc::now().timestamp())
+            .bind(change_id)
+            .execute(&mut *transaction)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        transaction.commit()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        Ok(EmailChangeRevert::Reverted)
+    }
+
+    #[tracing::instrument(name = "Marking user email as undeliverable in SQLite", skip_all)]
+    async fn mark_email_undeliverable(&self, email: &Email) -> Result<(), UserStoreError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE users
+            SET email_undeliverable = TRUE
+            WHERE email = ?
+            "#,
+        )
+        .bind(email.as_ref())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        match result.rows_affected() {
+            0 => Err(UserStoreError::UserNotFound),
+            _ => Ok(()),
+        }
+    }
+}
+
+// Another account already holding the address violates the primary key
+fn map_update_email_error(e: sqlx::Error) -> UserStoreError {
+    match e {
+        sqlx::Error::Database(ref db_err) if db_err.is_unique_violation() => UserStoreError::UserAlreadyExists,
+        _ => UserStoreError::UnexpectedError(e.into()),
+    }
+}
+
+fn row_into_user(row: SqliteRow) -> Result<User, UserStoreError> {
+    let email: String = row.try_get("email").map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+    let password_hash: String = row.try_get("password_hash").map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+    let requires_2fa: bool = row.try_get("requires_2fa").map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+    let email_undeliverable: bool = row.try_get("email_undeliverable").map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+    let email = Email::parse(SecretString::new(email.into_boxed_str()))
+        .map_err(UserStoreError::UnexpectedError)?;
+    let password = HashedPassword::parse_password_hash(SecretString::new(password_hash.into_boxed_str()))
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+    let mut user = User::new(email, password, requires_2fa);
+    user.email_undeliverable = email_undeliverable;
+    Ok(user)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::data_stores::sqlite_test_pool;
+
+    fn email(address: &str) -> Email {
+        Email::parse(SecretString::new(address.to_owned().into_boxed_str())).unwrap()
+    }
+
+    async fn user(address: &str) -> User {
+        let password = SecretString::new("password123".to_owned().into_boxed_str());
+        let password = HashedPassword::parse(password).await.unwrap();
+        User::new(email(address), password, true)
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_user() {
+        let store = SqliteUserStore::new(sqlite_test_pool().await);
+
+        store.add_user(user("test@example.com").await).await.unwrap();
+        assert_eq!(
+            store.add_user(user("test@example.com").await).await,
+            Err(UserStoreError::UserAlreadyExists)
+        );
+
+        let stored = store.get_user(&email("test@example.com")).await.unwrap();
+        assert!(stored.requires_2fa);
+        assert!(!stored.email_undeliverable);
+        assert_eq!(store.get_user(&email("other@example.com")).await.err(), Some(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_validate_user() {
+        let store = SqliteUserStore::new(sqlite_test_pool().await);
+        store.add_user(user("test@example.com").await).await.unwrap();
+
+        assert_eq!(store.validate_user(&email("test@example.com"), "password123").await, Ok(()));
+        assert_eq!(
+            store.validate_user(&email("test@example.com"), "wrong-password").await,
+            Err(UserStoreError::InvalidCredentials)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_confirm_email_change() {
+        let store = SqliteUserStore::new(sqlite_test_pool().await);
+        store.add_user(user("old@example.com").await).await.unwrap();
+        store.add_user(user("taken@example.com").await).await.unwrap();
+        store.mark_email_undeliverable(&email("old@example.com")).await.unwrap();
+
+        assert_eq!(
+            store.confirm_email_change("change1", &email("old@example.com"), email("taken@example.com")).await,
+            Err(UserStoreError::UserAlreadyExists)
+        );
+        store.confirm_email_change("change1", &email("old@example.com"), email("new@example.com")).await.unwrap();
+
+        let stored = store.get_user(&email("new@example.com")).await.unwrap();
+        assert!(!stored.email_undeliverable);
+        assert_eq!(
+            store.confirm_email_change("change2", &email("old@example.com"), email("other@example.com")).await,
+            Err(UserStoreError::UserNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_revert_email_change() {
+        let store = SqliteUserStore::new(sqlite_test_pool().await);
+        store.add_user(user("old@example.com").await).await.unwrap();
+        store.confirm_email_change("change1", &email("old@example.com"), email("new@example.com")).await.unwrap();
+
+        assert_eq!(store.revert_email_change("unknown").await, Ok(EmailChangeRevert::NotConfirmed));
+        assert_eq!(store.revert_email_change("change1").await, Ok(EmailChangeRevert::Reverted));
+        assert!(store.get_user(&email("old@example.com")).await.is_ok());
+        assert_eq!(store.revert_email_change("change1").await, Ok(EmailChangeRevert::Ended));
+    }
+}
@@ -0,0 +1,291 @@
+use color_eyre::eyre::Result;
+
+use crate::app_state::{EmailClientType, EmailOutboxStoreType};
+
+use crate::domain::{Email, EmailClient, OutboxEmail};
+use crate::utils::config::EmailOutboxConfig;
+
+// Queues emails in the outbox instead of sending them. Routes return as soon as
+// the email is stored, and `EmailOutboxWorker` delivers it in the background.
+pub struct OutboxEmailClient {
+    outbox_store: EmailOutboxStoreType,
+}
+
+impl OutboxEmailClient {
+    pub fn new(outbox_store: EmailOutboxStoreType) -> Self {
+        Self { outbox_store }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for OutboxEmailClient {
+    #[tracing::instrument(name = "Queuing email", skip_all)]
+    async fn send_email(&self,
+        recipient: Email,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+    ) -> Result<()> {
+        // Every call is an email of its own, even when the content repeats, like
+        // a notice sent for each signup attempt
+        let email = OutboxEmail::new(recipient, subject, html_content, text_content);
+
+        self.outbox_store.enqueue(email).await?;
+
+        Ok(())
+    }
+}
+
+// Delivers queued emails through another email client, retrying failures
+// with exponential backoff
+pub struct EmailOutboxWorker {
+    outbox_store: EmailOutboxStoreType,
+    email_client: EmailClientType,
+    config: EmailOutboxConfig,
+}
+
+impl EmailOutboxWorker {
+    pub fn new(outbox_store: EmailOutboxStoreType, email_client: EmailClientType, config: EmailOutboxConfig) -> Self {
+        Self {
+            outbox_store,
+            email_client,
+            config,
+        }
+    }
+
+    pub async fn run(self) {
+        loop {
+            match self.process_batch().await {
+                // There may be more due emails, keep going
+                Ok(count) if count >= self.config.batch_size as usize => continue,
+                Ok(_) => {}
+                Err(e) => tracing::error!(error = ?e, "Failed to process email outbox"),
+            }
+
+            tokio::time::sleep(self.config.poll_interval).await;
+        }
+    }
+
+    // Attempts up to `batch_size` due emails. Each is claimed right before it is sent,
+    // so its lease only has to cover its own delivery. Returns how many were attempted.
+    #[tracing::instrument(name = "Processing email outbox", skip_all)]
+    pub async fn process_batch(&self) -> Result<usize> {
+        let mut count = 0;
+
+        while count < self.config.batch_size as usize {
+            let Some(email) = self.outbox_store.claim_next(self.config.lease).await? else {
+                break;
+            };
+
+            self.deliver(&email).await?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    async fn deliver(&self, email: &OutboxEmail) -> Result<()> {
+        let result = self.email_client
+            .send_email(email.recipient.clone(), &email.subject, &email.html_body, &email.text_body)
+            .await;
+
+        let outbox_store = &self.outbox_store;
+
+        match result {
+            Ok(()) => outbox_store.mark_sent(email.id).await?,
+            Err(e) if email.attempts >= self.config.max_attempts => {
+                tracing::error!(email_id = %email.id, attempts = email.attempts, error = ?e, "Giving up on email");
+                outbox_store.dead_letter(email.id, &e.to_string()).await?;
+            }
+            Err(e) => {
+                let retry_in = self.config.backoff(email.attempts);
+                tracing::warn!(email_id = %email.id, attempts = email.attempts, ?retry_in, error = ?e, "Failed to send email, will retry");
+                outbox_store.schedule_retry(email.id, &e.to_string(), retry_in).await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::data_stores::hashmap_email_outbox_store::{HashmapEmailOutboxStore, OutboxStatus};
+    use color_eyre::eyre::eyre;
+    use secrecy::SecretString;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    // Fails the first `failures` sends, then succeeds
+    struct FlakyEmailClient {
+        failures: usize,
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait::async_trait]
+    impl EmailClient for FlakyEmailClient {
+        async fn send_email(&self, _recipient: Email, _subject: &str, _html_content: &str, _text_content: &str) -> Result<()> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            match call < self.failures {
+                true => Err(eyre!("provider unavailable")),
+                false => Ok(()),
+            }
+        }
+    }
+
+    fn recipient() -> Email {
+        Email::parse(SecretString::new("user@example.com".to_owned().into_boxed_str())).unwrap()
+    }
+
+    // No waiting between retries so tests can run every attempt right away
+    fn config(max_attempts: u32) -> EmailOutboxConfig {
+        EmailOutboxConfig {
+            max_attempts,
+            initial_backoff: Duration::ZERO,
+            ..EmailOutboxConfig::default()
+        }
+    }
+
+    struct Setup {
+        store: Arc<HashmapEmailOutboxStore>,
+        client: OutboxEmailClient,
+        worker: EmailOutboxWorker,
+        calls: Arc<AtomicUsize>,
+    }
+
+    fn setup(failures: usize, max_attempts: u32) -> Setup {
+        let store = Arc::new(HashmapEmailOutboxStore::default());
+        let calls = Arc::new(AtomicUsize::new(0));
+        let email_client = Arc::new(FlakyEmailClient { failures, calls: calls.clone() });
+
+        Setup {
+            client: OutboxEmailClient::new(store.clone()),
+            worker: EmailOutboxWorker::new(store.clone(), email_client, config(max_attempts)),
+            store,
+            calls,
+        }
+    }
+
+    // Only one email is queued in these tests
+    async fn queued_email_status(store: &Arc<HashmapEmailOutboxStore>) -> (OutboxStatus, u32, Option<String>) {
+        let ids = store.ids().await;
+        assert_eq!(ids.len(), 1);
+        store.status(&ids[0]).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_delivers_queued_email() {
+        let setup = setup(0, 3);
+
+        setup.client.send_email(recipient(), "Subject", "<p>Body</p>", "Body").await.unwrap();
+        assert_eq!(setup.calls.load(Ordering::SeqCst), 0);
+
+        assert_eq!(setup.worker.process_batch().await.unwrap(), 1);
+        assert_eq!(setup.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(queued_email_status(&setup.store).await.0, OutboxStatus::Sent);
+
+        // Sent emails are never delivered again
+        assert_eq!(setup.worker.process_batch().await.unwrap(), 0);
+        assert_eq!(setup.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_same_email_queued_twice_is_sent_twice() {
+        let setup = setup(0, 3);
+
+        setup.client.send_email(recipient(), "Subject", "<p>Body</p>", "Body").await.unwrap();
+        setup.client.send_email(recipient(), "Subject", "<p>Body</p>", "Body").await.unwrap();
+
+        assert_eq!(setup.worker.process_batch().await.unwrap(), 2);
+        assert_eq!(setup.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_attempts_at_most_batch_size_emails() {
+        let store = Arc::new(HashmapEmailOutboxStore::default());
+        let calls = Arc::new(AtomicUsize::new(0));
+        let email_client = Arc::new(FlakyEmailClient { failures: 0, calls: calls.clone() });
+        let config = EmailOutboxConfig { batch_size: 2, ..config(3) };
+        let worker = EmailOutboxWorker::new(store.clone(), email_client, config);
+
+        let client = OutboxEmailClient::new(store.clone());
+        for _ in 0..3 {
+            client.send_email(recipient(), "Subject", "<p>Body</p>", "Body").await.unwrap();
+        }
+
+        assert_eq!(worker.process_batch().await.unwrap(), 2);
+        assert_eq!(worker.process_batch().await.unwrap(), 1);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retries_failed_email() {
+        let mut setup = setup(2, 3);
+        // Without backoff a failed email is due again within the same batch
+        setup.worker.config.batch_size = 1;
+        setup.client.send_email(recipient(), "Subject", "<p>Body</p>", "Body").await.unwrap();
+
+        setup.worker.process_batch().await.unwrap();
+        let (status, attempts, last_error) = queued_email_status(&setup.store).await;
+        assert_eq!(status, OutboxStatus::Pending);
+        assert_eq!(attempts, 1);
+        assert_eq!(last_error.as_deref(), Some("provider unavailable"));
+
+        setup.worker.process_batch().await.unwrap();
+        setup.worker.process_batch().await.unwrap();
+
+        let (status, attempts, last_error) = queued_email_status(&setup.store).await;
+        assert_eq!(status, OutboxStatus::Sent);
+        assert_eq!(attempts, 3);
+        assert_eq!(last_error, None);
+    }
+
+    #[tokio::test]
+    async fn test_dead_letters_after_max_attempts() {
+        let setup = setup(usize::MAX, 3);
+        setup.client.send_email(recipient(), "Subject", "<p>Body</p>", "Body").await.unwrap();
+
+        for _ in 0..5 {
+            setup.worker.process_batch().await.unwrap();
+        }
+
+        let (status, attempts, _) = queued_email_status(&setup.store).await;
+        assert_eq!(status, OutboxStatus::Dead);
+        assert_eq!(attempts, 3);
+        assert_eq!(setup.calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_waits_for_backoff_before_retrying() {
+        let store = Arc::new(HashmapEmailOutboxStore::default());
+        let calls = Arc::new(AtomicUsize::new(0));
+        let email_client = Arc::new(FlakyEmailClient { failures: usize::MAX, calls: calls.clone() });
+        let worker = EmailOutboxWorker::new(store.clone(), email_client, EmailOutboxConfig::default());
+
+        OutboxEmailClient::new(store.clone())
+            .send_email(recipient(), "Subject", "<p>Body</p>", "Body")
+            .await
+            .unwrap();
+
+        assert_eq!(worker.process_batch().await.unwrap(), 1);
+        assert_eq!(worker.process_batch().await.unwrap(), 0);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let config = EmailOutboxConfig {
+            initial_backoff: Duration::from_secs(5),
+            max_backoff: Duration::from_secs(30),
+            ..EmailOutboxConfig::default()
+        };
+
+        assert_eq!(config.backoff(1), Duration::from_secs(5));
+        assert_eq!(config.backoff(2), Duration::from_secs(10));
+        assert_eq!(config.backoff(3), Duration::from_secs(20));
+        assert_eq!(config.backoff(4), Duration::from_secs(30));
+        assert_eq!(config.backoff(100), Duration::from_secs(30));
+    }
+}
@@ -0,0 +1,230 @@
+use askama::Template;
+use axum::http::{header::ACCEPT_LANGUAGE, HeaderMap};
+use color_eyre::eyre::{Context, Result};
+
+use crate::domain::{Email, EmailClient};
+use crate::utils::config::Branding;
+
+// Languages emails can be sent in. Each one has its own folder in `templates/emails`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Locale {
+    #[default]
+    En,
+    Es,
+}
+
+impl Locale {
+    pub fn parse(tag: &str) -> Option<Self> {
+        // Only the primary language matters, so "es-MX" is "es"
+        let language = tag.trim().split(['-', '_']).next()?.to_ascii_lowercase();
+
+        match language.as_str() {
+            "en" => Some(Locale::En),
+            "es" => Some(Locale::Es),
+            _ => None,
+        }
+    }
+
+    // Pick the first supported language from the `Accept-Language` header
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        headers
+            .get(ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| {
+                value
+                    .split(',')
+                    .filter_map(|part| part.split(';').next())
+                    .find_map(Locale::parse)
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::Es => "es",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum EmailTemplate {
+    TwoFACode { code: String },
+    Verification { link: String },
+    PasswordReset { link: String },
+    LoginLink { link: String, expires_in_minutes: i64 },
+    SecurityNotice { event: SecurityEvent, link: Option<String> },
+}
+
+// Account events users are told about by email
+#[derive(Debug, Clone)]
+pub enum SecurityEvent {
+    EmailChangeRequested { new_email: String },
+    AccountLocked { minutes: u64 },
+    SignupAttempted,
+}
+
+#[derive(Debug)]
+pub struct RenderedEmail {
+    pub subject: String,
+    pub html_body: String,
+    pub text_body: String,
+}
+
+// `emails/email.html` and `emails/email.txt` pick the per-locale template to include
+#[derive(Template)]
+#[template(path = "emails/email.html")]
+struct HtmlEmail<'a> {
+    email: &'a EmailTemplate,
+    locale: Locale,
+    branding: &'a Branding,
+}
+
+#[derive(Template)]
+#[template(path = "emails/email.txt")]
+struct TextEmail<'a> {
+    email: &'a EmailTemplate,
+    locale: Locale,
+    branding: &'a Branding,
+}
+
+impl EmailTemplate {
+    // Names used by the dev preview route
+    pub const NAMES: [&'static str; 5] = ["two-fa-code", "verification", "password-reset", "login-link", "security-notice"];
+
+    #[tracing::instrument(name = "Render email template", skip_all)]
+    pub fn render(&self, locale: Locale, branding: &Branding) -> Result<RenderedEmail> {
+        let html_body = HtmlEmail { email: self, locale, branding }
+            .render()
+            .wrap_err("failed to render HTML email")?;
+        let text_body = TextEmail { email: self, locale, branding }
+            .render()
+            .wrap_err("failed to render text email")?;
+
+        Ok(RenderedEmail {
+            subject: self.subject(locale, branding),
+            html_body,
+            text_body,
+        })
+    }
+
+    fn subject(&self, locale: Locale, branding: &Branding) -> String {
+        let subject = match (self, locale) {
+            (EmailTemplate::TwoFACode { .. }, Locale::En) => "Your verification code",
+            (EmailTemplate::TwoFACode { .. }, Locale::Es) => "Tu código de verificación",
+            (EmailTemplate::Verification { .. }, Locale::En) => "Confirm your email address",
+            (EmailTemplate::Verification { .. }, Locale::Es) => "Confirma tu dirección de correo",
+            (EmailTemplate::PasswordReset { .. }, Locale::En) => "Reset your password",
+            (EmailTemplate::PasswordReset { .. }, Locale::Es) => "Restablece tu contraseña",
+            (EmailTemplate::LoginLink { .. }, Locale::En) => "Your login link",
+            (EmailTemplate::LoginLink { .. }, Locale::Es) => "Tu enlace de inicio de sesión",
+            (EmailTemplate::SecurityNotice { .. }, Locale::En) => "Security notice",
+            (EmailTemplate::SecurityNotice { .. }, Locale::Es) => "Aviso de seguridad",
+        };
+
+        format!("{} - {}", branding.product_name, subject)
+    }
+
+    // Build a template filled with sample values, for previewing
+    pub fn sample(name: &str) -> Option<Self> {
+        let link = "https://example.com/link?token=sample".to_owned();
+
+        match name {
+            "two-fa-code" => Some(EmailTemplate::TwoFACode { code: "123456".to_owned() }),
+            "verification" => Some(EmailTemplate::Verification { link }),
+            "password-reset" => Some(EmailTemplate::PasswordReset { link }),
+            "login-link" => Some(EmailTemplate::LoginLink { link, expires_in_minutes: 5 }),
+            "security-notice" => Some(EmailTemplate::SecurityNotice {
+                event: SecurityEvent::EmailChangeRequested { new_email: "new@example.com".to_owned() },
+                link: Some(link),
+            }),
+            _ => None,
+        }
+    }
+}
+
+// Render a template and send it through the given email client
+pub async fn send_email_template(
+    email_client: &dyn EmailClient,
+    recipient: Email,
+    template: &EmailTemplate,
+    locale: Locale,
+    branding: &Branding,
+) -> Result<()> {
+    let email = template.render(locale, branding)?;
+
+    email_client
+        .send_email(recipient, &email.subject, &email.html_body, &email.text_body)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_locale_from_headers() {
+        let test_cases = [
+            ("es-MX,es;q=0.9,en;q=0.8", Locale::Es),
+            ("fr-FR, en-US;q=0.8", Locale::En),
+            ("fr-FR", Locale::En),
+            ("ES", Locale::Es),
+        ];
+
+        for (header, expected) in test_cases {
+            let mut headers = HeaderMap::new();
+            headers.insert(ACCEPT_LANGUAGE, HeaderValue::from_static(header));
+            assert_eq!(Locale::from_headers(&headers), expected, "Failed for header: {}", header);
+        }
+
+        assert_eq!(Locale::from_headers(&HeaderMap::new()), Locale::En);
+    }
+
+    #[test]
+    fn test_render_all_templates() {
+        let branding = Branding::default();
+
+        for name in EmailTemplate::NAMES {
+            for locale in [Locale::En, Locale::Es] {
+                let template = EmailTemplate::sample(name).unwrap();
+                let email = template.render(locale, &branding).unwrap();
+
+                assert!(email.subject.starts_with(&branding.product_name));
+                assert!(email.html_body.contains(&branding.logo_url), "Failed for {} {:?}", name, locale);
+                assert!(email.html_body.contains(&branding.support_email), "Failed for {} {:?}", name, locale);
+                assert!(email.text_body.contains(&branding.support_email), "Failed for {} {:?}", name, locale);
+                assert!(!email.text_body.contains('<'), "Failed for {} {:?}", name, locale);
+            }
+        }
+    }
+
+    #[test]
+    fn test_render_two_fa_code() {
+        let template = EmailTemplate::TwoFACode { code: "654321".to_owned() };
+
+        let email = template.render(Locale::En, &Branding::default()).unwrap();
+        assert!(email.html_body.contains("654321"));
+        assert!(email.text_body.contains("654321"));
+
+        let email = template.render(Locale::Es, &Branding::default()).unwrap();
+        assert!(email.text_body.contains("Tu código de verificación es"));
+    }
+
+    #[test]
+    fn test_render_escapes_html() {
+        let template = EmailTemplate::SecurityNotice {
+            event: SecurityEvent::EmailChangeRequested { new_email: "<script>@example.com".to_owned() },
+            link: None,
+        };
+
+        let email = template.render(Locale::En, &Branding::default()).unwrap();
+        assert!(!email.html_body.contains("<script>"));
+        assert!(email.text_body.contains("<script>@example.com"));
+    }
+
+    #[test]
+    fn test_sample_unknown_template() {
+        assert!(EmailTemplate::sample("unknown").is_none());
+    }
+}
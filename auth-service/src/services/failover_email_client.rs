@@ -0,0 +1,337 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use color_eyre::eyre::{eyre, Result};
+use reqwest::Client;
+use secrecy::SecretString;
+
+use crate::domain::{Email, EmailClient};
+use crate::services::postmark_email_client::PostmarkEmailClient;
+use crate::services::smtp_email_client::SmtpEmailClient;
+use crate::services::file_email_client::FileEmailClient;
+use crate::utils::config::{EmailProviderConfig, EmailProvidersConfig};
+use crate::utils::constants::{prod, POSTMARK_AUTH_TOKEN};
+
+// Tries each provider in order until one sends the email. A provider that keeps
+// failing is skipped for a while so emails don't wait on it.
+pub struct FailoverEmailClient {
+    providers: Vec<Provider>,
+    attempt_timeout: Duration,
+    failure_threshold: u32,
+    cooldown: Duration,
+}
+
+struct Provider {
+    name: String,
+    client: Box<dyn EmailClient>,
+    breaker: Mutex<CircuitBreaker>,
+}
+
+#[derive(Default)]
+struct CircuitBreaker {
+    consecutive_failures: u32,
+    // While set and in the future, the provider is skipped
+    open_until: Option<Instant>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProviderHealth {
+    pub name: String,
+    pub healthy: bool,
+    pub consecutive_failures: u32,
+}
+
+impl FailoverEmailClient {
+    pub fn new(
+        providers: Vec<(String, Box<dyn EmailClient>)>,
+        attempt_timeout: Duration,
+        failure_threshold: u32,
+        cooldown: Duration,
+    ) -> Self {
+        let providers = providers
+            .into_iter()
+            .map(|(name, client)| {
+                metrics::gauge!("email_provider_healthy", "provider" => name.clone()).set(1.0);
+                Provider { name, client, breaker: Mutex::new(CircuitBreaker::default()) }
+            })
+            .collect();
+
+        Self {
+            providers,
+            attempt_timeout,
+            failure_threshold,
+            cooldown,
+        }
+    }
+
+    // Builds every configured provider
+    pub fn from_config(config: &EmailProvidersConfig) -> Result<Self> {
+        let providers = config.providers
+            .iter()
+            .map(|provider| {
+                let client: Box<dyn EmailClient> = match provider {
+                    EmailProviderConfig::Postmark => Box::new(postmark_email_client()?),
+                    EmailProviderConfig::Smtp(smtp_config) => Box::new(SmtpEmailClient::new(smtp_config)?),
+                    EmailProviderConfig::File(directory) => Box::new(FileEmailClient::new(directory.to_owned(), prod_sender()?)?),
+                };
+                Ok((provider.name(), client))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        if providers.is_empty() {
+            return Err(eyre!("at least one email provider must be configured"));
+        }
+
+        Ok(Self::new(providers, config.attempt_timeout, config.failure_threshold, config.cooldown))
+    }
+
+    pub fn health(&self) -> Vec<ProviderHealth> {
+        let now = Instant::now();
+
+        self.providers
+            .iter()
+            .map(|provider| {
+                let breaker = provider.breaker.lock().unwrap();
+                ProviderHealth {
+                    name: provider.name.clone(),
+                    healthy: !breaker.is_open(now),
+                    consecutive_failures: breaker.consecutive_failures,
+                }
+            })
+            .collect()
+    }
+
+    fn record_success(&self, provider: &Provider) {
+        *provider.breaker.lock().unwrap() = CircuitBreaker::default();
+
+        metrics::counter!("email_provider_sends_total", "provider" => provider.name.clone(), "outcome" => "success").increment(1);
+        metrics::gauge!("email_provider_healthy", "provider" => provider.name.clone()).set(1.0);
+    }
+
+    fn record_failure(&self, provider: &Provider, outcome: &'static str) {
+        let tripped = {
+            let mut breaker = provider.breaker.lock().unwrap();
+            breaker.consecutive_failures += 1;
+
+            // Also re-opens a breaker whose trial attempt after the cooldown failed
+            let tripped = breaker.consecutive_failures >= self.failure_threshold;
+            if tripped {
+                breaker.open_until = Some(Instant::now() + self.cooldown);
+            }
+            tripped
+        };
+
+        metrics::counter!("email_provider_sends_total", "provider" => provider.name.clone(), "outcome" => outcome).increment(1);
+        if tripped {
+            tracing::warn!(provider = %provider.name, cooldown = ?self.cooldown, "Email provider is failing, skipping it for now");
+            metrics::gauge!("email_provider_healthy", "provider" => provider.name.clone()).set(0.0);
+        }
+    }
+}
+
+impl CircuitBreaker {
+    fn is_open(&self, now: Instant) -> bool {
+        self.open_until.is_some_and(|open_until| now < open_until)
+    }
+}
+
+fn prod_sender() -> Result<Email> {
+    Email::parse(SecretString::new(prod::email_client::SENDER.to_owned().into_boxed_str()))
+}
+
+fn postmark_email_client() -> Result<PostmarkEmailClient> {
+    let http_client = Client::builder()
+        .timeout(prod::email_client::TIMEOUT)
+        .build()?;
+
+    Ok(PostmarkEmailClient::new(
+        prod::email_client::BASE_URL.to_owned(),
+        prod_sender()?,
+        POSTMARK_AUTH_TOKEN.to_owned(),
+        http_client,
+    ))
+}
+
+#[async_trait::async_trait]
+impl EmailClient for FailoverEmailClient {
+    #[tracing::instrument(name = "Sending email with failover", skip_all)]
+    async fn send_email(&self,
+        recipient: Email,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+    ) -> Result<()> {
+        let mut errors = Vec::new();
+
+        for provider in &self.providers {
+            if provider.breaker.lock().unwrap().is_open(Instant::now()) {
+                metrics::counter!("email_provider_sends_total", "provider" => provider.name.clone(), "outcome" => "skipped").increment(1);
+                errors.push(format!("{}: skipped after repeated failures", provider.name));
+                continue;
+            }
+
+            let send = provider.client.send_email(recipient.clone(), subject, html_content, text_content);
+
+            match tokio::time::timeout(self.attempt_timeout, send).await {
+                Ok(Ok(())) => {
+                    self.record_success(provider);
+                    return Ok(());
+                }
+                Ok(Err(e)) => {
+                    tracing::warn!(provider = %provider.name, error = ?e, "Email provider failed to send email");
+                    self.record_failure(provider, "failure");
+                    errors.push(format!("{}: {}", provider.name, e));
+                }
+                Err(_) => {
+                    tracing::warn!(provider = %provider.name, "Email provider timed out");
+                    self.record_failure(provider, "timeout");
+                    errors.push(format!("{}: timed out", provider.name));
+                }
+            }
+        }
+
+        Err(eyre!("every email provider failed ({})", errors.join("; ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Clone, Copy)]
+    enum Behavior {
+        Succeed,
+        Fail,
+        Hang,
+    }
+
+    struct FakeEmailClient {
+        behavior: Behavior,
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait::async_trait]
+    impl EmailClient for FakeEmailClient {
+        async fn send_email(&self, _recipient: Email, _subject: &str, _html_content: &str, _text_content: &str) -> Result<()> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            match self.behavior {
+                Behavior::Succeed => Ok(()),
+                Behavior::Fail => Err(eyre!("provider error")),
+                Behavior::Hang => {
+                    tokio::time::sleep(Duration::from_secs(10)).await;
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    fn client(behaviors: &[Behavior], cooldown: Duration) -> (FailoverEmailClient, Vec<Arc<AtomicUsize>>) {
+        let calls: Vec<_> = behaviors.iter().map(|_| Arc::new(AtomicUsize::new(0))).collect();
+        let providers = behaviors
+            .iter()
+            .zip(&calls)
+            .enumerate()
+            .map(|(i, (behavior, calls))| {
+                let client: Box<dyn EmailClient> = Box::new(FakeEmailClient { behavior: *behavior, calls: calls.clone() });
+                (format!("provider-{}", i), client)
+            })
+            .collect();
+
+        (FailoverEmailClient::new(providers, Duration::from_millis(50), 2, cooldown), calls)
+    }
+
+    async fn send(client: &FailoverEmailClient) -> Result<()> {
+        let recipient = Email::parse(SecretString::new("user@example.com".to_owned().into_boxed_str())).unwrap();
+        client.send_email(recipient, "Subject", "<p>Body</p>", "Body").await
+    }
+
+    fn call_counts(calls: &[Arc<AtomicUsize>]) -> Vec<usize> {
+        calls.iter().map(|calls| calls.load(Ordering::SeqCst)).collect()
+    }
+
+    #[tokio::test]
+    async fn test_uses_primary_when_it_works() {
+        let (client, calls) = client(&[Behavior::Succeed, Behavior::Succeed], Duration::from_secs(60));
+
+        send(&client).await.unwrap();
+
+        assert_eq!(call_counts(&calls), vec![1, 0]);
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_on_error() {
+        let (client, calls) = client(&[Behavior::Fail, Behavior::Succeed], Duration::from_secs(60));
+
+        send(&client).await.unwrap();
+
+        assert_eq!(call_counts(&calls), vec![1, 1]);
+        assert_eq!(client.health()[0].consecutive_failures, 1);
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_on_timeout() {
+        let (client, calls) = client(&[Behavior::Hang, Behavior::Succeed], Duration::from_secs(60));
+
+        let start = Instant::now();
+        send(&client).await.unwrap();
+
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert_eq!(call_counts(&calls), vec![1, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_fails_if_every_provider_fails() {
+        let (client, _) = client(&[Behavior::Fail, Behavior::Fail], Duration::from_secs(60));
+
+        let error = send(&client).await.unwrap_err().to_string();
+
+        assert!(error.contains("provider-0: provider error"));
+        assert!(error.contains("provider-1: provider error"));
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker_skips_failing_provider() {
+        let (client, calls) = client(&[Behavior::Fail, Behavior::Succeed], Duration::from_secs(60));
+
+        // The breaker trips after 2 failures
+        send(&client).await.unwrap();
+        send(&client).await.unwrap();
+        assert!(!client.health()[0].healthy);
+
+        send(&client).await.unwrap();
+        assert_eq!(call_counts(&calls), vec![2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker_retries_provider_after_cooldown() {
+        let (client, calls) = client(&[Behavior::Fail, Behavior::Succeed], Duration::from_millis(20));
+
+        send(&client).await.unwrap();
+        send(&client).await.unwrap();
+        assert!(!client.health()[0].healthy);
+
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        assert!(client.health()[0].healthy);
+
+        // The trial attempt fails, so the breaker opens again straight away
+        send(&client).await.unwrap();
+        assert_eq!(call_counts(&calls), vec![3, 3]);
+        assert!(!client.health()[0].healthy);
+    }
+
+    #[tokio::test]
+    async fn test_success_resets_circuit_breaker() {
+        let (client, _) = client(&[Behavior::Succeed], Duration::from_secs(60));
+
+        client.record_failure(&client.providers[0], "failure");
+        assert_eq!(client.health()[0].consecutive_failures, 1);
+
+        send(&client).await.unwrap();
+        assert_eq!(
+            client.health(),
+            vec![ProviderHealth { name: "provider-0".to_owned(), healthy: true, consecutive_failures: 0 }]
+        );
+    }
+}
@@ -0,0 +1,167 @@
+use chrono::Utc;
+use color_eyre::eyre::{Context, Result};
+use lettre::message::{Mailbox, MultiPart};
+use lettre::Message;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::domain::{Email, EmailClient};
+
+// Writes each email to a directory as an .eml file instead of sending it, so
+// codes and links can be read when running locally. A small JSON summary is
+// written next to every message for the dev mailbox.
+pub struct FileEmailClient {
+    directory: PathBuf,
+    sender: Email,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MailboxMessage {
+    pub id: String,
+    pub recipient: String,
+    pub subject: String,
+    pub sent_at: String,
+    pub text_body: String,
+}
+
+impl FileEmailClient {
+    pub fn new(directory: PathBuf, sender: Email) -> Result<Self> {
+        std::fs::create_dir_all(&directory)
+            .wrap_err_with(|| format!("failed to create email directory {}", directory.display()))?;
+
+        Ok(Self { directory, sender })
+    }
+
+    // Most recent messages first
+    pub async fn recent_messages(directory: &Path, limit: usize) -> Result<Vec<MailboxMessage>> {
+        let mut entries = match tokio::fs::read_dir(directory).await {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut paths = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().is_some_and(|extension| extension == "json") {
+                paths.push(path);
+            }
+        }
+
+        // File names start with the time they were written
+        paths.sort_unstable_by(|a, b| b.cmp(a));
+
+        let mut messages = Vec::new();
+        for path in paths.into_iter().take(limit) {
+            let summary = tokio::fs::read(&path).await?;
+            messages.push(serde_json::from_slice(&summary)
+                .wrap_err_with(|| format!("invalid mailbox entry {}", path.display()))?);
+        }
+
+        Ok(messages)
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for FileEmailClient {
+    #[tracing::instrument(name = "Writing email to file", skip_all)]
+    async fn send_email(&self,
+        recipient: Email,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+    ) -> Result<()> {
+        let message = Message::builder()
+            .from(self.sender.as_ref().parse::<Mailbox>()?)
+            .to(recipient.as_ref().parse::<Mailbox>()?)
+            .subject(subject)
+            .multipart(MultiPart::alternative_plain_html(
+                text_content.to_owned(),
+                html_content.to_owned(),
+            ))?;
+
+        let sent_at = Utc::now();
+        let id = format!("{}-{}", sent_at.format("%Y%m%dT%H%M%S%.3fZ"), Uuid::new_v4().simple());
+        let path = self.directory.join(format!("{}.eml", id));
+
+        let summary = MailboxMessage {
+            id: id.clone(),
+            recipient: recipient.as_ref().to_owned(),
+            subject: subject.to_owned(),
+            sent_at: sent_at.to_rfc3339(),
+            text_body: text_content.to_owned(),
+        };
+
+        tokio::fs::write(&path, message.formatted()).await
+            .wrap_err_with(|| format!("failed to write email to {}", path.display()))?;
+        tokio::fs::write(self.directory.join(format!("{}.json", id)), serde_json::to_vec_pretty(&summary)?).await?;
+
+        tracing::info!(
+            path = %path.display(),
+            "Wrote email to {} with subject: {}\n{}",
+            recipient.as_ref(),
+            subject,
+            text_content,
+        );
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::SecretString;
+
+    fn email(address: &str) -> Email {
+        Email::parse(SecretString::new(address.to_owned().into_boxed_str())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_send_email_writes_eml_file() {
+        let directory = tempfile::tempdir().unwrap();
+        let client = FileEmailClient::new(directory.path().to_owned(), email("sender@example.com")).unwrap();
+
+        client.send_email(email("user@example.com"), "Your code", "<p>123456</p>", "123456").await.unwrap();
+
+        let eml_files: Vec<_> = std::fs::read_dir(directory.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "eml"))
+            .collect();
+        assert_eq!(eml_files.len(), 1);
+
+        let eml = std::fs::read_to_string(&eml_files[0]).unwrap();
+        assert!(eml.contains("To: user@example.com"));
+        assert!(eml.contains("Subject: Your code"));
+        assert!(eml.contains("123456"));
+    }
+
+    #[tokio::test]
+    async fn test_recent_messages_returns_newest_first() {
+        let directory = tempfile::tempdir().unwrap();
+        let client = FileEmailClient::new(directory.path().to_owned(), email("sender@example.com")).unwrap();
+
+        for subject in ["First", "Second", "Third"] {
+            client.send_email(email("user@example.com"), subject, "", subject).await.unwrap();
+            // Ids are ordered by their millisecond timestamp
+            tokio::time::sleep(std::time::Duration::from_millis(2)).await;
+        }
+
+        let messages = FileEmailClient::recent_messages(directory.path(), 2).await.unwrap();
+        let subjects: Vec<_> = messages.iter().map(|message| message.subject.as_str()).collect();
+        assert_eq!(subjects, vec!["Third", "Second"]);
+        assert_eq!(messages[0].recipient, "user@example.com");
+        assert_eq!(messages[0].text_body, "Third");
+    }
+
+    #[tokio::test]
+    async fn test_recent_messages_is_empty_for_missing_directory() {
+        let directory = tempfile::tempdir().unwrap();
+
+        let messages = FileEmailClient::recent_messages(&directory.path().join("missing"), 10).await.unwrap();
+        assert!(messages.is_empty());
+    }
+}
@@ -0,0 +1,49 @@
+use std::net::IpAddr;
+use std::path::Path;
+
+use color_eyre::eyre::{Context, Result};
+use maxminddb::{geoip2, Reader};
+
+// Approximate location of IP addresses from a local MaxMind (GeoLite2/GeoIP2 City
+// or Country) database. Without a database every location is unknown.
+#[derive(Default)]
+pub struct GeoIp {
+    reader: Option<Reader<Vec<u8>>>,
+}
+
+impl GeoIp {
+    pub fn open(path: &Path) -> Result<Self> {
+        let reader = Reader::open_readfile(path)
+            .wrap_err(format!("failed to open GeoIP database {}", path.display()))?;
+
+        Ok(Self { reader: Some(reader) })
+    }
+
+    // "City, Country", or just the country when the city isn't known
+    pub fn locate(&self, ip: IpAddr) -> Option<String> {
+        let reader = self.reader.as_ref()?;
+        let record: geoip2::City = reader.lookup(ip).ok()?;
+
+        let city = record.city.and_then(|city| english_name(city.names));
+        let country = record.country.and_then(|country| english_name(country.names));
+
+        match (city, country) {
+            (Some(city), Some(country)) => Some(format!("{}, {}", city, country)),
+            (None, Some(country)) => Some(country),
+            (Some(city), None) => Some(city),
+            (None, None) => None,
+        }
+    }
+}
+
+fn english_name(names: Option<std::collections::BTreeMap<&str, &str>>) -> Option<String> {
+    names?.get("en").map(|name| (*name).to_owned())
+}
+
+impl std::fmt::Debug for GeoIp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GeoIp")
+            .field("enabled", &self.reader.is_some())
+            .finish()
+    }
+}
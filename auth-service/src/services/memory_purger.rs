@@ -0,0 +1,65 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+// In-memory stores whose entries expire
+#[async_trait::async_trait]
+pub trait ExpiringStore: Send + Sync {
+    // Drops expired entries and returns how many
+    async fn evict_expired(&self) -> usize;
+}
+
+// Drops expired entries from the in-memory stores, like PostgresPurger does for
+// Postgres. The stores already ignore expired entries, this only frees the memory.
+pub struct MemoryPurger {
+    stores: Vec<Arc<dyn ExpiringStore>>,
+    interval: Duration,
+}
+
+impl MemoryPurger {
+    pub fn new(stores: Vec<Arc<dyn ExpiringStore>>, interval: Duration) -> Self {
+        Self { stores, interval }
+    }
+
+    pub async fn run(self) {
+        loop {
+            let count = self.purge().await;
+            if count > 0 {
+                tracing::debug!(count, "Evicted expired entries");
+            }
+
+            tokio::time::sleep(self.interval).await;
+        }
+    }
+
+    pub async fn purge(&self) -> usize {
+        let mut count = 0;
+        for store in &self.stores {
+            count += store.evict_expired().await;
+        }
+
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::data_stores::BannedTokenStore;
+    use crate::services::data_stores::hashset_banned_token_store::HashsetBannedTokenStore;
+    use secrecy::SecretString;
+
+    #[tokio::test]
+    async fn test_purge_counts_evictions_of_every_store() {
+        let expired = Arc::new(HashsetBannedTokenStore::default());
+        let live = Arc::new(HashsetBannedTokenStore::default());
+        for (store, ttl) in [(&expired, Duration::ZERO), (&live, Duration::from_secs(60))] {
+            store.add_token(&SecretString::new("token1".to_owned().into_boxed_str()), ttl).await.unwrap();
+            store.add_token(&SecretString::new("token2".to_owned().into_boxed_str()), ttl).await.unwrap();
+        }
+
+        let purger = MemoryPurger::new(vec![expired, live], Duration::from_secs(60));
+
+        assert_eq!(purger.purge().await, 2);
+        assert_eq!(purger.purge().await, 0);
+    }
+}
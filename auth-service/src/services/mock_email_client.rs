@@ -6,12 +6,12 @@ pub struct MockEmailClient {}
 
 #[async_trait::async_trait]
 impl EmailClient for MockEmailClient {
-    async fn send_email(&self, recipient: Email, subject: &str, content: &str) ->  Result<()> {
+    async fn send_email(&self, recipient: Email, subject: &str, _html_content: &str, text_content: &str) ->  Result<()> {
         tracing::debug!(
             "Sending email to {} with subject: {} and content: {}",
             recipient.as_ref(),
             subject,
-            content
+            text_content
         );
         
         Ok(())
@@ -1,3 +1,13 @@
 pub mod data_stores;
 pub mod mock_email_client;
-pub mod postmark_email_client;
\ No newline at end of file
+pub mod postmark_email_client;
+pub mod smtp_email_client;
+pub mod file_email_client;
+pub mod failover_email_client;
+pub mod email_templates;
+pub mod email_outbox;
+pub mod geoip;
+pub mod redis_connection;
+pub mod postgres_purger;
+pub mod sqlite_purger;
+pub mod memory_purger;
@@ -0,0 +1,72 @@
+use color_eyre::eyre::Result;
+use sqlx::PgPool;
+
+use std::time::Duration;
+
+use crate::utils::auth::REVERT_EMAIL_CHANGE_TTL_SECONDS;
+
+// Deletes expired rows from the Postgres stores that keep short-lived data. The
+// stores already ignore expired rows, this only keeps the tables from growing.
+pub struct PostgresPurger {
+    pool: PgPool,
+    interval: Duration,
+    // Age at which sent and dead-lettered emails are deleted from the outbox
+    outbox_retention: Duration,
+}
+
+impl PostgresPurger {
+    pub fn new(pool: PgPool, interval: Duration, outbox_retention: Duration) -> Self {
+        Self { pool, interval, outbox_retention }
+    }
+
+    pub async fn run(self) {
+        loop {
+            match self.purge().await {
+                Ok(0) => {}
+                Ok(count) => tracing::debug!(count, "Purged expired rows"),
+                Err(e) => tracing::error!(error = ?e, "Failed to purge expired rows"),
+            }
+
+            tokio::time::sleep(self.interval).await;
+        }
+    }
+
+    // Returns how many rows were deleted
+    #[tracing::instrument(name = "Purging expired rows from PostgreSQL", skip_all)]
+    pub async fn purge(&self) -> Result<u64> {
+        let banned_tokens = sqlx::query!("DELETE FROM banned_tokens WHERE expires_at <= now()")
+            .execute(&self.pool)
+            .await?;
+        let two_fa_codes = sqlx::query!("DELETE FROM two_fa_codes WHERE expires_at <= now()")
+            .execute(&self.pool)
+            .await?;
+        let sessions = sqlx::query!("DELETE FROM sessions WHERE expires_at <= now()")
+            .execute(&self.pool)
+            .await?;
+
+        // Their revert links have expired
+        let email_changes = sqlx::query!(
+            "DELETE FROM email_changes WHERE confirmed_at <= now() - make_interval(secs => $1)",
+            REVERT_EMAIL_CHANGE_TTL_SECONDS as f64,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        let outbox = sqlx::query!(
+            r#"
+            DELETE FROM email_outbox
+            WHERE sent_at <= now() - make_interval(secs => $1)
+                OR dead_at <= now() - make_interval(secs => $1)
+            "#,
+            self.outbox_retention.as_secs_f64(),
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(banned_tokens.rows_affected()
+            + two_fa_codes.rows_affected()
+            + sessions.rows_affected()
+            + email_changes.rows_affected()
+            + outbox.rows_affected())
+    }
+}
@@ -33,7 +33,8 @@ impl EmailClient for PostmarkEmailClient {
     async fn send_email(&self, 
         recipient: Email,
         subject: &str,
-        content: &str,
+        html_content: &str,
+        text_content: &str,
     ) -> Result<()> {
         // Parse the base URL and join it with the email endpoint
         let base = Url::parse(&self.base_url)?;
@@ -44,8 +45,8 @@ impl EmailClient for PostmarkEmailClient {
             from: self.sender.as_ref(),
             to: recipient.as_ref(),
             subject,
-            html_body: content,
-            text_body: content,
+            html_body: html_content,
+            text_body: text_content,
             message_stream: MESSAGE_STREAM,
         };
 
@@ -158,7 +159,7 @@ mod tests {
 
         // Execute the send_email function and check the outcome
         let outcome = email_client
-            .send_email(email(), &subject(), &content())
+            .send_email(email(), &subject(), &content(), &content())
             .await;
 
         assert!(outcome.is_ok());
@@ -179,7 +180,7 @@ mod tests {
 
         // Execute the send_email function and check the outcome
         let outcome = email_client
-            .send_email(email(), &subject(), &content())
+            .send_email(email(), &subject(), &content(), &content())
             .await;
 
         assert!(outcome.is_err());
@@ -201,7 +202,7 @@ mod tests {
 
         // Execute the send_email function and check the outcome
         let outcome = email_client
-            .send_email(email(), &subject(), &content())
+            .send_email(email(), &subject(), &content(), &content())
             .await;
 
         assert!(outcome.is_err());
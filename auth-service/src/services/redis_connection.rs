@@ -0,0 +1,209 @@
+use redis::{
+    aio::{ConnectionLike, ConnectionManager, ConnectionManagerConfig},
+    cluster::ClusterClientBuilder,
+    cluster_async::ClusterConnection,
+    sentinel::{SentinelClient, SentinelNodeConnectionInfo, SentinelServerType},
+    Client, Cmd, ConnectionAddr, ErrorKind, IntoConnectionInfo, Pipeline, RedisFuture, RedisResult, TlsMode, Value,
+};
+use secrecy::{ExposeSecret, SecretString};
+use tokio::sync::Mutex;
+
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use crate::utils::config::RedisConfig;
+
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(5);
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
+const RETRIES: u32 = 5;
+const MAX_RETRY_DELAY_MS: u64 = 2000;
+
+// Connection shared by every Redis store, whatever the deployment looks like.
+// Requests from all tasks are multiplexed over it and it reconnects on its own,
+// so every call just uses a clone.
+#[derive(Clone)]
+pub enum RedisConnection {
+    Standalone(ConnectionManager),
+    Sentinel(SentinelConnection),
+    Cluster(ClusterConnection),
+}
+
+impl RedisConnection {
+    pub async fn connect(config: &RedisConfig) -> RedisResult<Self> {
+        match config {
+            RedisConfig::Standalone { url } => {
+                let client = Client::open(url.expose_secret())?;
+                Ok(RedisConnection::Standalone(connection_manager(client).await?))
+            }
+            RedisConfig::Sentinel { sentinels, master_name, url } => {
+                let connection = SentinelConnection::connect(sentinels, master_name, url).await?;
+                Ok(RedisConnection::Sentinel(connection))
+            }
+            RedisConfig::Cluster { nodes } => {
+                // Credentials and TLS are taken from the first node
+                let connection = ClusterClientBuilder::new(nodes.iter().map(|node| node.expose_secret()))
+                    .connection_timeout(CONNECTION_TIMEOUT)
+                    .response_timeout(RESPONSE_TIMEOUT)
+                    .retries(RETRIES)
+                    .max_retry_wait(MAX_RETRY_DELAY_MS)
+                    .build()?
+                    .get_async_connection()
+                    .await?;
+                Ok(RedisConnection::Cluster(connection))
+            }
+        }
+    }
+}
+
+impl ConnectionLike for RedisConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        match self {
+            RedisConnection::Standalone(conn) => conn.req_packed_command(cmd),
+            RedisConnection::Sentinel(conn) => conn.req_packed_command(cmd),
+            RedisConnection::Cluster(conn) => conn.req_packed_command(cmd),
+        }
+    }
+
+    fn req_packed_commands<'a>(&'a mut self, cmd: &'a Pipeline, offset: usize, count: usize) -> RedisFuture<'a, Vec<Value>> {
+        match self {
+            RedisConnection::Standalone(conn) => conn.req_packed_commands(cmd, offset, count),
+            RedisConnection::Sentinel(conn) => conn.req_packed_commands(cmd, offset, count),
+            RedisConnection::Cluster(conn) => conn.req_packed_commands(cmd, offset, count),
+        }
+    }
+
+    fn get_db(&self) -> i64 {
+        match self {
+            RedisConnection::Standalone(conn) => conn.get_db(),
+            RedisConnection::Sentinel(conn) => conn.get_db(),
+            RedisConnection::Cluster(conn) => conn.get_db(),
+        }
+    }
+}
+
+// `{}` marks the part of a key that Redis Cluster hashes. Tagging the ID keeps all
+// keys about the same user or token in one slot, so scripts and transactions that
+// touch several of them still work.
+pub fn hash_tagged_key(prefix: &str, id: &str) -> String {
+    format!("{}{{{}}}", prefix, id)
+}
+
+async fn connection_manager(client: Client) -> RedisResult<ConnectionManager> {
+    let config = ConnectionManagerConfig::new()
+        .set_connection_timeout(CONNECTION_TIMEOUT)
+        .set_response_timeout(RESPONSE_TIMEOUT)
+        .set_number_of_retries(RETRIES as usize)
+        .set_max_delay(MAX_RETRY_DELAY_MS);
+
+    ConnectionManager::new_with_config(client, config).await
+}
+
+// Talks to the current master. `ConnectionManager` only ever reconnects to the
+// address it started with, so after a failover the master is looked up again.
+#[derive(Clone)]
+pub struct SentinelConnection {
+    sentinel: Arc<Mutex<SentinelClient>>,
+    // Bumped every time the master is looked up, so concurrent failures only
+    // trigger one lookup
+    master: Arc<RwLock<(u64, ConnectionManager)>>,
+}
+
+impl SentinelConnection {
+    async fn connect(sentinels: &[SecretString], master_name: &str, url: &SecretString) -> RedisResult<Self> {
+        let master_info = url.expose_secret().into_connection_info()?;
+        let tls_mode = match master_info.addr {
+            ConnectionAddr::TcpTls { insecure: true, .. } => Some(TlsMode::Insecure),
+            ConnectionAddr::TcpTls { insecure: false, .. } => Some(TlsMode::Secure),
+            _ => None,
+        };
+
+        let mut sentinel = SentinelClient::build(
+            sentinels.iter().map(|sentinel| sentinel.expose_secret()).collect(),
+            master_name.to_owned(),
+            Some(SentinelNodeConnectionInfo {
+                tls_mode,
+                redis_connection_info: Some(master_info.redis),
+            }),
+            SentinelServerType::Master,
+        )?;
+
+        let master = connection_manager(sentinel.async_get_client().await?).await?;
+
+        Ok(Self {
+            sentinel: Arc::new(Mutex::new(sentinel)),
+            master: Arc::new(RwLock::new((0, master))),
+        })
+    }
+
+    fn current(&self) -> (u64, ConnectionManager) {
+        self.master.read().expect("sentinel master lock poisoned").clone()
+    }
+
+    // The failed request still returns its error, only the next ones go to the new master
+    async fn recover_from<T>(&self, generation: u64, result: &RedisResult<T>) {
+        let Err(err) = result else {
+            return;
+        };
+
+        let master_moved = err.kind() == ErrorKind::ReadOnly
+            || err.is_connection_dropped()
+            || err.is_connection_refusal()
+            || err.is_timeout();
+        if !master_moved {
+            return;
+        }
+
+        let mut sentinel = self.sentinel.lock().await;
+        if self.current().0 != generation {
+            // Another request already looked the master up
+            return;
+        }
+
+        let master = match sentinel.async_get_client().await {
+            Ok(client) => connection_manager(client).await,
+            Err(e) => Err(e),
+        };
+
+        match master {
+            Ok(master) => {
+                tracing::warn!("Redis master changed after error: {}", err);
+                *self.master.write().expect("sentinel master lock poisoned") = (generation + 1, master);
+            }
+            Err(e) => tracing::error!("Failed to look up Redis master: {}", e),
+        }
+    }
+}
+
+impl ConnectionLike for SentinelConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        Box::pin(async move {
+            let (generation, mut conn) = self.current();
+            let result = conn.req_packed_command(cmd).await;
+            self.recover_from(generation, &result).await;
+            result
+        })
+    }
+
+    fn req_packed_commands<'a>(&'a mut self, cmd: &'a Pipeline, offset: usize, count: usize) -> RedisFuture<'a, Vec<Value>> {
+        Box::pin(async move {
+            let (generation, mut conn) = self.current();
+            let result = conn.req_packed_commands(cmd, offset, count).await;
+            self.recover_from(generation, &result).await;
+            result
+        })
+    }
+
+    fn get_db(&self) -> i64 {
+        self.current().1.get_db()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_tagged_key_hashes_only_the_id() {
+        assert_eq!(hash_tagged_key("two_fa_code:", "user@example.com"), "two_fa_code:{user@example.com}");
+    }
+}
@@ -0,0 +1,94 @@
+use color_eyre::eyre::{Context, Result};
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::transport::smtp::client::{Certificate, Tls, TlsParameters};
+use lettre::transport::smtp::PoolConfig;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use secrecy::ExposeSecret;
+use std::time::Duration;
+
+use crate::domain::{Email, EmailClient};
+use crate::utils::config::{SmtpConfig, SmtpTls};
+
+// Sends emails through an SMTP server. Connections are pooled and reused
+// between emails.
+pub struct SmtpEmailClient {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    sender: Email,
+    timeout: Duration,
+}
+
+impl SmtpEmailClient {
+    pub fn new(config: &SmtpConfig) -> Result<Self> {
+        let pool_config = PoolConfig::new()
+            .max_size(config.pool_max_size)
+            .idle_timeout(config.pool_idle_timeout);
+
+        let mut builder = AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host)
+            .port(config.port)
+            .tls(tls(config)?)
+            .timeout(Some(config.timeout))
+            .pool_config(pool_config);
+
+        if let (Some(username), Some(password)) = (&config.username, &config.password) {
+            builder = builder.credentials(Credentials::new(
+                username.to_owned(),
+                password.expose_secret().to_owned(),
+            ));
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+            sender: config.sender.clone(),
+            timeout: config.timeout,
+        })
+    }
+}
+
+fn tls(config: &SmtpConfig) -> Result<Tls> {
+    if config.tls == SmtpTls::None {
+        return Ok(Tls::None);
+    }
+
+    let mut parameters = TlsParameters::builder(config.host.to_owned());
+
+    if let Some(path) = &config.ca_cert_path {
+        let pem = std::fs::read(path)
+            .wrap_err_with(|| format!("failed to read SMTP CA certificate from {}", path.display()))?;
+        parameters = parameters.add_root_certificate(Certificate::from_pem(&pem)?);
+    }
+
+    let parameters = parameters.build_rustls()?;
+
+    match config.tls {
+        SmtpTls::StartTls => Ok(Tls::Required(parameters)),
+        _ => Ok(Tls::Wrapper(parameters)),
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for SmtpEmailClient {
+    #[tracing::instrument(name = "Sending email", skip_all)]
+    async fn send_email(&self,
+        recipient: Email,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+    ) -> Result<()> {
+        let message = Message::builder()
+            .from(self.sender.as_ref().parse::<Mailbox>()?)
+            .to(recipient.as_ref().parse::<Mailbox>()?)
+            .subject(subject)
+            .multipart(MultiPart::alternative_plain_html(
+                text_content.to_owned(),
+                html_content.to_owned(),
+            ))?;
+
+        // The transport only applies its timeout while connecting, so bound the whole exchange
+        tokio::time::timeout(self.timeout, self.transport.send(message))
+            .await
+            .wrap_err("timed out sending email over SMTP")??;
+
+        Ok(())
+    }
+}
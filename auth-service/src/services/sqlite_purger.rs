@@ -0,0 +1,83 @@
+use chrono::Utc;
+use color_eyre::eyre::Result;
+use sqlx::SqlitePool;
+
+use std::time::Duration;
+
+use crate::utils::auth::REVERT_EMAIL_CHANGE_TTL_SECONDS;
+
+// Deletes expired rows from the SQLite stores, like PostgresPurger does for Postgres
+pub struct SqlitePurger {
+    pool: SqlitePool,
+    interval: Duration,
+}
+
+impl SqlitePurger {
+    pub fn new(pool: SqlitePool, interval: Duration) -> Self {
+        Self { pool, interval }
+    }
+
+    pub async fn run(self) {
+        loop {
+            match self.purge().await {
+                Ok(0) => {}
+                Ok(count) => tracing::debug!(count, "Purged expired rows"),
+                Err(e) => tracing::error!(error = ?e, "Failed to purge expired rows"),
+            }
+
+            tokio::time::sleep(self.interval).await;
+        }
+    }
+
+    // Returns how many rows were deleted
+    #[tracing::instrument(name = "Purging expired rows from SQLite", skip_all)]
+    pub async fn purge(&self) -> Result<u64> {
+        let now = Utc::now().timestamp();
+
+        let banned_tokens = sqlx::query("DELETE FROM banned_tokens WHERE expires_at <= ?")
+            .bind(now)
+            .execute(&self.pool)
+            .await?;
+        let two_fa_codes = sqlx::query("DELETE FROM two_fa_codes WHERE expires_at <= ?")
+            .bind(now)
+            .execute(&self.pool)
+            .await?;
+
+        // Their revert links have expired
+        let email_changes = sqlx::query("DELETE FROM email_changes WHERE confirmed_at <= ?")
+            .bind(now - REVERT_EMAIL_CHANGE_TTL_SECONDS)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(banned_tokens.rows_affected() + two_fa_codes.rows_affected() + email_changes.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::data_stores::sqlite_test_pool;
+
+    #[tokio::test]
+    async fn test_purge_only_deletes_expired_rows() {
+        let pool = sqlite_test_pool().await;
+        let now = Utc::now().timestamp();
+
+        sqlx::query("INSERT INTO banned_tokens (token_hash, expires_at) VALUES ('expired', ?), ('live', ?)")
+            .bind(now - 1)
+            .bind(now + 60)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let purger = SqlitePurger::new(pool.clone(), Duration::from_secs(60));
+        assert_eq!(purger.purge().await.unwrap(), 1);
+        assert_eq!(purger.purge().await.unwrap(), 0);
+
+        let remaining: Vec<String> = sqlx::query_scalar("SELECT token_hash FROM banned_tokens")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(remaining, ["live"]);
+    }
+}
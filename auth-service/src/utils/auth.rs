@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use axum_extra::extract::cookie::{Cookie, SameSite};
 use chrono::Utc;
 use jsonwebtoken::{DecodingKey, EncodingKey, Validation, decode, encode};
@@ -5,26 +7,62 @@ use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use color_eyre::eyre::{Context, ContextCompat, Report, Result, eyre};
 use secrecy::{ExposeSecret, SecretString};
+use subtle::ConstantTimeEq;
 
-use super::constants::{JWT_COOKIE_NAME, JWT_SECRET};
+use super::config::{AuthCookieConfig, SameSiteMode};
+use super::constants::JWT_SECRET;
 use crate::app_state::BannedTokenStoreType;
-use crate::domain::Email;
+use crate::domain::{Email, SessionId, SessionPolicy};
 
 // Create cookie with a new JWT auth token
 #[tracing::instrument(name = "Generate_Auth_Cookie", skip_all)]
-pub fn generate_auth_cookie(email: &Email) -> Result<Cookie<'static>> {
-    let token = generate_auth_token(email)?;
-    Ok(create_auth_cookie(token))
+pub fn generate_auth_cookie(email: &Email, session_id: Option<&SessionId>, config: &AuthCookieConfig) -> Result<Cookie<'static>> {
+    let token = generate_auth_token(email, session_id)?;
+    Ok(create_auth_cookie(token, config))
+}
+
+// Create cookie holding a session ID. It lasts as long as the session can,
+// the session store decides when it actually ends.
+#[tracing::instrument(name = "Generate_Session_Cookie", skip_all)]
+pub fn generate_session_cookie(session_id: &SessionId, policy: &SessionPolicy, config: &AuthCookieConfig) -> Cookie<'static> {
+    let mut cookie = create_auth_cookie(session_id.as_ref().to_owned(), config);
+    cookie.set_max_age(time::Duration::seconds(policy.absolute_timeout.num_seconds()));
+
+    cookie
 }
 
 // Create cookie and set the value to the passed-in token string
 #[tracing::instrument(name = "Create_Auth_Cookie", skip_all)]
-fn create_auth_cookie(token: String) -> Cookie<'static> {
-    Cookie::build((JWT_COOKIE_NAME, token))
+fn create_auth_cookie(token: String, config: &AuthCookieConfig) -> Cookie<'static> {
+    let mut cookie = Cookie::build((config.name(), token))
         .path("/") // apply cookie to all URLs on the server
         .http_only(true) // prevent JavaScript from accessing the cookie
-        .same_site(SameSite::Lax) // send cookie with "same-site" requests, and with "cross-site" top-level navigations.
-        .build()
+        .secure(config.secure) // only send the cookie over HTTPS
+        .same_site(config.same_site.into())
+        .max_age(time::Duration::seconds(TOKEN_TTL_SECONDS)) // expire together with the token
+        .build();
+
+    if let Some(domain) = &config.domain {
+        cookie.set_domain(domain.clone());
+    }
+
+    cookie
+}
+
+// Cookie to pass to `CookieJar::remove`. Browsers only delete a cookie when
+// the removal has the same name, path and domain.
+pub fn removal_auth_cookie(config: &AuthCookieConfig) -> Cookie<'static> {
+    create_auth_cookie(String::new(), config)
+}
+
+impl From<SameSiteMode> for SameSite {
+    fn from(mode: SameSiteMode) -> Self {
+        match mode {
+            SameSiteMode::Strict => SameSite::Strict,
+            SameSiteMode::Lax => SameSite::Lax,
+            SameSiteMode::None => SameSite::None,
+        }
+    }
 }
 
 #[derive(Debug, Error)]
@@ -38,9 +76,13 @@ pub enum GenerateTokenError {
 // This value determines how long the JWT auth token is valid for
 pub const TOKEN_TTL_SECONDS: i64 = 600; // 10 minutes
 
-// Create JWT auth token
+// How long a 2FA code can be used after it was sent
+pub const TWO_FA_CODE_TTL_SECONDS: i64 = 600; // 10 minutes
+
+// Create JWT auth token. The session ID ties it to the session record listed
+// under `/sessions`, so it can be revoked from there.
 #[tracing::instrument(name = "Generate_Auth_Token", skip_all)]
-fn generate_auth_token(email: &Email) -> Result<String> {
+pub fn generate_auth_token(email: &Email, session_id: Option<&SessionId>) -> Result<String> {
     let delta = chrono::Duration::try_seconds(TOKEN_TTL_SECONDS)
         .wrap_err("failed to create 10 minute time delta")?;
 
@@ -57,7 +99,9 @@ fn generate_auth_token(email: &Email) -> Result<String> {
 
     let sub = email.as_ref().to_owned();
 
-    let claims = Claims { sub, exp };
+    let sid = session_id.map(|id| id.as_ref().to_owned());
+
+    let claims = Claims { sub, exp, sid };
 
     create_token(&claims)
 }
@@ -71,8 +115,6 @@ pub async fn validate_token(
     // Check if token is in banned token store
     let token = SecretString::new(token.to_owned().into_boxed_str());
     let token_is_banned = banned_token_store
-        .read()
-        .await
         .check_token(&token)
         .await?;
 
@@ -90,9 +132,90 @@ pub async fn validate_token(
     .wrap_err("failed to decode token")
 }
 
-// Create JWT auth token by encoding claims using the JWT secret
+// How long a token with this `exp` can still be used, at least a second
+pub fn time_to_expiry(exp: usize) -> Duration {
+    let now = Utc::now().timestamp() as u64;
+    Duration::from_secs((exp as u64).saturating_sub(now).max(1))
+}
+
+// Ban a JWT or link token until it would have expired anyway
+#[tracing::instrument(name = "Ban_Token", skip_all)]
+pub async fn ban_token(
+    token: &str,
+    exp: usize,
+    banned_token_store: BannedTokenStoreType,
+) -> Result<()> {
+    banned_token_store
+        .add_token(&SecretString::new(token.to_owned().into_boxed_str()), time_to_expiry(exp))
+        .await
+        .wrap_err("failed to ban token")
+}
+
+// Create a token that is embedded in a link sent to the user by email.
+// The purpose is stored in the `aud` claim, so a link token is never accepted as an
+// auth token (and vice versa).
+#[tracing::instrument(name = "Generate_Link_Token", skip_all)]
+pub fn generate_link_token(
+    email: &Email,
+    purpose: LinkTokenPurpose,
+    jti: &str,
+    new_email: Option<&Email>,
+) -> Result<String> {
+    let delta = chrono::Duration::try_seconds(purpose.ttl_seconds())
+        .wrap_err("failed to create link token time delta")?;
+
+    let exp = Utc::now()
+        .checked_add_signed(delta)
+        .ok_or(eyre!("Failed to add time delta to current time"))?
+        .timestamp();
+
+    let exp: usize = exp
+        .try_into()
+        .wrap_err(format!("failed to cast exp time to usize. exp time: {}", exp))?;
+
+    let claims = LinkClaims {
+        sub: email.as_ref().to_owned(),
+        exp,
+        aud: purpose.audience().to_owned(),
+        jti: jti.to_owned(),
+        new_email: new_email.map(|email| email.as_ref().to_owned()),
+    };
+
+    create_token(&claims)
+}
+
+// Check if a link token is valid for the given purpose and has not been used yet
+#[tracing::instrument(name = "Validate_Link_Token", skip_all)]
+pub async fn validate_link_token(
+    token: &str,
+    purpose: LinkTokenPurpose,
+    banned_token_store: BannedTokenStoreType,
+) -> Result<LinkClaims> {
+    let token = SecretString::new(token.to_owned().into_boxed_str());
+    let token_is_banned = banned_token_store
+        .check_token(&token)
+        .await?;
+
+    if token_is_banned {
+        return Err(eyre!("link token has already been used"));
+    }
+
+    let mut validation = Validation::default();
+    validation.set_audience(&[purpose.audience()]);
+    validation.set_required_spec_claims(&["exp", "aud"]);
+
+    decode::<LinkClaims>(
+        token.expose_secret(),
+        &DecodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
+        &validation,
+    )
+    .map(|data| data.claims)
+    .wrap_err("failed to decode link token")
+}
+
+// Create JWT by encoding claims using the JWT secret
 #[tracing::instrument(name = "Create_Token", skip_all)]
-fn create_token(claims: &Claims) -> Result<String> {
+fn create_token<T: Serialize>(claims: &T) -> Result<String> {
     encode(
         &jsonwebtoken::Header::default(),
         &claims,
@@ -105,21 +228,73 @@ fn create_token(claims: &Claims) -> Result<String> {
 pub struct Claims {
     pub sub: String,
     pub exp: usize,
+    // Session the token belongs to. Tokens issued before sessions were tracked have none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+}
+
+// What a link token may be used for. Each purpose has its own lifetime.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LinkTokenPurpose {
+    ConfirmEmailChange,
+    RevertEmailChange,
+    MagicLink,
+}
+
+impl LinkTokenPurpose {
+    fn audience(&self) -> &'static str {
+        match self {
+            LinkTokenPurpose::ConfirmEmailChange => "confirm-email-change",
+            LinkTokenPurpose::RevertEmailChange => "revert-email-change",
+            LinkTokenPurpose::MagicLink => "magic-link",
+        }
+    }
+
+    fn ttl_seconds(&self) -> i64 {
+        match self {
+            LinkTokenPurpose::ConfirmEmailChange => TOKEN_TTL_SECONDS,
+            LinkTokenPurpose::RevertEmailChange => REVERT_EMAIL_CHANGE_TTL_SECONDS,
+            LinkTokenPurpose::MagicLink => MAGIC_LINK_TTL_SECONDS,
+        }
+    }
+}
+
+// The revert link goes to the old address, whose owner may not read it right away
+pub const REVERT_EMAIL_CHANGE_TTL_SECONDS: i64 = 60 * 60 * 24; // 24 hours
+
+// A magic link logs the user in, so keep it short-lived
+pub const MAGIC_LINK_TTL_SECONDS: i64 = 300; // 5 minutes
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LinkClaims {
+    pub sub: String,
+    pub exp: usize,
+    pub aud: String,
+    // Shared by all links issued for the same request
+    pub jti: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_email: Option<String>,
+}
+
+// Compares secrets without leaking how much of them matched through timing
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.ct_eq(b).into()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::constants::JWT_COOKIE_NAME;
     use secrecy::SecretString;
-    use tokio::sync::RwLock;
     use std::sync::Arc;
+    use crate::domain::data_stores::BannedTokenStore;
     use crate::services::data_stores::hashset_banned_token_store::HashsetBannedTokenStore;
 
     #[tokio::test]
     async fn test_generate_auth_cookie() {
         let email_secret = SecretString::new("test@example.com".to_owned().into_boxed_str());
         let email = Email::parse(email_secret).unwrap();
-        let cookie = generate_auth_cookie(&email).unwrap();
+        let cookie = generate_auth_cookie(&email, None, &AuthCookieConfig::default()).unwrap();
         assert_eq!(cookie.name(), JWT_COOKIE_NAME);
         assert_eq!(cookie.value().split('.').count(), 3);
         assert_eq!(cookie.path(), Some("/"));
@@ -130,29 +305,106 @@ mod tests {
     #[tokio::test]
     async fn test_create_auth_cookie() {
         let token = "test_token".to_owned();
-        let cookie = create_auth_cookie(token.clone());
+        let cookie = create_auth_cookie(token.clone(), &AuthCookieConfig::default());
         assert_eq!(cookie.name(), JWT_COOKIE_NAME);
         assert_eq!(cookie.value(), token);
         assert_eq!(cookie.path(), Some("/"));
         assert_eq!(cookie.http_only(), Some(true));
+        assert_eq!(cookie.secure(), Some(false));
         assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+        assert_eq!(cookie.max_age(), Some(time::Duration::seconds(TOKEN_TTL_SECONDS)));
+        assert_eq!(cookie.domain(), None);
+    }
+
+    // Every combination of cookie settings either builds a cookie with exactly
+    // those attributes or is rejected before a browser could drop it
+    #[test]
+    fn test_auth_cookie_settings_combinations() {
+        let same_site_modes = [
+            (SameSiteMode::Strict, SameSite::Strict),
+            (SameSiteMode::Lax, SameSite::Lax),
+            (SameSiteMode::None, SameSite::None),
+        ];
+
+        for host_prefix in [false, true] {
+            for secure in [false, true] {
+                for domain in [None, Some("example.com".to_owned())] {
+                    for (same_site, expected_same_site) in same_site_modes {
+                        let config = AuthCookieConfig {
+                            host_prefix,
+                            secure,
+                            domain: domain.clone(),
+                            same_site,
+                        };
+
+                        let should_be_valid = (!host_prefix || (secure && domain.is_none()))
+                            && (same_site != SameSiteMode::None || secure);
+                        assert_eq!(config.validate().is_ok(), should_be_valid, "{:?}", config);
+
+                        if !should_be_valid {
+                            continue;
+                        }
+
+                        let cookie = create_auth_cookie("token".to_owned(), &config);
+                        let expected_name = if host_prefix { "__Host-jwt" } else { JWT_COOKIE_NAME };
+                        assert_eq!(cookie.name(), expected_name, "{:?}", config);
+                        assert_eq!(cookie.secure(), Some(secure), "{:?}", config);
+                        assert_eq!(cookie.domain(), domain.as_deref(), "{:?}", config);
+                        assert_eq!(cookie.same_site(), Some(expected_same_site), "{:?}", config);
+                        assert_eq!(cookie.path(), Some("/"), "{:?}", config);
+                        assert_eq!(cookie.http_only(), Some(true), "{:?}", config);
+                        assert_eq!(cookie.max_age(), Some(time::Duration::seconds(TOKEN_TTL_SECONDS)), "{:?}", config);
+
+                        // The removal has to match the cookie, or the browser keeps it
+                        let removal = removal_auth_cookie(&config);
+                        assert_eq!(removal.name(), cookie.name(), "{:?}", config);
+                        assert_eq!(removal.path(), cookie.path(), "{:?}", config);
+                        assert_eq!(removal.domain(), cookie.domain(), "{:?}", config);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_same_site_mode() {
+        assert_eq!("Strict".parse(), Ok(SameSiteMode::Strict));
+        assert_eq!("lax".parse(), Ok(SameSiteMode::Lax));
+        assert_eq!("NONE".parse(), Ok(SameSiteMode::None));
+        assert!("sometimes".parse::<SameSiteMode>().is_err());
     }
 
     #[tokio::test]
     async fn test_generate_auth_token() {
         let email_secret = SecretString::new("test@example.com".to_owned().into_boxed_str());
         let email = Email::parse(email_secret).unwrap();
-        let result = generate_auth_token(&email).unwrap();
+        let result = generate_auth_token(&email, None).unwrap();
         assert_eq!(result.split('.').count(), 3);
     }
 
+    #[tokio::test]
+    async fn test_auth_token_carries_session_id() {
+        let email_secret = SecretString::new("test@example.com".to_owned().into_boxed_str());
+        let email = Email::parse(email_secret).unwrap();
+        let banned_token_store = Arc::new(HashsetBannedTokenStore::default());
+        let session_id = SessionId::default();
+
+        let token = generate_auth_token(&email, Some(&session_id)).unwrap();
+        let claims = validate_token(&token, banned_token_store.clone()).await.unwrap();
+        assert_eq!(claims.sid.as_deref(), Some(session_id.as_ref()));
+
+        let token = generate_auth_token(&email, None).unwrap();
+        let claims = validate_token(&token, banned_token_store).await.unwrap();
+        assert_eq!(claims.sid, None);
+    }
+
     #[tokio::test]
     async fn test_validate_token_with_valid_token() {
         let email_secret = SecretString::new("test@example.com".to_owned().into_boxed_str());
         let email = Email::parse(email_secret).unwrap();
-        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        let banned_token_store = Arc::new(HashsetBannedTokenStore::default());
 
-        let token = generate_auth_token(&email).unwrap();
+        let token = generate_auth_token(&email, None).unwrap();
         let result = validate_token(&token, banned_token_store).await.unwrap();
         assert_eq!(result.sub, "test@example.com");
 
@@ -166,9 +418,97 @@ mod tests {
 
     #[tokio::test]
     async fn test_validate_token_with_invalid_token() {
-        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        let banned_token_store = Arc::new(HashsetBannedTokenStore::default());
         let token = "invalid_token".to_owned();
         let result = validate_token(&token, banned_token_store).await;
         assert!(result.is_err());
     }
-}
+
+    #[tokio::test]
+    async fn test_validate_link_token_with_valid_token() {
+        let email = Email::parse(SecretString::new("test@example.com".to_owned().into_boxed_str())).unwrap();
+        let new_email = Email::parse(SecretString::new("new@example.com".to_owned().into_boxed_str())).unwrap();
+        let banned_token_store = Arc::new(HashsetBannedTokenStore::default());
+
+        let token = generate_link_token(&email, LinkTokenPurpose::ConfirmEmailChange, "id", Some(&new_email)).unwrap();
+        let result = validate_link_token(&token, LinkTokenPurpose::ConfirmEmailChange, banned_token_store)
+            .await
+            .unwrap();
+
+        assert_eq!(result.sub, "test@example.com");
+        assert_eq!(result.jti, "id");
+        assert_eq!(result.new_email, Some("new@example.com".to_owned()));
+    }
+
+    #[tokio::test]
+    async fn test_validate_link_token_with_wrong_purpose() {
+        let email = Email::parse(SecretString::new("test@example.com".to_owned().into_boxed_str())).unwrap();
+        let banned_token_store = Arc::new(HashsetBannedTokenStore::default());
+
+        let token = generate_link_token(&email, LinkTokenPurpose::RevertEmailChange, "id", None).unwrap();
+        let result = validate_link_token(&token, LinkTokenPurpose::ConfirmEmailChange, banned_token_store).await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_link_token_and_auth_token_are_not_interchangeable() {
+        let email = Email::parse(SecretString::new("test@example.com".to_owned().into_boxed_str())).unwrap();
+        let banned_token_store = Arc::new(HashsetBannedTokenStore::default());
+
+        let link_token = generate_link_token(&email, LinkTokenPurpose::ConfirmEmailChange, "id", None).unwrap();
+        assert!(validate_token(&link_token, banned_token_store.clone()).await.is_err());
+
+        let auth_token = generate_auth_token(&email, None).unwrap();
+        let result = validate_link_token(&auth_token, LinkTokenPurpose::ConfirmEmailChange, banned_token_store).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_link_token_with_banned_token() {
+        let email = Email::parse(SecretString::new("test@example.com".to_owned().into_boxed_str())).unwrap();
+        let banned_token_store = Arc::new(HashsetBannedTokenStore::default());
+
+        let token = generate_link_token(&email, LinkTokenPurpose::ConfirmEmailChange, "id", None).unwrap();
+        banned_token_store
+            .add_token(&SecretString::new(token.clone().into_boxed_str()), Duration::from_secs(60))
+            .await
+            .unwrap();
+
+        let result = validate_link_token(&token, LinkTokenPurpose::ConfirmEmailChange, banned_token_store).await;
+        assert!(result.is_err());
+    }
+
+    // The ban outlives the 10 minutes of an auth token, or a used link would work again
+    #[tokio::test(start_paused = true)]
+    async fn test_banned_link_token_stays_banned_until_it_expires() {
+        let email = Email::parse(SecretString::new("test@example.com".to_owned().into_boxed_str())).unwrap();
+        let banned_token_store = Arc::new(HashsetBannedTokenStore::default());
+
+        let token = generate_link_token(&email, LinkTokenPurpose::RevertEmailChange, "id", None).unwrap();
+        let claims = validate_link_token(&token, LinkTokenPurpose::RevertEmailChange, banned_token_store.clone())
+            .await
+            .unwrap();
+        ban_token(&token, claims.exp, banned_token_store.clone()).await.unwrap();
+
+        tokio::time::advance(Duration::from_secs(TOKEN_TTL_SECONDS as u64 + 1)).await;
+
+        let result = validate_link_token(&token, LinkTokenPurpose::RevertEmailChange, banned_token_store.clone()).await;
+        assert!(result.is_err());
+
+        // By then the link has expired and needs no ban
+        tokio::time::advance(Duration::from_secs(REVERT_EMAIL_CHANGE_TTL_SECONDS as u64)).await;
+
+        let token = SecretString::new(token.into_boxed_str());
+        assert_eq!(banned_token_store.check_token(&token).await, Ok(false));
+    }
+
+    #[test]
+    fn test_time_to_expiry() {
+        let exp = Utc::now().timestamp() as usize + 60;
+
+        assert!(time_to_expiry(exp) > Duration::from_secs(58));
+        assert!(time_to_expiry(exp) <= Duration::from_secs(60));
+        assert_eq!(time_to_expiry(0), Duration::from_secs(1));
+    }
+}
\ No newline at end of file
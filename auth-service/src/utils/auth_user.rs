@@ -0,0 +1,198 @@
+use std::time::Duration;
+
+use axum::{extract::FromRequestParts, http::{HeaderMap, header::AUTHORIZATION, request::Parts}};
+use axum_extra::extract::CookieJar;
+use chrono::Utc;
+use color_eyre::eyre::{Context, Result, eyre};
+use secrecy::SecretString;
+
+use super::auth::{self, Claims};
+use super::config::AuthMode;
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, Email, SessionId};
+
+// Where the token of an authenticated request came from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenSource {
+    Cookie,
+    Bearer,
+}
+
+// A request with a valid JWT or session ID, sent either as `Authorization: Bearer` by
+// clients that can't keep cookies, or in the auth cookie by browsers.
+// The Bearer header wins when both are present.
+#[derive(Debug)]
+pub struct AuthenticatedUser {
+    pub claims: Claims,
+    pub token: String,
+    pub source: TokenSource,
+}
+
+impl FromRequestParts<AppState> for AuthenticatedUser {
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let (token, source) = match bearer_token(&parts.headers) {
+            Some(token) => (token.to_owned(), TokenSource::Bearer),
+            None => {
+                let jar = CookieJar::from_headers(&parts.headers);
+                let cookie = jar
+                    .get(&state.config.auth_cookie.name())
+                    .ok_or(AuthAPIError::MissingToken)?;
+                (cookie.value().to_owned(), TokenSource::Cookie)
+            }
+        };
+
+        let claims = validate_auth_token(state, &token)
+            .await
+            .map_err(|_| AuthAPIError::InvalidToken)?;
+
+        Ok(Self { claims, token, source })
+    }
+}
+
+// Checks a token in whichever auth mode is configured. Either way the token's
+// session must still be in the session store, and counts as active again. In
+// session mode this pushes back its idle timeout.
+#[tracing::instrument(name = "Validate_Auth_Token", skip_all)]
+pub async fn validate_auth_token(state: &AppState, token: &str) -> Result<Claims> {
+    match state.config.auth_mode {
+        AuthMode::Jwt => {
+            let claims = auth::validate_token(token, state.banned_token_store.clone()).await?;
+
+            let sid = claims.sid.clone().ok_or_else(|| eyre!("token has no session"))?;
+            let id = SessionId::parse(sid.clone())?;
+            let session_revoked = state.banned_token_store
+                .check_token(&SecretString::new(sid.into_boxed_str()))
+                .await?;
+
+            if session_revoked {
+                return Err(eyre!("session has been revoked"));
+            }
+
+            record_jwt_activity(state, &id, claims.exp).await?;
+
+            Ok(claims)
+        }
+        AuthMode::Session => {
+            let id = SessionId::parse(token.to_owned())?;
+            let policy = &state.config.session.policy;
+            let session_store = &state.session_store;
+
+            let mut session = session_store.get_session(&id)
+                .await
+                .wrap_err("failed to get session")?;
+
+            let now = Utc::now();
+            if session.is_expired(policy, now) {
+                session_store.remove_session(&id)
+                    .await
+                    .wrap_err("failed to remove expired session")?;
+                return Err(eyre!("session has expired"));
+            }
+
+            session.last_seen = now;
+            session_store.touch_session(&id, now, session.time_to_live(policy, now))
+                .await
+                .wrap_err("failed to touch session")?;
+
+            let exp = session.expires_at(policy).timestamp();
+            let exp: usize = exp
+                .try_into()
+                .wrap_err(format!("failed to cast exp time to usize. exp time: {}", exp))?;
+
+            Ok(Claims { sub: session.email.as_ref().to_owned(), exp, sid: Some(id.as_ref().to_owned()) })
+        }
+    }
+}
+
+// A JWT is only as good as its session record, so a token whose session is
+// gone from the store is rejected like a revoked one
+async fn record_jwt_activity(state: &AppState, id: &SessionId, exp: usize) -> Result<()> {
+    let now = Utc::now();
+    let ttl = auth::time_to_expiry(exp);
+
+    state.session_store
+        .touch_session(id, now, ttl)
+        .await
+        .wrap_err("failed to touch session")
+}
+
+// Makes a token unusable from now on, together with the session it belongs to
+#[tracing::instrument(name = "Revoke_Auth_Token", skip_all)]
+pub async fn revoke_auth_token(state: &AppState, token: &str, claims: &Claims) -> Result<()> {
+    if state.config.auth_mode == AuthMode::Jwt {
+        auth::ban_token(token, claims.exp, state.banned_token_store.clone()).await?;
+    }
+
+    match &claims.sid {
+        Some(sid) => revoke_session(state, &SessionId::parse(sid.clone())?).await,
+        None => Ok(()),
+    }
+}
+
+// Ends a session. JWTs of the session can't be recalled, so in JWT mode its ID
+// is banned until the last of the tokens carrying it expires.
+#[tracing::instrument(name = "Revoke_Session", skip_all)]
+pub async fn revoke_session(state: &AppState, id: &SessionId) -> Result<()> {
+    if state.config.auth_mode == AuthMode::Jwt {
+        state.banned_token_store
+            .add_token(&SecretString::new(id.as_ref().to_owned().into_boxed_str()), Duration::from_secs(auth::TOKEN_TTL_SECONDS as u64))
+            .await
+            .wrap_err("failed to ban session")?;
+    }
+
+    state.session_store
+        .remove_session(id)
+        .await
+        .wrap_err("failed to remove session")
+}
+
+// Logs the user out everywhere, e.g. once their address has changed hands
+#[tracing::instrument(name = "Revoke_User_Sessions", skip_all)]
+pub async fn revoke_user_sessions(state: &AppState, email: &Email) -> Result<()> {
+    let sessions = state.session_store
+        .list_sessions(email)
+        .await
+        .wrap_err("failed to list sessions")?;
+
+    for session in sessions {
+        revoke_session(state, &session.id).await?;
+    }
+
+    Ok(())
+}
+
+// The token from an `Authorization: Bearer <token>` header
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    let value = headers.get(AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+    let token = token.trim();
+
+    (scheme.eq_ignore_ascii_case("bearer") && !token.is_empty()).then_some(token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers_with_authorization(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn reads_bearer_token() {
+        assert_eq!(bearer_token(&headers_with_authorization("Bearer abc.def.ghi")), Some("abc.def.ghi"));
+        assert_eq!(bearer_token(&headers_with_authorization("bearer abc")), Some("abc"));
+    }
+
+    #[test]
+    fn ignores_other_schemes_and_empty_tokens() {
+        assert_eq!(bearer_token(&HeaderMap::new()), None);
+        assert_eq!(bearer_token(&headers_with_authorization("Basic dXNlcjpwYXNz")), None);
+        assert_eq!(bearer_token(&headers_with_authorization("Bearer ")), None);
+        assert_eq!(bearer_token(&headers_with_authorization("Bearer")), None);
+    }
+}
@@ -0,0 +1,36 @@
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+
+use axum::{extract::{ConnectInfo, FromRequestParts}, http::{header::USER_AGENT, request::Parts}};
+
+use super::rate_limit::client_ip;
+use crate::app_state::AppState;
+
+// Longer user agents are cut, so clients can't fill the session store with them
+const MAX_USER_AGENT_LENGTH: usize = 256;
+
+// Who sent the request, recorded on the sessions it starts
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClientInfo {
+    pub ip: Option<IpAddr>,
+    pub user_agent: Option<String>,
+}
+
+impl FromRequestParts<AppState> for ClientInfo {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let ip = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(peer)| client_ip(peer.ip(), &parts.headers, &state.config.ip_rate_limit.trusted_proxies));
+
+        let user_agent = parts
+            .headers
+            .get(USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.chars().take(MAX_USER_AGENT_LENGTH).collect());
+
+        Ok(Self { ip, user_agent })
+    }
+}
@@ -9,6 +9,7 @@ lazy_static! {
     pub static ref DATABASE_URL: SecretString = SecretString::new(set_token(env::DATABASE_URL_ENV_VAR).into_boxed_str());
     pub static ref REDIS_HOST_NAME: String = set_token_with_default(env::REDIS_HOST_NAME_ENV_VAR, DEFAULT_REDIS_HOSTNAME);
     pub static ref POSTMARK_AUTH_TOKEN: SecretString = SecretString::new(set_token(env::POSTMARK_AUTH_TOKEN_ENV_VAR).into_boxed_str());
+    pub static ref AUTH_SERVICE_URL: String = set_token_with_default(env::AUTH_SERVICE_URL_ENV_VAR, DEFAULT_AUTH_SERVICE_URL);
 }
 
 // TODO: Modify to return a SecretString
@@ -35,7 +36,174 @@ pub mod env {
     pub const DROPLET_IP_ENV_VAR: &str = "DROPLET_IP";
     pub const DATABASE_URL_ENV_VAR: &str = "DATABASE_URL";
     pub const REDIS_HOST_NAME_ENV_VAR: &str = "REDIS_HOST_NAME";
+    pub const REDIS_URL_ENV_VAR: &str = "REDIS_URL";
+    pub const REDIS_TOPOLOGY_ENV_VAR: &str = "REDIS_TOPOLOGY";
+    pub const REDIS_SENTINEL_URLS_ENV_VAR: &str = "REDIS_SENTINEL_URLS";
+    pub const REDIS_SENTINEL_MASTER_ENV_VAR: &str = "REDIS_SENTINEL_MASTER";
+    pub const REDIS_CLUSTER_URLS_ENV_VAR: &str = "REDIS_CLUSTER_URLS";
     pub const POSTMARK_AUTH_TOKEN_ENV_VAR: &str = "POSTMARK_AUTH_TOKEN";
+    pub const AUTH_SERVICE_URL_ENV_VAR: &str = "AUTH_SERVICE_URL";
+    pub const DEV_MODE_ENV_VAR: &str = "DEV_MODE";
+    pub const BRAND_PRODUCT_NAME_ENV_VAR: &str = "BRAND_PRODUCT_NAME";
+    pub const BRAND_LOGO_URL_ENV_VAR: &str = "BRAND_LOGO_URL";
+    pub const BRAND_SUPPORT_EMAIL_ENV_VAR: &str = "BRAND_SUPPORT_EMAIL";
+    pub const EMAIL_OTP_LOGIN_ENABLED_ENV_VAR: &str = "EMAIL_OTP_LOGIN_ENABLED";
+    pub const EMAIL_OTP_REQUEST_LIMIT_ENV_VAR: &str = "EMAIL_OTP_REQUEST_LIMIT";
+    pub const EMAIL_OTP_REQUEST_INTERVAL_SECS_ENV_VAR: &str = "EMAIL_OTP_REQUEST_INTERVAL_SECS";
+    pub const MAGIC_LINK_REQUEST_LIMIT_ENV_VAR: &str = "MAGIC_LINK_REQUEST_LIMIT";
+    pub const MAGIC_LINK_REQUEST_INTERVAL_SECS_ENV_VAR: &str = "MAGIC_LINK_REQUEST_INTERVAL_SECS";
+    pub const TWO_FA_VERIFY_LIMIT_ENV_VAR: &str = "TWO_FA_VERIFY_LIMIT";
+    pub const TWO_FA_VERIFY_INTERVAL_SECS_ENV_VAR: &str = "TWO_FA_VERIFY_INTERVAL_SECS";
+    pub const TWO_FA_MAX_FAILED_ATTEMPTS_ENV_VAR: &str = "TWO_FA_MAX_FAILED_ATTEMPTS";
+    pub const EMAIL_PROVIDERS_ENV_VAR: &str = "EMAIL_PROVIDERS";
+    pub const EMAIL_PROVIDER_TIMEOUT_SECS_ENV_VAR: &str = "EMAIL_PROVIDER_TIMEOUT_SECS";
+    pub const EMAIL_PROVIDER_FAILURE_THRESHOLD_ENV_VAR: &str = "EMAIL_PROVIDER_FAILURE_THRESHOLD";
+    pub const EMAIL_PROVIDER_COOLDOWN_SECS_ENV_VAR: &str = "EMAIL_PROVIDER_COOLDOWN_SECS";
+    pub const EMAIL_FILE_DIRECTORY_ENV_VAR: &str = "EMAIL_FILE_DIRECTORY";
+    pub const SMTP_HOST_ENV_VAR: &str = "SMTP_HOST";
+    pub const SMTP_PORT_ENV_VAR: &str = "SMTP_PORT";
+    pub const SMTP_TLS_ENV_VAR: &str = "SMTP_TLS";
+    pub const SMTP_CA_CERT_PATH_ENV_VAR: &str = "SMTP_CA_CERT_PATH";
+    pub const SMTP_USERNAME_ENV_VAR: &str = "SMTP_USERNAME";
+    pub const SMTP_PASSWORD_ENV_VAR: &str = "SMTP_PASSWORD";
+    pub const SMTP_SENDER_ENV_VAR: &str = "SMTP_SENDER";
+    pub const SMTP_TIMEOUT_SECS_ENV_VAR: &str = "SMTP_TIMEOUT_SECS";
+    pub const SMTP_POOL_MAX_SIZE_ENV_VAR: &str = "SMTP_POOL_MAX_SIZE";
+    pub const SMTP_POOL_IDLE_TIMEOUT_SECS_ENV_VAR: &str = "SMTP_POOL_IDLE_TIMEOUT_SECS";
+    pub const POSTMARK_WEBHOOK_USERNAME_ENV_VAR: &str = "POSTMARK_WEBHOOK_USERNAME";
+    pub const POSTMARK_WEBHOOK_PASSWORD_ENV_VAR: &str = "POSTMARK_WEBHOOK_PASSWORD";
+    pub const POSTMARK_WEBHOOK_SECRET_ENV_VAR: &str = "POSTMARK_WEBHOOK_SECRET";
+    pub const ACCOUNT_LOCKOUT_ENABLED_ENV_VAR: &str = "ACCOUNT_LOCKOUT_ENABLED";
+    pub const ACCOUNT_LOCKOUT_THRESHOLD_ENV_VAR: &str = "ACCOUNT_LOCKOUT_THRESHOLD";
+    pub const ACCOUNT_LOCKOUT_BASE_SECS_ENV_VAR: &str = "ACCOUNT_LOCKOUT_BASE_SECS";
+    pub const ACCOUNT_LOCKOUT_MAX_SECS_ENV_VAR: &str = "ACCOUNT_LOCKOUT_MAX_SECS";
+    pub const ACCOUNT_LOCKOUT_RESET_AFTER_SECS_ENV_VAR: &str = "ACCOUNT_LOCKOUT_RESET_AFTER_SECS";
+    pub const ACCOUNT_LOCKOUT_NOTIFY_USER_ENV_VAR: &str = "ACCOUNT_LOCKOUT_NOTIFY_USER";
+    pub const ACCOUNT_LOCKOUT_STORE_ENV_VAR: &str = "ACCOUNT_LOCKOUT_STORE";
+    pub const USER_STORE_ENV_VAR: &str = "USER_STORE";
+    pub const EMAIL_OUTBOX_STORE_ENV_VAR: &str = "EMAIL_OUTBOX_STORE";
+    pub const BANNED_TOKEN_STORE_ENV_VAR: &str = "BANNED_TOKEN_STORE";
+    pub const TWO_FA_CODE_STORE_ENV_VAR: &str = "TWO_FA_CODE_STORE";
+    pub const SESSION_STORE_ENV_VAR: &str = "SESSION_STORE";
+    pub const RATE_LIMIT_STORE_ENV_VAR: &str = "RATE_LIMIT_STORE";
+    pub const STORE_PURGE_INTERVAL_SECS_ENV_VAR: &str = "STORE_PURGE_INTERVAL_SECS";
+    pub const SQLITE_URL_ENV_VAR: &str = "SQLITE_URL";
+    pub const MEMORY_STORE_CAPACITY_ENV_VAR: &str = "MEMORY_STORE_CAPACITY";
+    pub const IP_RATE_LIMIT_ENABLED_ENV_VAR: &str = "IP_RATE_LIMIT_ENABLED";
+    pub const IP_RATE_LIMIT_TRUSTED_PROXIES_ENV_VAR: &str = "IP_RATE_LIMIT_TRUSTED_PROXIES";
+    pub const IP_RATE_LIMIT_LOGIN_LIMIT_ENV_VAR: &str = "IP_RATE_LIMIT_LOGIN_LIMIT";
+    pub const IP_RATE_LIMIT_LOGIN_INTERVAL_SECS_ENV_VAR: &str = "IP_RATE_LIMIT_LOGIN_INTERVAL_SECS";
+    pub const IP_RATE_LIMIT_SIGNUP_LIMIT_ENV_VAR: &str = "IP_RATE_LIMIT_SIGNUP_LIMIT";
+    pub const IP_RATE_LIMIT_SIGNUP_INTERVAL_SECS_ENV_VAR: &str = "IP_RATE_LIMIT_SIGNUP_INTERVAL_SECS";
+    pub const IP_RATE_LIMIT_VERIFY_2FA_LIMIT_ENV_VAR: &str = "IP_RATE_LIMIT_VERIFY_2FA_LIMIT";
+    pub const IP_RATE_LIMIT_VERIFY_2FA_INTERVAL_SECS_ENV_VAR: &str = "IP_RATE_LIMIT_VERIFY_2FA_INTERVAL_SECS";
+    pub const IP_RATE_LIMIT_MAGIC_LINK_LIMIT_ENV_VAR: &str = "IP_RATE_LIMIT_MAGIC_LINK_LIMIT";
+    pub const IP_RATE_LIMIT_MAGIC_LINK_INTERVAL_SECS_ENV_VAR: &str = "IP_RATE_LIMIT_MAGIC_LINK_INTERVAL_SECS";
+    pub const IP_RATE_LIMIT_EMAIL_OTP_LIMIT_ENV_VAR: &str = "IP_RATE_LIMIT_EMAIL_OTP_LIMIT";
+    pub const IP_RATE_LIMIT_EMAIL_OTP_INTERVAL_SECS_ENV_VAR: &str = "IP_RATE_LIMIT_EMAIL_OTP_INTERVAL_SECS";
+    pub const IP_RATE_LIMIT_VERIFY_EMAIL_OTP_LIMIT_ENV_VAR: &str = "IP_RATE_LIMIT_VERIFY_EMAIL_OTP_LIMIT";
+    pub const IP_RATE_LIMIT_VERIFY_EMAIL_OTP_INTERVAL_SECS_ENV_VAR: &str = "IP_RATE_LIMIT_VERIFY_EMAIL_OTP_INTERVAL_SECS";
+    pub const IP_RATE_LIMIT_CHANGE_EMAIL_LIMIT_ENV_VAR: &str = "IP_RATE_LIMIT_CHANGE_EMAIL_LIMIT";
+    pub const IP_RATE_LIMIT_CHANGE_EMAIL_INTERVAL_SECS_ENV_VAR: &str = "IP_RATE_LIMIT_CHANGE_EMAIL_INTERVAL_SECS";
+    pub const SIGNUP_ENUMERATION_SAFE_ENV_VAR: &str = "SIGNUP_ENUMERATION_SAFE";
+    pub const ADMIN_API_TOKEN_ENV_VAR: &str = "ADMIN_API_TOKEN";
+    pub const AUTH_COOKIE_HOST_PREFIX_ENV_VAR: &str = "AUTH_COOKIE_HOST_PREFIX";
+    pub const AUTH_COOKIE_SECURE_ENV_VAR: &str = "AUTH_COOKIE_SECURE";
+    pub const AUTH_COOKIE_DOMAIN_ENV_VAR: &str = "AUTH_COOKIE_DOMAIN";
+    pub const AUTH_COOKIE_SAME_SITE_ENV_VAR: &str = "AUTH_COOKIE_SAME_SITE";
+    pub const AUTH_MODE_ENV_VAR: &str = "AUTH_MODE";
+    pub const SESSION_IDLE_TIMEOUT_SECS_ENV_VAR: &str = "SESSION_IDLE_TIMEOUT_SECS";
+    pub const SESSION_ABSOLUTE_TIMEOUT_SECS_ENV_VAR: &str = "SESSION_ABSOLUTE_TIMEOUT_SECS";
+    pub const GEOIP_DB_PATH_ENV_VAR: &str = "GEOIP_DB_PATH";
+    pub const EMAIL_OUTBOX_MAX_ATTEMPTS_ENV_VAR: &str = "EMAIL_OUTBOX_MAX_ATTEMPTS";
+    pub const EMAIL_OUTBOX_INITIAL_BACKOFF_SECS_ENV_VAR: &str = "EMAIL_OUTBOX_INITIAL_BACKOFF_SECS";
+    pub const EMAIL_OUTBOX_MAX_BACKOFF_SECS_ENV_VAR: &str = "EMAIL_OUTBOX_MAX_BACKOFF_SECS";
+    pub const EMAIL_OUTBOX_POLL_INTERVAL_MS_ENV_VAR: &str = "EMAIL_OUTBOX_POLL_INTERVAL_MS";
+    pub const EMAIL_OUTBOX_BATCH_SIZE_ENV_VAR: &str = "EMAIL_OUTBOX_BATCH_SIZE";
+    pub const EMAIL_OUTBOX_LEASE_SECS_ENV_VAR: &str = "EMAIL_OUTBOX_LEASE_SECS";
+    pub const EMAIL_OUTBOX_RETENTION_SECS_ENV_VAR: &str = "EMAIL_OUTBOX_RETENTION_SECS";
+}
+
+pub mod defaults {
+    use std::time::Duration;
+
+    pub const BRAND_PRODUCT_NAME: &str = "Auth Service";
+    pub const BRAND_SUPPORT_EMAIL: &str = "support@example.com";
+
+    // Up to 3 codes at once, then one more every minute
+    pub const EMAIL_OTP_REQUEST_LIMIT: u32 = 3;
+    pub const EMAIL_OTP_REQUEST_INTERVAL: Duration = Duration::from_secs(60);
+    pub const MAGIC_LINK_REQUEST_LIMIT: u32 = 3;
+    pub const MAGIC_LINK_REQUEST_INTERVAL: Duration = Duration::from_secs(60);
+
+    // Per email, across all 2FA codes: up to 5 guesses at once, then one more every 2 minutes
+    pub const TWO_FA_VERIFY_LIMIT: u32 = 5;
+    pub const TWO_FA_VERIFY_INTERVAL: Duration = Duration::from_secs(120);
+    // A code is thrown away after this many wrong guesses
+    pub const TWO_FA_MAX_FAILED_ATTEMPTS: u32 = 5;
+
+    pub const EMAIL_PROVIDER_TIMEOUT: Duration = Duration::from_secs(10);
+    pub const EMAIL_PROVIDER_FAILURE_THRESHOLD: u32 = 3;
+    pub const EMAIL_PROVIDER_COOLDOWN: Duration = Duration::from_secs(60);
+
+    // Per client IP: bursts of 20 logins, then one every 3 seconds
+    pub const IP_RATE_LIMIT_LOGIN_LIMIT: u32 = 20;
+    pub const IP_RATE_LIMIT_LOGIN_INTERVAL: Duration = Duration::from_secs(3);
+    // Per client IP: bursts of 5 signups, then one a minute
+    pub const IP_RATE_LIMIT_SIGNUP_LIMIT: u32 = 5;
+    pub const IP_RATE_LIMIT_SIGNUP_INTERVAL: Duration = Duration::from_secs(60);
+    // Per client IP: bursts of 10 codes, then one every 6 seconds
+    pub const IP_RATE_LIMIT_VERIFY_2FA_LIMIT: u32 = 10;
+    pub const IP_RATE_LIMIT_VERIFY_2FA_INTERVAL: Duration = Duration::from_secs(6);
+    // Per client IP, for each route that emails a link or code: bursts of 5, then one a minute
+    pub const IP_RATE_LIMIT_MAGIC_LINK_LIMIT: u32 = 5;
+    pub const IP_RATE_LIMIT_MAGIC_LINK_INTERVAL: Duration = Duration::from_secs(60);
+    pub const IP_RATE_LIMIT_EMAIL_OTP_LIMIT: u32 = 5;
+    pub const IP_RATE_LIMIT_EMAIL_OTP_INTERVAL: Duration = Duration::from_secs(60);
+    pub const IP_RATE_LIMIT_CHANGE_EMAIL_LIMIT: u32 = 5;
+    pub const IP_RATE_LIMIT_CHANGE_EMAIL_INTERVAL: Duration = Duration::from_secs(60);
+    // Per client IP: bursts of 10 login codes, then one every 6 seconds
+    pub const IP_RATE_LIMIT_VERIFY_EMAIL_OTP_LIMIT: u32 = 10;
+    pub const IP_RATE_LIMIT_VERIFY_EMAIL_OTP_INTERVAL: Duration = Duration::from_secs(6);
+
+    // 5 wrong passwords lock the account for 1 minute, then 2, 4, ... up to 1 hour.
+    // Counters are forgotten a day after the last wrong password.
+    pub const ACCOUNT_LOCKOUT_THRESHOLD: u32 = 5;
+    pub const ACCOUNT_LOCKOUT_BASE_DURATION: Duration = Duration::from_secs(60);
+    pub const ACCOUNT_LOCKOUT_MAX_DURATION: Duration = Duration::from_secs(3600);
+    pub const ACCOUNT_LOCKOUT_RESET_AFTER: Duration = Duration::from_secs(86400);
+
+    // Sessions end after 30 minutes without a request, and a day after login at the latest
+    pub const SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(1800);
+    pub const SESSION_ABSOLUTE_TIMEOUT: Duration = Duration::from_secs(86400);
+
+    // Expired banned tokens, 2FA codes and sessions are deleted every 5 minutes
+    pub const STORE_PURGE_INTERVAL: Duration = Duration::from_secs(300);
+    // Created in the working directory on first start
+    pub const SQLITE_URL: &str = "sqlite://auth-service.db";
+    pub const MEMORY_STORE_CAPACITY: usize = 100_000;
+
+    // Name Sentinel deployments give the master unless configured otherwise
+    pub const REDIS_SENTINEL_MASTER: &str = "mymaster";
+
+    pub const EMAIL_FILE_DIRECTORY: &str = "mailbox";
+    pub const MAILBOX_LIMIT: usize = 20;
+
+    pub const SMTP_TIMEOUT: Duration = Duration::from_secs(10);
+    pub const SMTP_POOL_MAX_SIZE: u32 = 10;
+    pub const SMTP_POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+    // Retries after 5s, 10s, 20s, ... up to 10 minutes apart, then gives up after 8 attempts
+    pub const EMAIL_OUTBOX_MAX_ATTEMPTS: u32 = 8;
+    pub const EMAIL_OUTBOX_INITIAL_BACKOFF: Duration = Duration::from_secs(5);
+    pub const EMAIL_OUTBOX_MAX_BACKOFF: Duration = Duration::from_secs(600);
+    pub const EMAIL_OUTBOX_POLL_INTERVAL: Duration = Duration::from_millis(500);
+    pub const EMAIL_OUTBOX_BATCH_SIZE: u32 = 20;
+    // Emails are leased one at a time. Must be longer than a send through every
+    // provider, each up to its timeout, or a slow send could be claimed twice.
+    pub const EMAIL_OUTBOX_LEASE: Duration = Duration::from_secs(120);
+    // Sent and dead-lettered emails are kept this long for inspection
+    pub const EMAIL_OUTBOX_RETENTION: Duration = Duration::from_secs(7 * 24 * 3600);
 }
 
 pub mod prod {
@@ -62,4 +230,11 @@ pub mod test {
 }
 
 pub const JWT_COOKIE_NAME: &str = "jwt";
-pub const DEFAULT_REDIS_HOSTNAME: &str = "127.0.0.1";
\ No newline at end of file
+// Browsers only accept cookies with this prefix if they are `Secure`, have no
+// `Domain` and use `Path=/`
+pub const HOST_COOKIE_PREFIX: &str = "__Host-";
+pub const CSRF_COOKIE_NAME: &str = "csrf_token";
+pub const CSRF_HEADER_NAME: &str = "x-csrf-token";
+pub const WEBHOOK_SECRET_HEADER: &str = "X-Webhook-Secret";
+pub const DEFAULT_REDIS_HOSTNAME: &str = "127.0.0.1";
+pub const DEFAULT_AUTH_SERVICE_URL: &str = "http://localhost:3000";
\ No newline at end of file
@@ -0,0 +1,121 @@
+use axum::{extract::{Request, State}, http::{HeaderValue, Method, header::SET_COOKIE}, middleware::Next, response::{IntoResponse, Response}};
+use axum_extra::extract::{CookieJar, cookie::{Cookie, SameSite}};
+
+use super::auth::constant_time_eq;
+use super::auth_user::bearer_token;
+use super::constants::{CSRF_COOKIE_NAME, CSRF_HEADER_NAME};
+use crate::app_state::AppState;
+use crate::domain::AuthAPIError;
+
+// The CSRF token of the current browser, available to handlers as an extension
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsrfToken(pub String);
+
+// Makes sure every browser has a CSRF token cookie. The cookie is readable from
+// JavaScript, so the frontend can echo the token back in the `X-CSRF-Token`
+// header; a cross-site form can't, because it can't read our cookies.
+pub async fn issue_csrf_token(mut request: Request, next: Next) -> Response {
+    let jar = CookieJar::from_headers(request.headers());
+    let existing = jar
+        .get(CSRF_COOKIE_NAME)
+        .map(|cookie| cookie.value().to_owned())
+        .filter(|token| !token.is_empty());
+
+    let is_new = existing.is_none();
+    let token = existing.unwrap_or_else(generate_csrf_token);
+    request.extensions_mut().insert(CsrfToken(token.clone()));
+
+    let mut response = next.run(request).await;
+
+    if is_new
+        && let Ok(value) = HeaderValue::from_str(&create_csrf_cookie(token).to_string())
+    {
+        response.headers_mut().append(SET_COOKIE, value);
+    }
+
+    response
+}
+
+// Rejects state-changing requests authenticated by the JWT cookie unless they
+// carry the CSRF token from the cookie in the `X-CSRF-Token` header. Requests
+// without the JWT cookie are passed on so the handler can reject them as usual,
+// and so are Bearer requests, which a cross-site page can't forge.
+pub async fn require_csrf_token(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    if is_safe_method(request.method()) {
+        return next.run(request).await;
+    }
+
+    if bearer_token(request.headers()).is_some() {
+        return next.run(request).await;
+    }
+
+    let jar = CookieJar::from_headers(request.headers());
+    if jar.get(&state.config.auth_cookie.name()).is_none() {
+        return next.run(request).await;
+    }
+
+    let cookie_token = jar.get(CSRF_COOKIE_NAME).map(|cookie| cookie.value());
+    let header_token = request
+        .headers()
+        .get(CSRF_HEADER_NAME)
+        .and_then(|value| value.to_str().ok());
+
+    match (cookie_token, header_token) {
+        (Some(cookie), Some(header)) if !cookie.is_empty() && constant_time_eq(cookie.as_bytes(), header.as_bytes()) => {
+            next.run(request).await
+        }
+        _ => {
+            tracing::warn!(path = request.uri().path(), "Rejected request without a valid CSRF token");
+            AuthAPIError::InvalidCsrfToken.into_response()
+        }
+    }
+}
+
+fn is_safe_method(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE)
+}
+
+fn generate_csrf_token() -> String {
+    let bytes: [u8; 32] = rand::random();
+    hex::encode(bytes)
+}
+
+fn create_csrf_cookie(token: String) -> Cookie<'static> {
+    Cookie::build((CSRF_COOKIE_NAME, token))
+        .path("/")
+        .http_only(false) // the frontend reads the token to send it back in a header
+        .same_site(SameSite::Lax)
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csrf_tokens_are_random() {
+        let token = generate_csrf_token();
+
+        assert_eq!(token.len(), 64);
+        assert_ne!(token, generate_csrf_token());
+    }
+
+    #[test]
+    fn csrf_cookie_is_readable_from_javascript() {
+        let cookie = create_csrf_cookie("token".to_owned());
+
+        assert_eq!(cookie.name(), CSRF_COOKIE_NAME);
+        assert_eq!(cookie.value(), "token");
+        assert_eq!(cookie.path(), Some("/"));
+        assert_eq!(cookie.http_only(), Some(false));
+        assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+    }
+
+    #[test]
+    fn only_mutating_methods_need_a_token() {
+        assert!(is_safe_method(&Method::GET));
+        assert!(is_safe_method(&Method::HEAD));
+        assert!(!is_safe_method(&Method::POST));
+        assert!(!is_safe_method(&Method::DELETE));
+    }
+}
@@ -0,0 +1,19 @@
+use std::sync::OnceLock;
+
+use color_eyre::eyre::Result;
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+
+static PROMETHEUS_HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+
+// Records metrics from the `metrics` macros so `/metrics` can serve them
+pub fn init_metrics() -> Result<()> {
+    let handle = PrometheusBuilder::new().install_recorder()?;
+    let _ = PROMETHEUS_HANDLE.set(handle);
+
+    Ok(())
+}
+
+// Metrics in the Prometheus text format, if `init_metrics` was called
+pub fn render_metrics() -> Option<String> {
+    PROMETHEUS_HANDLE.get().map(|handle| handle.render())
+}
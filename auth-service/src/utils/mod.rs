@@ -1,3 +1,9 @@
 pub mod constants;
 pub mod auth;
-pub mod tracing;
\ No newline at end of file
+pub mod tracing;
+pub mod config;
+pub mod metrics;
+pub mod rate_limit;
+pub mod csrf;
+pub mod auth_user;
+pub mod client_info;
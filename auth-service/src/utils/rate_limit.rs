@@ -0,0 +1,221 @@
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+
+use axum::{extract::{ConnectInfo, Request, State}, http::HeaderMap, middleware::Next, response::{IntoResponse, Response}};
+
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, RateLimitDecision};
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+
+// Limits how often a client can call the routes that attackers hammer, like
+// login and signup. Buckets are kept in the rate limit store, so every replica
+// sees the same counts.
+pub async fn rate_limit_by_ip(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let config = &state.config.ip_rate_limit;
+
+    let Some((route, policy)) = config.policy_for(request.uri().path()) else {
+        return next.run(request).await;
+    };
+
+    let client_ip = client_ip(peer.ip(), request.headers(), &config.trusted_proxies);
+    let key = format!("ip:{}:{}", route, client_ip);
+
+    let decision = state.rate_limit_store
+        .check(&key, policy)
+        .await;
+
+    match decision {
+        Ok(RateLimitDecision::Allowed) => next.run(request).await,
+        Ok(RateLimitDecision::Limited { retry_after }) => {
+            tracing::warn!(route, %client_ip, "Rate limited request");
+            AuthAPIError::TooManyRequests { retry_after }.into_response()
+        }
+        // Losing the rate limit store shouldn't take logins down with it
+        Err(err) => {
+            tracing::error!(error = %err, "Failed to check rate limit, allowing request");
+            next.run(request).await
+        }
+    }
+}
+
+// The address of the client that made the request. Proxies we trust put the
+// address they received the request from at the end of `X-Forwarded-For`, so it
+// is read right to left until an address that isn't a trusted proxy is found.
+pub fn client_ip(peer: IpAddr, headers: &HeaderMap, trusted_proxies: &TrustedProxies) -> IpAddr {
+    if !trusted_proxies.contains(&peer) {
+        return peer;
+    }
+
+    let forwarded: Vec<Option<IpAddr>> = headers
+        .get_all(X_FORWARDED_FOR)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|ip| ip.trim().parse().ok())
+        .collect();
+
+    let mut client = peer;
+    for ip in forwarded.into_iter().rev() {
+        // Anything left of a malformed entry can't be trusted
+        let Some(ip) = ip else {
+            break;
+        };
+
+        client = ip;
+        if !trusted_proxies.contains(&ip) {
+            break;
+        }
+    }
+
+    client
+}
+
+// Proxies allowed to tell us the client address, as IPs or CIDR ranges
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrustedProxies(Vec<IpNetwork>);
+
+impl TrustedProxies {
+    pub fn new(networks: Vec<IpNetwork>) -> Self {
+        Self(networks)
+    }
+
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        self.0.iter().any(|network| network.contains(ip))
+    }
+}
+
+impl FromStr for TrustedProxies {
+    type Err = String;
+
+    // Comma separated, e.g. "10.0.0.0/8, 127.0.0.1"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|network| !network.is_empty())
+            .map(IpNetwork::from_str)
+            .collect::<Result<Vec<_>, _>>()
+            .map(Self)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IpNetwork {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpNetwork {
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.addr, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix_len as u32).unwrap_or(0);
+                u32::from(network) & mask == u32::from(*ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix_len as u32).unwrap_or(0);
+                u128::from(network) & mask == u128::from(*ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for IpNetwork {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix_len) = match s.split_once('/') {
+            Some((addr, prefix_len)) => (addr, Some(prefix_len)),
+            None => (s, None),
+        };
+
+        let addr: IpAddr = addr.parse().map_err(|_| format!("invalid IP address: {}", s))?;
+        let max_prefix_len = if addr.is_ipv4() { 32 } else { 128 };
+
+        let prefix_len = match prefix_len {
+            Some(prefix_len) => prefix_len
+                .parse()
+                .ok()
+                .filter(|prefix_len| *prefix_len <= max_prefix_len)
+                .ok_or_else(|| format!("invalid prefix length: {}", s))?,
+            None => max_prefix_len,
+        };
+
+        Ok(Self { addr, prefix_len })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    fn forwarded_for(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(X_FORWARDED_FOR, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_ip_network_contains() {
+        let test_cases = [
+            ("10.0.0.0/8", "10.1.2.3", true),
+            ("10.0.0.0/8", "11.0.0.1", false),
+            ("127.0.0.1", "127.0.0.1", true),
+            ("127.0.0.1", "127.0.0.2", false),
+            ("0.0.0.0/0", "8.8.8.8", true),
+            ("fd00::/8", "fd12::1", true),
+            ("fd00::/8", "fe80::1", false),
+            ("10.0.0.0/8", "::1", false),
+        ];
+
+        for (network, address, expected) in test_cases {
+            let network: IpNetwork = network.parse().unwrap();
+            assert_eq!(network.contains(&ip(address)), expected, "Failed for {:?} and {}", network, address);
+        }
+    }
+
+    #[test]
+    fn test_ip_network_rejects_invalid_input() {
+        for input in ["", "not-an-ip", "10.0.0.0/33", "::1/129", "10.0.0.0/x"] {
+            assert!(input.parse::<IpNetwork>().is_err(), "Failed for input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_client_ip_ignores_header_from_untrusted_peer() {
+        let trusted: TrustedProxies = "10.0.0.0/8".parse().unwrap();
+
+        let client = client_ip(ip("203.0.113.7"), &forwarded_for("198.51.100.1"), &trusted);
+        assert_eq!(client, ip("203.0.113.7"));
+    }
+
+    #[test]
+    fn test_client_ip_skips_trusted_proxies() {
+        let trusted: TrustedProxies = "10.0.0.0/8, 127.0.0.1".parse().unwrap();
+
+        // The client can put anything at the start of the header, only the
+        // entries added by our proxies count
+        let headers = forwarded_for("1.1.1.1, 198.51.100.1, 10.0.0.2");
+        let client = client_ip(ip("127.0.0.1"), &headers, &trusted);
+        assert_eq!(client, ip("198.51.100.1"));
+    }
+
+    #[test]
+    fn test_client_ip_falls_back_to_peer_without_header() {
+        let trusted: TrustedProxies = "127.0.0.1".parse().unwrap();
+
+        let client = client_ip(ip("127.0.0.1"), &HeaderMap::new(), &trusted);
+        assert_eq!(client, ip("127.0.0.1"));
+    }
+}
@@ -0,0 +1,227 @@
+use std::time::Duration;
+
+use auth_service::domain::{data_stores::AccountLockoutStore, Email, ErrorResponse, FailedLoginOutcome, LockoutPolicy, LockoutStatus};
+use auth_service::services::data_stores::postgres_account_lockout_store::PostgresAccountLockoutStore;
+use auth_service::utils::config::{AccountLockoutConfig, AdminConfig, Config};
+use secrecy::SecretString;
+use wiremock::{Mock, ResponseTemplate};
+use wiremock::matchers::{method, path};
+
+use crate::helpers::{configure_postgresql, delete_database, get_random_email, TestApp};
+
+const ADMIN_API_TOKEN: &str = "admin-token";
+
+fn email() -> Email {
+    Email::parse(SecretString::new(get_random_email().into_boxed_str())).unwrap()
+}
+
+fn lockout_config(notify_user: bool) -> Config {
+    Config {
+        account_lockout: AccountLockoutConfig {
+            policy: LockoutPolicy::new(3, Duration::from_secs(60), Duration::from_secs(600), Duration::from_secs(3600)),
+            notify_user,
+            ..AccountLockoutConfig::default()
+        },
+        admin: AdminConfig {
+            api_token: Some(SecretString::new(ADMIN_API_TOKEN.to_owned().into_boxed_str())),
+        },
+        ..Config::default()
+    }
+}
+
+async fn signup(app: &TestApp, email: &str) {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+}
+
+async fn login(app: &TestApp, email: &str, password: &str) -> reqwest::Response {
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": password,
+    });
+
+    app.post_login(&login_body).await
+}
+
+#[tokio::test]
+async fn should_lock_account_after_repeated_wrong_passwords() {
+    let mut app = TestApp::new_with_config(lockout_config(false)).await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+
+    for _ in 0..2 {
+        let response = login(&app, &random_email, "wrong-password").await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = login(&app, &random_email, "wrong-password").await;
+    assert_eq!(response.status().as_u16(), 423);
+    assert_eq!(response.headers()["retry-after"], "60");
+
+    // The right password doesn't get past the lock
+    let response = login(&app, &random_email, "password123").await;
+    assert_eq!(response.status().as_u16(), 423);
+    assert_eq!(
+        response.json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialized response body to ErrorResponse")
+            .error,
+        "Account is temporarily locked, try again later".to_owned()
+    );
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_lock_unknown_emails_the_same_way() {
+    let mut app = TestApp::new_with_config(lockout_config(false)).await;
+
+    let random_email = get_random_email();
+
+    for expected_status in [401, 401, 423] {
+        let response = login(&app, &random_email, "wrong-password").await;
+        assert_eq!(response.status().as_u16(), expected_status);
+    }
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_reset_failures_after_successful_login() {
+    let mut app = TestApp::new_with_config(lockout_config(false)).await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+
+    for _ in 0..2 {
+        login(&app, &random_email, "wrong-password").await;
+    }
+
+    let response = login(&app, &random_email, "password123").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    for _ in 0..2 {
+        let response = login(&app, &random_email, "wrong-password").await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_email_user_when_account_is_locked() {
+    let mut app = TestApp::new_with_config(lockout_config(true)).await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    for _ in 0..3 {
+        login(&app, &random_email, "wrong-password").await;
+    }
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_unlock_account_with_admin_token() {
+    let mut app = TestApp::new_with_config(lockout_config(false)).await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+
+    for _ in 0..3 {
+        login(&app, &random_email, "wrong-password").await;
+    }
+
+    let body = serde_json::json!({ "email": random_email });
+
+    let response = app.post_unlock_account(&body, "wrong-token").await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.post_unlock_account(&body, ADMIN_API_TOKEN).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = login(&app, &random_email, "password123").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_not_expose_unlock_without_admin_token() {
+    let mut app = TestApp::new().await;
+
+    let body = serde_json::json!({ "email": get_random_email() });
+
+    let response = app.post_unlock_account(&body, ADMIN_API_TOKEN).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn postgres_store_locks_progressively_and_resets() {
+    let pg_pool = configure_postgresql().await;
+    let db_name = pg_pool.connect_options().get_database().unwrap().to_string();
+    let store = PostgresAccountLockoutStore::new(pg_pool.clone());
+    let policy = LockoutPolicy::new(2, Duration::from_secs(60), Duration::from_secs(600), Duration::from_secs(3600));
+    let email = email();
+
+    assert_eq!(store.record_failure(&email, &policy).await, Ok(FailedLoginOutcome::Counted { failed_attempts: 1 }));
+    assert_eq!(store.status(&email).await, Ok(LockoutStatus::Unlocked));
+    assert_eq!(
+        store.record_failure(&email, &policy).await,
+        Ok(FailedLoginOutcome::Locked { lock_duration: Duration::from_secs(60) })
+    );
+
+    match store.status(&email).await.unwrap() {
+        LockoutStatus::Locked { retry_after } => assert!(retry_after <= Duration::from_secs(60) && retry_after > Duration::from_secs(55)),
+        LockoutStatus::Unlocked => panic!("expected account to be locked"),
+    }
+
+    store.record_failure(&email, &policy).await.unwrap();
+    assert_eq!(
+        store.record_failure(&email, &policy).await,
+        Ok(FailedLoginOutcome::Locked { lock_duration: Duration::from_secs(120) })
+    );
+
+    store.reset(&email).await.unwrap();
+    assert_eq!(store.status(&email).await, Ok(LockoutStatus::Unlocked));
+    assert_eq!(store.record_failure(&email, &policy).await, Ok(FailedLoginOutcome::Counted { failed_attempts: 1 }));
+
+    pg_pool.close().await;
+    delete_database(&db_name).await;
+}
+
+#[tokio::test]
+async fn postgres_store_unlocks_when_lock_expires() {
+    let pg_pool = configure_postgresql().await;
+    let db_name = pg_pool.connect_options().get_database().unwrap().to_string();
+    let store = PostgresAccountLockoutStore::new(pg_pool.clone());
+    let policy = LockoutPolicy::new(1, Duration::from_millis(100), Duration::from_secs(1), Duration::from_secs(3600));
+    let email = email();
+
+    store.record_failure(&email, &policy).await.unwrap();
+    assert!(matches!(store.status(&email).await, Ok(LockoutStatus::Locked { .. })));
+
+    tokio::time::sleep(Duration::from_millis(150)).await;
+
+    assert_eq!(store.status(&email).await, Ok(LockoutStatus::Unlocked));
+
+    pg_pool.close().await;
+    delete_database(&db_name).await;
+}
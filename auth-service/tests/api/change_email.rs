@@ -0,0 +1,402 @@
+use auth_service::domain::ErrorResponse;
+use wiremock::{Mock, ResponseTemplate};
+use wiremock::matchers::{method, path};
+
+use crate::helpers::{TestApp, get_random_email};
+
+// Signs up and logs in a user without 2FA, leaving the JWT cookie in the test client
+async fn signup_and_login(app: &TestApp, email: &str) {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+async fn mount_email_server(app: &TestApp, expected_emails: u64) {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(expected_emails)
+        .mount(&app.email_server)
+        .await;
+}
+
+#[tokio::test]
+async fn should_return_202_and_send_two_emails() {
+    let mut app = TestApp::new().await;
+
+    let email = get_random_email();
+    signup_and_login(&app, &email).await;
+    mount_email_server(&app, 2).await;
+
+    let body = serde_json::json!({
+        "password": "password123",
+        "newEmail": get_random_email(),
+    });
+
+    let response = app.post_change_email(&body).await;
+    assert_eq!(response.status().as_u16(), 202);
+
+    assert!(app.get_token_from_sent_email("/change-email/confirm").await.is_some());
+    assert!(app.get_token_from_sent_email("/change-email/revert").await.is_some());
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_change_email_only_after_confirmation() {
+    let mut app = TestApp::new().await;
+
+    let email = get_random_email();
+    let new_email = get_random_email();
+    signup_and_login(&app, &email).await;
+    mount_email_server(&app, 2).await;
+
+    let body = serde_json::json!({
+        "password": "password123",
+        "newEmail": new_email,
+    });
+
+    let response = app.post_change_email(&body).await;
+    assert_eq!(response.status().as_u16(), 202);
+
+    // The old address still works until the change is confirmed
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+    });
+    assert_eq!(app.post_login(&login_body).await.status().as_u16(), 200);
+
+    let token = app.get_token_from_sent_email("/change-email/confirm").await.unwrap();
+    let response = app.get_change_email_confirm(&token).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Sessions of the old address are logged out
+    assert_eq!(app.get_sessions().await.status().as_u16(), 401);
+
+    assert_eq!(app.post_login(&login_body).await.status().as_u16(), 401);
+
+    let login_body = serde_json::json!({
+        "email": new_email,
+        "password": "password123",
+    });
+    assert_eq!(app.post_login(&login_body).await.status().as_u16(), 200);
+
+    // Confirmation links are single-use
+    let response = app.get_change_email_confirm(&token).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_restore_old_email_when_reverted() {
+    let mut app = TestApp::new().await;
+
+    let email = get_random_email();
+    let new_email = get_random_email();
+    signup_and_login(&app, &email).await;
+    mount_email_server(&app, 2).await;
+
+    let body = serde_json::json!({
+        "password": "password123",
+        "newEmail": new_email,
+    });
+
+    let response = app.post_change_email(&body).await;
+    assert_eq!(response.status().as_u16(), 202);
+
+    let confirm_token = app.get_token_from_sent_email("/change-email/confirm").await.unwrap();
+    let revert_token = app.get_token_from_sent_email("/change-email/revert").await.unwrap();
+
+    let response = app.get_change_email_confirm(&confirm_token).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Log in under the new address, as whoever made the change would
+    let login_body = serde_json::json!({
+        "email": new_email,
+        "password": "password123",
+    });
+    assert_eq!(app.post_login(&login_body).await.status().as_u16(), 200);
+
+    let response = app.get_change_email_revert(&revert_token).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    assert_eq!(app.get_sessions().await.status().as_u16(), 401);
+
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+    });
+    assert_eq!(app.post_login(&login_body).await.status().as_u16(), 200);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_not_confirm_after_revert() {
+    let mut app = TestApp::new().await;
+
+    let email = get_random_email();
+    signup_and_login(&app, &email).await;
+    mount_email_server(&app, 2).await;
+
+    let body = serde_json::json!({
+        "password": "password123",
+        "newEmail": get_random_email(),
+    });
+
+    let response = app.post_change_email(&body).await;
+    assert_eq!(response.status().as_u16(), 202);
+
+    let confirm_token = app.get_token_from_sent_email("/change-email/confirm").await.unwrap();
+    let revert_token = app.get_token_from_sent_email("/change-email/revert").await.unwrap();
+
+    let response = app.get_change_email_revert(&revert_token).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.get_change_email_confirm(&confirm_token).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_not_revert_onto_account_that_took_the_address() {
+    let mut app = TestApp::new().await;
+
+    let email = get_random_email();
+    let victim_email = get_random_email();
+    signup_and_login(&app, &email).await;
+    mount_email_server(&app, 4).await;
+
+    // A change to an unregistered address is never confirmed, but its revert link still arrives
+    let body = serde_json::json!({
+        "password": "password123",
+        "newEmail": victim_email,
+    });
+    let response = app.post_change_email(&body).await;
+    assert_eq!(response.status().as_u16(), 202);
+    let revert_token = app.get_token_from_sent_email("/change-email/revert").await.unwrap();
+
+    // Then the account leaves its address and the victim signs up with the other one
+    let body = serde_json::json!({
+        "password": "password123",
+        "newEmail": get_random_email(),
+    });
+    let response = app.post_change_email(&body).await;
+    assert_eq!(response.status().as_u16(), 202);
+    let confirm_token = app.get_token_from_sent_email("/change-email/confirm").await.unwrap();
+    let response = app.get_change_email_confirm(&confirm_token).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let signup_body = serde_json::json!({
+        "email": victim_email,
+        "password": "password456",
+        "requires2FA": false
+    });
+    assert_eq!(app.post_signup(&signup_body).await.status().as_u16(), 201);
+
+    let response = app.get_change_email_revert(&revert_token).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The victim's account stays where it is
+    let login_body = serde_json::json!({
+        "email": victim_email,
+        "password": "password456",
+    });
+    assert_eq!(app.post_login(&login_body).await.status().as_u16(), 200);
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password456",
+    });
+    assert_eq!(app.post_login(&login_body).await.status().as_u16(), 401);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_not_revert_change_after_account_moved_on() {
+    let mut app = TestApp::new().await;
+
+    let email = get_random_email();
+    let second_email = get_random_email();
+    signup_and_login(&app, &email).await;
+    mount_email_server(&app, 4).await;
+
+    let body = serde_json::json!({
+        "password": "password123",
+        "newEmail": second_email,
+    });
+    let response = app.post_change_email(&body).await;
+    assert_eq!(response.status().as_u16(), 202);
+    let revert_token = app.get_token_from_sent_email("/change-email/revert").await.unwrap();
+    let confirm_token = app.get_token_from_sent_email("/change-email/confirm").await.unwrap();
+    assert_eq!(app.get_change_email_confirm(&confirm_token).await.status().as_u16(), 200);
+
+    // The old JWT names the old address, so log in again before the next change
+    let login_body = serde_json::json!({
+        "email": second_email,
+        "password": "password123",
+    });
+    assert_eq!(app.post_login(&login_body).await.status().as_u16(), 200);
+
+    let body = serde_json::json!({
+        "password": "password123",
+        "newEmail": get_random_email(),
+    });
+    let response = app.post_change_email(&body).await;
+    assert_eq!(response.status().as_u16(), 202);
+    let confirm_token = app.get_token_from_sent_email("/change-email/confirm").await.unwrap();
+    assert_eq!(app.get_change_email_confirm(&confirm_token).await.status().as_u16(), 200);
+
+    // Someone else takes the address the account left behind
+    let signup_body = serde_json::json!({
+        "email": second_email,
+        "password": "password456",
+        "requires2FA": false
+    });
+    assert_eq!(app.post_signup(&signup_body).await.status().as_u16(), 201);
+
+    let response = app.get_change_email_revert(&revert_token).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let login_body = serde_json::json!({
+        "email": second_email,
+        "password": "password456",
+    });
+    assert_eq!(app.post_login(&login_body).await.status().as_u16(), 200);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_missing_token() {
+    let mut app = TestApp::new().await;
+
+    let body = serde_json::json!({
+        "password": "password123",
+        "newEmail": get_random_email(),
+    });
+
+    let response = app.post_change_email(&body).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_incorrect_password() {
+    let mut app = TestApp::new().await;
+
+    let email = get_random_email();
+    signup_and_login(&app, &email).await;
+
+    let body = serde_json::json!({
+        "password": "password456",
+        "newEmail": get_random_email(),
+    });
+
+    let response = app.post_change_email(&body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    assert_eq!(
+        response.json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialized response body to ErrorResponse")
+            .error,
+        "Incorrect credentials".to_owned()
+    );
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_return_409_if_new_email_is_taken() {
+    let mut app = TestApp::new().await;
+
+    let other_email = get_random_email();
+    let signup_body = serde_json::json!({
+        "email": other_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    app.post_signup(&signup_body).await;
+
+    let email = get_random_email();
+    signup_and_login(&app, &email).await;
+
+    let body = serde_json::json!({
+        "password": "password123",
+        "newEmail": other_email,
+    });
+
+    let response = app.post_change_email(&body).await;
+    assert_eq!(response.status().as_u16(), 409);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_invalid_link_token() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_change_email_confirm("invalid").await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.get_change_email_revert("invalid").await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_accept_bearer_token() {
+    let mut app = TestApp::new().await;
+
+    let email = get_random_email();
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "returnToken": true,
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let login_response = response.json::<serde_json::Value>().await.unwrap();
+    let token = login_response["token"].as_str().unwrap();
+
+    mount_email_server(&app, 2).await;
+
+    let body = serde_json::json!({
+        "password": "password123",
+        "newEmail": get_random_email(),
+    });
+
+    // Bearer requests don't need a CSRF token
+    let response = app.post_change_email_with_bearer(&body, token).await;
+    assert_eq!(response.status().as_u16(), 202);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
@@ -0,0 +1,122 @@
+use auth_service::{domain::ErrorResponse, utils::constants::{CSRF_COOKIE_NAME, CSRF_HEADER_NAME}};
+
+use crate::helpers::{TestApp, get_random_email};
+
+async fn signup_and_login(app: &TestApp, email: &str) {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn should_issue_csrf_token_once() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_csrf_token().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == CSRF_COOKIE_NAME)
+        .expect("No CSRF cookie found");
+    assert!(!cookie.http_only());
+    let cookie_value = cookie.value().to_owned();
+
+    let body = response.json::<serde_json::Value>().await.unwrap();
+    let token = body["csrfToken"].as_str().unwrap().to_owned();
+    assert_eq!(cookie_value, token);
+
+    // The browser already has a token, so it is kept
+    let response = app.get_csrf_token().await;
+    assert!(response.cookies().all(|cookie| cookie.name() != CSRF_COOKIE_NAME));
+
+    let body = response.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(body["csrfToken"].as_str().unwrap(), token);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_return_403_if_logout_has_no_csrf_token() {
+    let mut app = TestApp::new().await;
+
+    signup_and_login(&app, &get_random_email()).await;
+
+    let response = app.post_logout_without_csrf_token().await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(
+        response.json::<ErrorResponse>().await.unwrap().error,
+        "Missing or invalid CSRF token"
+    );
+
+    // The session survived the forged request
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_return_403_if_csrf_token_does_not_match_cookie() {
+    let mut app = TestApp::new().await;
+
+    signup_and_login(&app, &get_random_email()).await;
+
+    let response = app.http_client
+        .post(format!("{}/logout", &app.address))
+        .header(CSRF_HEADER_NAME, "not-the-token")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status().as_u16(), 403);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_return_403_if_change_email_has_no_csrf_token() {
+    let mut app = TestApp::new().await;
+
+    signup_and_login(&app, &get_random_email()).await;
+
+    let body = serde_json::json!({
+        "password": "password123",
+        "newEmail": get_random_email(),
+    });
+
+    let response = app.http_client
+        .post(format!("{}/change-email", &app.address))
+        .json(&body)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status().as_u16(), 403);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_not_require_csrf_token_without_jwt_cookie() {
+    let mut app = TestApp::new().await;
+
+    // Nothing to forge without a session, the handler rejects it as before
+    let response = app.post_logout_without_csrf_token().await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
@@ -0,0 +1,96 @@
+use auth_service::domain::{Email, EmailClient};
+use auth_service::services::file_email_client::{FileEmailClient, MailboxMessage};
+use auth_service::utils::config::{Config, EmailProviderConfig, EmailProvidersConfig};
+use secrecy::SecretString;
+
+use crate::helpers::TestApp;
+
+fn dev_config() -> Config {
+    Config {
+        dev_mode: true,
+        ..Config::default()
+    }
+}
+
+#[tokio::test]
+async fn should_return_html_preview() {
+    let mut app = TestApp::new_with_config(dev_config()).await;
+
+    let response = app.get_dev_email_preview("two-fa-code", &[]).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.headers()["content-type"].to_str().unwrap().starts_with("text/html"));
+    assert!(response.text().await.unwrap().contains("123456"));
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_return_text_preview_in_requested_locale() {
+    let mut app = TestApp::new_with_config(dev_config()).await;
+
+    let response = app.get_dev_email_preview("two-fa-code", &[("locale", "es"), ("format", "text")]).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.headers()["content-type"].to_str().unwrap().starts_with("text/plain"));
+    assert!(response.text().await.unwrap().contains("Tu código de verificación es"));
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_return_404_for_unknown_template() {
+    let mut app = TestApp::new_with_config(dev_config()).await;
+
+    let response = app.get_dev_email_preview("unknown", &[]).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_not_expose_previews_outside_dev_mode() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_dev_email_preview("two-fa-code", &[]).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_list_emails_written_by_file_provider() {
+    let directory = tempfile::tempdir().unwrap();
+    let config = Config {
+        email_providers: EmailProvidersConfig {
+            providers: vec![EmailProviderConfig::File(directory.path().to_owned())],
+            ..EmailProvidersConfig::default()
+        },
+        ..dev_config()
+    };
+    let mut app = TestApp::new_with_config(config).await;
+
+    let sender = Email::parse(SecretString::new("sender@example.com".to_owned().into_boxed_str())).unwrap();
+    let recipient = Email::parse(SecretString::new("user@example.com".to_owned().into_boxed_str())).unwrap();
+    let client = FileEmailClient::new(directory.path().to_owned(), sender).unwrap();
+    client.send_email(recipient, "Your code", "<p>123456</p>", "Your code is 123456").await.unwrap();
+
+    let response = app.get_dev_mailbox().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let messages = response.json::<Vec<MailboxMessage>>().await.unwrap();
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].recipient, "user@example.com");
+    assert_eq!(messages[0].subject, "Your code");
+    assert!(messages[0].text_body.contains("123456"));
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_return_404_for_mailbox_without_file_provider() {
+    let mut app = TestApp::new_with_config(dev_config()).await;
+
+    let response = app.get_dev_mailbox().await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
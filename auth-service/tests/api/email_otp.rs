@@ -0,0 +1,333 @@
+use std::time::Duration;
+
+use auth_service::domain::{Email, RateLimitPolicy, TwoFACodePurpose};
+use auth_service::routes::TwoFactorAuthResponse;
+use auth_service::utils::config::{Config, EmailOtpLoginConfig, TwoFAConfig};
+use auth_service::utils::constants::JWT_COOKIE_NAME;
+use secrecy::{ExposeSecret, SecretString};
+use wiremock::{Mock, ResponseTemplate};
+use wiremock::matchers::{method, path};
+
+use crate::helpers::{TestApp, get_random_email};
+
+fn email_otp_config() -> Config {
+    Config {
+        email_otp_login: EmailOtpLoginConfig {
+            enabled: true,
+            request_limit: RateLimitPolicy::new(2, Duration::from_secs(60)),
+        },
+        two_fa: TwoFAConfig {
+            verify_limit: RateLimitPolicy::new(3, Duration::from_secs(60)),
+            max_failed_attempts: 5,
+        },
+        ..Config::default()
+    }
+}
+
+async fn signup(app: &TestApp, email: &str) {
+    signup_with_2fa(app, email, false).await;
+}
+
+async fn signup_with_2fa(app: &TestApp, email: &str, requires_2fa: bool) {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": requires_2fa
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+}
+
+#[tokio::test]
+async fn should_return_200_if_correct_code() {
+    let mut app = TestApp::new_with_config(email_otp_config()).await;
+
+    let random_email = SecretString::new(get_random_email().into_boxed_str());
+    let email = Email::parse(random_email.clone()).unwrap();
+    signup(&app, random_email.expose_secret()).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_email_otp(&serde_json::json!({ "email": random_email.expose_secret() })).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let response_json = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+
+    let (login_attempt_id, two_fa_code) = app.get_two_fa_code(&email, TwoFACodePurpose::EmailOtp).await.unwrap();
+    app.wait_for_sent_emails(1).await;
+
+    assert_eq!(response_json.login_attempt_id, login_attempt_id.as_ref().to_owned());
+
+    let verify_body = serde_json::json!({
+        "email": random_email.expose_secret(),
+        "loginAttemptId": login_attempt_id.as_ref(),
+        "2FACode": two_fa_code.as_ref(),
+    });
+
+    let response = app.post_verify_email_otp(&verify_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+
+    assert!(!auth_cookie.value().is_empty());
+
+    // Codes are single-use
+    let response = app.post_verify_email_otp(&verify_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_incorrect_code() {
+    let mut app = TestApp::new_with_config(email_otp_config()).await;
+
+    let random_email = SecretString::new(get_random_email().into_boxed_str());
+    signup(&app, random_email.expose_secret()).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_email_otp(&serde_json::json!({ "email": random_email.expose_secret() })).await;
+    let response_json = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+
+    let verify_body = serde_json::json!({
+        "email": random_email.expose_secret(),
+        "loginAttemptId": response_json.login_attempt_id,
+        "2FACode": "000000",
+    });
+
+    let response = app.post_verify_email_otp(&verify_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.wait_for_sent_emails(1).await;
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_return_206_without_sending_email_if_user_not_found() {
+    let mut app = TestApp::new_with_config(email_otp_config()).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_email_otp(&serde_json::json!({ "email": get_random_email() })).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_return_429_if_too_many_code_requests() {
+    let mut app = TestApp::new_with_config(email_otp_config()).await;
+
+    let email = get_random_email();
+    signup(&app, &email).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    let body = serde_json::json!({ "email": email });
+
+    assert_eq!(app.post_email_otp(&body).await.status().as_u16(), 206);
+    assert_eq!(app.post_email_otp(&body).await.status().as_u16(), 206);
+    assert_eq!(app.post_email_otp(&body).await.status().as_u16(), 429);
+
+    app.wait_for_sent_emails(2).await;
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_return_429_if_too_many_guesses() {
+    let mut app = TestApp::new_with_config(email_otp_config()).await;
+
+    let email = get_random_email();
+
+    let verify_body = serde_json::json!({
+        "email": email,
+        "loginAttemptId": uuid::Uuid::new_v4().to_string(),
+        "2FACode": "000000",
+    });
+
+    for _ in 0..3 {
+        assert_eq!(app.post_verify_email_otp(&verify_body).await.status().as_u16(), 401);
+    }
+    assert_eq!(app.post_verify_email_otp(&verify_body).await.status().as_u16(), 429);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_return_401_after_too_many_wrong_guesses_for_a_code() {
+    let mut config = email_otp_config();
+    config.two_fa = TwoFAConfig {
+        verify_limit: RateLimitPolicy::new(10, Duration::from_secs(60)),
+        max_failed_attempts: 2,
+    };
+    let mut app = TestApp::new_with_config(config).await;
+
+    let random_email = SecretString::new(get_random_email().into_boxed_str());
+    let email = Email::parse(random_email.clone()).unwrap();
+    signup(&app, random_email.expose_secret()).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_email_otp(&serde_json::json!({ "email": random_email.expose_secret() })).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let (login_attempt_id, two_fa_code) = app.get_two_fa_code(&email, TwoFACodePurpose::EmailOtp).await.unwrap();
+    app.wait_for_sent_emails(1).await;
+    let wrong_code = if two_fa_code.as_ref() == "000000" { "111111" } else { "000000" };
+
+    for _ in 0..2 {
+        let verify_body = serde_json::json!({
+            "email": random_email.expose_secret(),
+            "loginAttemptId": login_attempt_id.as_ref(),
+            "2FACode": wrong_code,
+        });
+        assert_eq!(app.post_verify_email_otp(&verify_body).await.status().as_u16(), 401);
+    }
+
+    // The code is gone, so even the right one no longer works
+    let verify_body = serde_json::json!({
+        "email": random_email.expose_secret(),
+        "loginAttemptId": login_attempt_id.as_ref(),
+        "2FACode": two_fa_code.as_ref(),
+    });
+    assert_eq!(app.post_verify_email_otp(&verify_body).await.status().as_u16(), 401);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_keep_codes_apart_from_password_login_codes() {
+    let mut app = TestApp::new_with_config(email_otp_config()).await;
+
+    let random_email = SecretString::new(get_random_email().into_boxed_str());
+    let email = Email::parse(random_email.clone()).unwrap();
+    signup_with_2fa(&app, random_email.expose_secret(), true).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = serde_json::json!({
+        "email": random_email.expose_secret(),
+        "password": "password123",
+    });
+    assert_eq!(app.post_login(&login_body).await.status().as_u16(), 206);
+
+    // Requesting a login code doesn't replace the pending 2FA code
+    let response = app.post_email_otp(&serde_json::json!({ "email": random_email.expose_secret() })).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let (otp_attempt_id, otp_code) = app.get_two_fa_code(&email, TwoFACodePurpose::EmailOtp).await.unwrap();
+    app.wait_for_sent_emails(2).await;
+    let (login_attempt_id, two_fa_code) = app.two_fa_code_store.get_code(&email, TwoFACodePurpose::Login).await.unwrap();
+
+    // Each code only works on the route it was sent for
+    let verify_body = serde_json::json!({
+        "email": random_email.expose_secret(),
+        "loginAttemptId": otp_attempt_id.as_ref(),
+        "2FACode": otp_code.as_ref(),
+    });
+    assert_eq!(app.post_verify_2fa(&verify_body).await.status().as_u16(), 401);
+
+    let verify_body = serde_json::json!({
+        "email": random_email.expose_secret(),
+        "loginAttemptId": login_attempt_id.as_ref(),
+        "2FACode": two_fa_code.as_ref(),
+    });
+    assert_eq!(app.post_verify_email_otp(&verify_body).await.status().as_u16(), 401);
+    assert_eq!(app.post_verify_2fa(&verify_body).await.status().as_u16(), 200);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_return_token_in_body_if_requested() {
+    let mut app = TestApp::new_with_config(email_otp_config()).await;
+
+    let random_email = SecretString::new(get_random_email().into_boxed_str());
+    let email = Email::parse(random_email.clone()).unwrap();
+    signup(&app, random_email.expose_secret()).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_email_otp(&serde_json::json!({ "email": random_email.expose_secret() })).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let (login_attempt_id, two_fa_code) = app.get_two_fa_code(&email, TwoFACodePurpose::EmailOtp).await.unwrap();
+    app.wait_for_sent_emails(1).await;
+
+    let verify_body = serde_json::json!({
+        "email": random_email.expose_secret(),
+        "loginAttemptId": login_attempt_id.as_ref(),
+        "2FACode": two_fa_code.as_ref(),
+        "returnToken": true,
+    });
+
+    let response = app.post_verify_email_otp(&verify_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.cookies().all(|cookie| cookie.name() != JWT_COOKIE_NAME));
+
+    let body = response.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(body["tokenType"], "Bearer");
+
+    let response = app.post_verify_token(&serde_json::json!({ "token": body["token"] })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_return_404_if_disabled() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_email_otp(&serde_json::json!({ "email": get_random_email() })).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
@@ -0,0 +1,168 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use auth_service::app_state::EmailOutboxStoreType;
+use auth_service::domain::{data_stores::EmailOutboxStore, Email, EmailClient, OutboxEmail};
+use auth_service::services::data_stores::postgres_email_outbox_store::PostgresEmailOutboxStore;
+use auth_service::services::email_outbox::{EmailOutboxWorker, OutboxEmailClient};
+use auth_service::services::postmark_email_client::PostmarkEmailClient;
+use auth_service::utils::config::EmailOutboxConfig;
+use auth_service::utils::constants::test;
+use secrecy::SecretString;
+use sqlx::PgPool;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+use wiremock::matchers::{method, path};
+
+use crate::helpers::{configure_postgresql, delete_database, get_random_email};
+
+fn email() -> Email {
+    Email::parse(SecretString::new(get_random_email().into_boxed_str())).unwrap()
+}
+
+// No waiting between retries so tests can run every attempt right away
+fn config(max_attempts: u32) -> EmailOutboxConfig {
+    EmailOutboxConfig {
+        max_attempts,
+        initial_backoff: Duration::ZERO,
+        ..EmailOutboxConfig::default()
+    }
+}
+
+fn worker(outbox_store: EmailOutboxStoreType, email_server: &MockServer, config: EmailOutboxConfig) -> EmailOutboxWorker {
+    let http_client = reqwest::Client::builder()
+        .timeout(test::email_client::TIMEOUT)
+        .build()
+        .unwrap();
+    let sender = Email::parse(SecretString::new(test::email_client::SENDER.to_owned().into_boxed_str())).unwrap();
+    let email_client = PostmarkEmailClient::new(
+        email_server.uri(),
+        sender,
+        SecretString::new("auth_token".to_owned().into_boxed_str()),
+        http_client,
+    );
+
+    EmailOutboxWorker::new(outbox_store, Arc::new(email_client), config)
+}
+
+async fn email_status(pg_pool: &PgPool) -> (String, i32) {
+    sqlx::query_as("SELECT status, attempts FROM email_outbox")
+        .fetch_one(pg_pool)
+        .await
+        .unwrap()
+}
+
+async fn clean_up(pg_pool: PgPool) {
+    let db_name = pg_pool.connect_options().get_database().unwrap().to_owned();
+    pg_pool.close().await;
+    delete_database(&db_name).await;
+}
+
+#[tokio::test]
+async fn should_deliver_queued_email_once() {
+    let pg_pool = configure_postgresql().await;
+    let outbox_store: EmailOutboxStoreType = Arc::new(PostgresEmailOutboxStore::new(pg_pool.clone()));
+    let email_server = MockServer::start().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&email_server)
+        .await;
+
+    let client = OutboxEmailClient::new(outbox_store.clone());
+    client.send_email(email(), "Subject", "<p>Body</p>", "Body").await.unwrap();
+
+    let worker = worker(outbox_store, &email_server, config(3));
+    assert_eq!(worker.process_batch().await.unwrap(), 1);
+    assert_eq!(worker.process_batch().await.unwrap(), 0);
+
+    assert_eq!(email_status(&pg_pool).await, ("sent".to_owned(), 1));
+
+    // The bodies may hold codes and links, they aren't kept after delivery
+    let bodies: (String, String) = sqlx::query_as("SELECT html_body, text_body FROM email_outbox")
+        .fetch_one(&pg_pool)
+        .await
+        .unwrap();
+    assert_eq!(bodies, (String::new(), String::new()));
+
+    clean_up(pg_pool).await;
+}
+
+#[tokio::test]
+async fn should_deliver_same_notice_queued_twice_twice() {
+    let pg_pool = configure_postgresql().await;
+    let outbox_store: EmailOutboxStoreType = Arc::new(PostgresEmailOutboxStore::new(pg_pool.clone()));
+    let email_server = MockServer::start().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&email_server)
+        .await;
+
+    // Like a notice sent for each signup attempt with a registered email
+    let client = OutboxEmailClient::new(outbox_store.clone());
+    let recipient = email();
+    client.send_email(recipient.clone(), "Signup attempt", "<p>Someone tried to sign up</p>", "Someone tried to sign up").await.unwrap();
+    client.send_email(recipient, "Signup attempt", "<p>Someone tried to sign up</p>", "Someone tried to sign up").await.unwrap();
+
+    let worker = worker(outbox_store, &email_server, config(3));
+    assert_eq!(worker.process_batch().await.unwrap(), 2);
+    assert_eq!(worker.process_batch().await.unwrap(), 0);
+
+    clean_up(pg_pool).await;
+}
+
+#[tokio::test]
+async fn should_dead_letter_after_max_attempts() {
+    let pg_pool = configure_postgresql().await;
+    let outbox_store: EmailOutboxStoreType = Arc::new(PostgresEmailOutboxStore::new(pg_pool.clone()));
+    let email_server = MockServer::start().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(2)
+        .mount(&email_server)
+        .await;
+
+    OutboxEmailClient::new(outbox_store.clone())
+        .send_email(email(), "Subject", "<p>Body</p>", "Body")
+        .await
+        .unwrap();
+
+    // Without backoff a failed email is due again within the same batch
+    let config = EmailOutboxConfig { batch_size: 1, ..config(2) };
+    let worker = worker(outbox_store, &email_server, config);
+    worker.process_batch().await.unwrap();
+    assert_eq!(email_status(&pg_pool).await, ("pending".to_owned(), 1));
+
+    worker.process_batch().await.unwrap();
+    assert_eq!(email_status(&pg_pool).await, ("dead".to_owned(), 2));
+
+    assert_eq!(worker.process_batch().await.unwrap(), 0);
+
+    clean_up(pg_pool).await;
+}
+
+#[tokio::test]
+async fn should_not_claim_email_twice_while_leased() {
+    let pg_pool = configure_postgresql().await;
+    let store = PostgresEmailOutboxStore::new(pg_pool.clone());
+
+    let outbox_email = OutboxEmail::new(email(), "Subject", "<p>Body</p>", "Body");
+    store.enqueue(outbox_email.clone()).await.unwrap();
+
+    let claimed = store.claim_next(Duration::from_secs(60)).await.unwrap().unwrap();
+    assert_eq!(claimed.id, outbox_email.id);
+    assert_eq!(claimed.attempts, 1);
+
+    assert!(store.claim_next(Duration::from_secs(60)).await.unwrap().is_none());
+
+    store.schedule_retry(outbox_email.id, "provider unavailable", Duration::ZERO).await.unwrap();
+    assert!(store.claim_next(Duration::from_secs(60)).await.unwrap().is_some());
+
+    clean_up(pg_pool).await;
+}
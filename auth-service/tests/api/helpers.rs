@@ -1,19 +1,26 @@
 use auth_service::{
     Application, 
-    app_state::{AppState, BannedTokenStoreType, TwoFACodeStoreType}, 
+    app_state::{AppState, BannedTokenStoreType, SessionStoreType, TwoFACodeStoreType}, 
     get_postgres_pool, 
-    get_redis_client, 
-    domain::Email,
+    get_redis_connection,
+    domain::{Email, LoginAttemptId, TwoFACode, TwoFACodePurpose},
     services::{
         data_stores::{
             postgres_user_store::PostgresUserStore,
+            postgres_account_lockout_store::PostgresAccountLockoutStore,
             redis_banned_token_store::RedisBannedTokenStore,
             redis_two_fa_code_store::RedisTwoFACodeStore,
+            redis_session_store::RedisSessionStore,
+            postgres_banned_token_store::PostgresBannedTokenStore,
+            postgres_two_fa_code_store::PostgresTwoFACodeStore,
+            postgres_session_store::PostgresSessionStore,
+            hashmap_rate_limit_store::HashmapRateLimitStore,
         }, 
-        mock_email_client::MockEmailClient,
         postmark_email_client::PostmarkEmailClient,
+        geoip::GeoIp,
+        redis_connection::RedisConnection,
     }, 
-    utils::constants::{DATABASE_URL, REDIS_HOST_NAME, prod::email_client, test}
+    utils::{config::{redis_url, Config, IpRateLimitConfig, RedisConfig, StoreKind}, constants::{CSRF_COOKIE_NAME, CSRF_HEADER_NAME, DATABASE_URL, REDIS_HOST_NAME, test}}
 };
 use sqlx::{Executor, PgConnection, PgPool, postgres::{PgConnectOptions, PgPoolOptions}, Connection};
 use wiremock::MockServer;
@@ -21,9 +28,8 @@ use wiremock::MockServer;
 use std::{
     collections::HashMap, str::FromStr, sync::Arc
 };
-use tokio::sync::RwLock;
 use uuid::Uuid;
-use reqwest::cookie::Jar;
+use reqwest::{Url, cookie::{CookieStore, Jar}};
 use reqwest::Client;
 use secrecy::{ExposeSecret, SecretString};
 
@@ -33,28 +39,68 @@ pub struct TestApp {
     pub http_client: reqwest::Client,
     pub banned_token_store: BannedTokenStoreType,
     pub two_fa_code_store: TwoFACodeStoreType,
+    pub session_store: SessionStoreType,
     pub email_server: MockServer,
+    pub pg_pool: PgPool,
     pub db_name: String,
     pub clean_up_called: bool,
 }
 
 impl TestApp {
+    // Most tests send many requests from the same address, so the IP rate limiter is off
     pub async fn new() -> Self {
+        let config = Config {
+            ip_rate_limit: IpRateLimitConfig { enabled: false, ..IpRateLimitConfig::default() },
+            ..Config::default()
+        };
+
+        Self::new_with_config(config).await
+    }
+
+    pub async fn new_with_config(config: Config) -> Self {
         let pg_pool = configure_postgresql().await;
         let db_name = pg_pool.connect_options().get_database().unwrap().to_string();
-        let redis_conn = Arc::new(RwLock::new(configure_redis()));
-
-        let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
-        let banned_token_store = Arc::new(RwLock::new(RedisBannedTokenStore::new(redis_conn.clone())));
-        let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_conn)));
-        // let email_client = Arc::new(RwLock::new(MockEmailClient::default()));
+        // Apps that keep everything in Postgres run without a Redis server
+        let redis_conn = match config.uses(StoreKind::Redis) {
+            true => Some(configure_redis().await),
+            false => None,
+        };
+        let redis = || redis_conn.clone().expect("Redis is not connected");
+
+        let user_store = Arc::new(PostgresUserStore::new(pg_pool.clone()));
+        let account_lockout_store = Arc::new(PostgresAccountLockoutStore::new(pg_pool.clone()));
+        let banned_token_store: BannedTokenStoreType = match config.stores.banned_tokens {
+            StoreKind::Postgres => Arc::new(PostgresBannedTokenStore::new(pg_pool.clone())),
+            _ => Arc::new(RedisBannedTokenStore::new(redis())),
+        };
+        let two_fa_code_store: TwoFACodeStoreType = match config.stores.two_fa_codes {
+            StoreKind::Postgres => Arc::new(PostgresTwoFACodeStore::new(pg_pool.clone())),
+            _ => Arc::new(RedisTwoFACodeStore::new(redis())),
+        };
+        let session_store: SessionStoreType = match config.stores.sessions {
+            StoreKind::Postgres => Arc::new(PostgresSessionStore::new(pg_pool.clone())),
+            _ => Arc::new(RedisSessionStore::new(redis())),
+        };
+        // Every app gets its own buckets, so tests running in parallel don't share limits
+        let rate_limit_store = Arc::new(HashmapRateLimitStore::default());
+        // let email_client = Arc::new(MockEmailClient::default());
 
         // Set up mock email server
         let email_server = MockServer::start().await;
         let base_url = email_server.uri();
-        let email_client = Arc::new(RwLock::new(configure_postmark_email_client(base_url)));
-
-        let app_state = AppState::new(user_store, banned_token_store.clone(), two_fa_code_store.clone(), email_client);
+        let email_client = Arc::new(configure_postmark_email_client(base_url));
+
+        let app_state = AppState::new(
+            user_store,
+            banned_token_store.clone(),
+            two_fa_code_store.clone(),
+            email_client,
+            rate_limit_store,
+            account_lockout_store,
+            session_store.clone(),
+            GeoIp::default(),
+            config,
+        );
 
         let app = Application::build(app_state,test::APP_ADDRESS)
             .await
@@ -79,7 +125,9 @@ impl TestApp {
             http_client,
             banned_token_store,
             two_fa_code_store,
+            session_store,
             email_server,
+            pg_pool,
             db_name,
             clean_up_called: false,
         }
@@ -111,7 +159,52 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn post_magic_link<T: serde::Serialize>(&self, body: &T) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/login/magic-link", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_magic_link_callback(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/login/magic-link/callback", &self.address))
+            .query(&[("token", token)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_email_otp<T: serde::Serialize>(&self, body: &T) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/login/email-otp", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_verify_email_otp<T: serde::Serialize>(&self, body: &T) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/login/email-otp/verify", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn post_logout(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/logout", &self.address))
+            .header(CSRF_HEADER_NAME, self.csrf_token())
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_logout_without_csrf_token(&self) -> reqwest::Response {
         self.http_client
             .post(format!("{}/logout", &self.address))
             .send()
@@ -119,6 +212,62 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn post_logout_with_bearer(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/logout", &self.address))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_sessions(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/sessions", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_sessions_with_bearer(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/sessions", &self.address))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_session(&self, id: &str) -> reqwest::Response {
+        self.http_client
+            .delete(format!("{}/sessions/{}", &self.address, id))
+            .header(CSRF_HEADER_NAME, self.csrf_token())
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_csrf_token(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/csrf-token", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // The CSRF token the server set in the cookie jar, like the frontend reads it
+    pub fn csrf_token(&self) -> String {
+        let url = Url::parse(&self.address).expect("Failed to parse URL");
+        self.cookie_jar
+            .cookies(&url)
+            .and_then(|cookies| {
+                parse_cookie_values(cookies.to_str().unwrap())
+                    .get(CSRF_COOKIE_NAME)
+                    .map(|token| token.to_string())
+            })
+            .unwrap_or_default()
+    }
+
     pub async fn post_verify_2fa<T: serde::Serialize>(&self, body: &T) -> reqwest::Response {
         self.http_client
             .post(format!("{}/verify-2fa", &self.address))
@@ -137,39 +286,148 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
-    pub async fn delete_database(&mut self, db_name: &str) {
-        let postgresql_conn_url: String = DATABASE_URL.expose_secret().to_owned();
+    pub async fn post_change_email<T: serde::Serialize>(&self, body: &T) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/change-email", &self.address))
+            .header(CSRF_HEADER_NAME, self.csrf_token())
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
 
-        let connection_options = PgConnectOptions::from_str(&postgresql_conn_url)
-            .expect("Failed to parse PostgreSQL connection string");
+    pub async fn post_change_email_with_bearer<T: serde::Serialize>(&self, body: &T, token: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/change-email", &self.address))
+            .bearer_auth(token)
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
 
-        let mut connection = PgConnection::connect_with(&connection_options)
+    pub async fn get_change_email_confirm(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/change-email/confirm", &self.address))
+            .query(&[("token", token)])
+            .send()
             .await
-            .expect("Failed to connect to Postgres");
-
-        // Kill any active connections to the database
-        connection
-            .execute(
-                format!(
-                    r#"
-                    SELECT pg_terminate_backend(pg_stat_activity.pid)
-                    FROM pg_stat_activity
-                    WHERE pg_stat_activity.datname = '{}'
-                    AND pid <> pg_backend_pid();
-            "#,
-                    db_name
-                )
-                .as_str(),
-            )
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_change_email_revert(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/change-email/revert", &self.address))
+            .query(&[("token", token)])
+            .send()
             .await
-            .expect("Failed to drop the database.");
+            .expect("Failed to execute request.")
+    }
 
-        // Drop the database
-        connection
-            .execute(format!(r#"DROP DATABASE "{}";"#, db_name).as_str())
+    pub async fn get_dev_email_preview(&self, name: &str, query: &[(&str, &str)]) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/dev/emails/{}", &self.address, name))
+            .query(query)
+            .send()
             .await
-            .expect("Failed to drop the database.");
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_postmark_webhook<T: serde::Serialize>(&self, body: &T, headers: &[(&str, &str)]) -> reqwest::Response {
+        let mut request = self.http_client
+            .post(format!("{}/webhooks/postmark", &self.address))
+            .json(body);
+
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+
+        request
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
 
+    pub async fn post_unlock_account<T: serde::Serialize>(&self, body: &T, admin_token: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/admin/unlock-account", &self.address))
+            .bearer_auth(admin_token)
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_metrics(&self, admin_token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/metrics", &self.address))
+            .bearer_auth(admin_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_dev_mailbox(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/dev/mailbox", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // Find the token of the most recent emailed link that points to `path`. Some
+    // emails are sent in the background, so wait a little for them.
+    pub async fn get_token_from_sent_email(&self, path: &str) -> Option<String> {
+        for _ in 0..50 {
+            if let Some(token) = self.find_token_in_sent_emails(path).await {
+                return Some(token);
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+
+        None
+    }
+
+    // Login codes are stored in the background, after the response is sent
+    pub async fn get_two_fa_code(&self, email: &Email, purpose: TwoFACodePurpose) -> Option<(LoginAttemptId, TwoFACode)> {
+        for _ in 0..50 {
+            if let Ok(code) = self.two_fa_code_store.get_code(email, purpose).await {
+                return Some(code);
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+
+        None
+    }
+
+    // Waits for emails that are sent in the background to reach the mock server
+    pub async fn wait_for_sent_emails(&self, count: usize) {
+        for _ in 0..50 {
+            let received = self.email_server.received_requests().await.map_or(0, |requests| requests.len());
+            if received >= count {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+    }
+
+    async fn find_token_in_sent_emails(&self, path: &str) -> Option<String> {
+        let requests = self.email_server.received_requests().await?;
+        let marker = format!("{}?token=", path);
+
+        requests.iter().rev().find_map(|request| {
+            let body: serde_json::Value = serde_json::from_slice(&request.body).ok()?;
+            let text = body.get("TextBody")?.as_str()?;
+            let start = text.find(&marker)? + marker.len();
+            let token = text[start..]
+                .split(|c: char| c.is_whitespace())
+                .next()?;
+            Some(token.to_owned())
+        })
+    }
+
+    pub async fn delete_database(&mut self, db_name: &str) {
+        delete_database(db_name).await;
         self.clean_up_called = true;
     }
 
@@ -183,6 +441,40 @@ impl Drop for TestApp {
     }
 }
 
+pub async fn delete_database(db_name: &str) {
+    let postgresql_conn_url: String = DATABASE_URL.expose_secret().to_owned();
+
+    let connection_options = PgConnectOptions::from_str(&postgresql_conn_url)
+        .expect("Failed to parse PostgreSQL connection string");
+
+    let mut connection = PgConnection::connect_with(&connection_options)
+        .await
+        .expect("Failed to connect to Postgres");
+
+    // Kill any active connections to the database
+    connection
+        .execute(
+            format!(
+                r#"
+                SELECT pg_terminate_backend(pg_stat_activity.pid)
+                FROM pg_stat_activity
+                WHERE pg_stat_activity.datname = '{}'
+                AND pid <> pg_backend_pid();
+        "#,
+                db_name
+            )
+            .as_str(),
+        )
+        .await
+        .expect("Failed to drop the database.");
+
+    // Drop the database
+    connection
+        .execute(format!(r#"DROP DATABASE "{}";"#, db_name).as_str())
+        .await
+        .expect("Failed to drop the database.");
+}
+
 pub fn get_random_email() -> String {
     format!("{}@example.com", Uuid::new_v4())
 }
@@ -213,7 +505,7 @@ pub fn get_all_cookies(response: &reqwest::Response) -> HashMap<String, String>
     cookies
 }
 
-async fn configure_postgresql() -> PgPool {
+pub async fn configure_postgresql() -> PgPool {
     let postgresql_conn_url = DATABASE_URL.expose_secret().to_owned();
 
     // We are creating a new database for each test case, and we need to ensure each database has a unique name!
@@ -259,10 +551,11 @@ async fn configure_database(db_conn_string: &str, db_name: &str) {
         .expect("Failed to migrate the database");
 }
 
-fn configure_redis() -> redis::Connection {
-    get_redis_client(REDIS_HOST_NAME.to_owned())
-        .expect("Failed to get Redis client")
-        .get_connection()
+async fn configure_redis() -> RedisConnection {
+    let config = RedisConfig::Standalone { url: redis_url(&REDIS_HOST_NAME) };
+
+    get_redis_connection(&config)
+        .await
         .expect("Failed to get Redis connection")
 }
 
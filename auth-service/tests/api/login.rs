@@ -1,4 +1,4 @@
-use auth_service::domain::{Email, ErrorResponse};
+use auth_service::domain::{Email, ErrorResponse, TwoFACodePurpose};
 use auth_service::routes::TwoFactorAuthResponse;
 use auth_service::utils::constants::JWT_COOKIE_NAME;
 use secrecy::{ExposeSecret, SecretString};
@@ -83,12 +83,11 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
     assert_eq!(response.status().as_u16(), 206);
 
     // Verify that 2FA code was added to store
-    let two_fa_code_store = app.two_fa_code_store.write().await;
-    assert!(two_fa_code_store.get_code(&email).await.is_ok());
+    let two_fa_code_store = &app.two_fa_code_store;
+    assert!(two_fa_code_store.get_code(&email, TwoFACodePurpose::Login).await.is_ok());
 
     // Verify the response JSON is correct
-    let (login_attempt_id, _) = two_fa_code_store.get_code(&email).await.unwrap();
-    drop(two_fa_code_store);
+    let (login_attempt_id, _) = two_fa_code_store.get_code(&email, TwoFACodePurpose::Login).await.unwrap();
 
     let response_json = response
         .json::<TwoFactorAuthResponse>()
@@ -193,4 +192,43 @@ async fn should_return_422_if_malformed_credentials() {
     }
 
     app.delete_database(&app.db_name.clone()).await;
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn should_return_token_in_body_if_requested() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "returnToken": true,
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.cookies().all(|cookie| cookie.name() != JWT_COOKIE_NAME));
+
+    let body = response.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(body["tokenType"], "Bearer");
+    assert_eq!(body["expiresIn"], 600);
+
+    let verify_token_body = serde_json::json!({
+        "token": body["token"],
+    });
+
+    let response = app.post_verify_token(&verify_token_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
@@ -4,7 +4,6 @@ use auth_service::{
 };
 
 use reqwest::{Url, cookie::CookieStore};
-use tokio::sync::RwLock;
 use secrecy::SecretString;
 
 use std::sync::Arc;
@@ -59,12 +58,11 @@ async fn should_return_200_if_valid_jwt_cookie() {
     }
 
     let auth_cookie_login_secret = SecretString::new(auth_cookie_login.value().to_owned().into_boxed_str());
-    let banned_token_store = app.banned_token_store.write().await;
+    let banned_token_store = &app.banned_token_store;
     let is_token_banned = banned_token_store
         .check_token(&auth_cookie_login_secret)
         .await
         .unwrap();
-    drop(banned_token_store);
 
     assert!(is_token_banned);
 
@@ -177,10 +175,58 @@ async fn should_return_401_if_invalid_token() {
     let cookies = parse_cookie_values(cookies.to_str().unwrap());
 
     let cookie = cookies.get(JWT_COOKIE_NAME).unwrap();
-    let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+    let banned_token_store = Arc::new(HashsetBannedTokenStore::default());
 
     let result = auth::validate_token(cookie, banned_token_store).await;
     assert!(result.is_err());
 
     app.delete_database(&app.db_name.clone()).await;
 }
+
+#[tokio::test]
+async fn should_logout_with_bearer_token() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "returnToken": true,
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = response.json::<serde_json::Value>().await.unwrap();
+    let token = body["token"].as_str().unwrap();
+
+    // Bearer requests don't need a CSRF token
+    let response = app.post_logout_with_bearer(token).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The token is banned now
+    let response = app.post_logout_with_bearer(token).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_invalid_bearer_token() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_logout_with_bearer("invalid").await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
@@ -0,0 +1,199 @@
+use std::time::Duration;
+
+use auth_service::domain::{Email, RateLimitPolicy, TwoFACodePurpose};
+use auth_service::routes::TwoFactorAuthResponse;
+use auth_service::utils::config::{Config, MagicLinkConfig};
+use auth_service::utils::constants::JWT_COOKIE_NAME;
+use secrecy::{ExposeSecret, SecretString};
+use wiremock::{Mock, ResponseTemplate};
+use wiremock::matchers::{method, path};
+
+use crate::helpers::{TestApp, get_random_email};
+
+async fn signup(app: &TestApp, email: &str, requires_2fa: bool) {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": requires_2fa
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+}
+
+#[tokio::test]
+async fn should_return_200_and_log_in_with_valid_link() {
+    let mut app = TestApp::new().await;
+
+    let email = get_random_email();
+    signup(&app, &email, false).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_magic_link(&serde_json::json!({ "email": email })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = app.get_token_from_sent_email("/login/magic-link/callback").await.unwrap();
+    let response = app.get_magic_link_callback(&token).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+
+    assert!(!auth_cookie.value().is_empty());
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_link_used_twice() {
+    let mut app = TestApp::new().await;
+
+    let email = get_random_email();
+    signup(&app, &email, false).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_magic_link(&serde_json::json!({ "email": email })).await;
+
+    let token = app.get_token_from_sent_email("/login/magic-link/callback").await.unwrap();
+    let response = app.get_magic_link_callback(&token).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.get_magic_link_callback(&token).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_return_206_if_2fa_enabled() {
+    let mut app = TestApp::new().await;
+
+    let random_email = SecretString::new(get_random_email().into_boxed_str());
+    let email = Email::parse(random_email.clone()).unwrap();
+    signup(&app, random_email.expose_secret(), true).await;
+
+    // One email for the link and one for the 2FA code
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_magic_link(&serde_json::json!({ "email": random_email.expose_secret() })).await;
+
+    let token = app.get_token_from_sent_email("/login/magic-link/callback").await.unwrap();
+    let response = app.get_magic_link_callback(&token).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    assert!(response.cookies().all(|cookie| cookie.name() != JWT_COOKIE_NAME));
+
+    let response_json = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+
+    let two_fa_code_store = &app.two_fa_code_store;
+    let (login_attempt_id, _) = two_fa_code_store.get_code(&email, TwoFACodePurpose::Login).await.unwrap();
+
+    assert_eq!(response_json.login_attempt_id, login_attempt_id.as_ref().to_owned());
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_return_200_without_sending_email_if_user_not_found() {
+    let mut app = TestApp::new().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_magic_link(&serde_json::json!({ "email": get_random_email() })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+// Unknown emails count too, so the limit doesn't tell them apart
+#[tokio::test]
+async fn should_return_429_if_too_many_link_requests() {
+    let config = Config {
+        magic_link: MagicLinkConfig { request_limit: RateLimitPolicy::new(2, Duration::from_secs(60)) },
+        ..Config::default()
+    };
+    let mut app = TestApp::new_with_config(config).await;
+
+    let body = serde_json::json!({ "email": get_random_email() });
+
+    assert_eq!(app.post_magic_link(&body).await.status().as_u16(), 200);
+    assert_eq!(app.post_magic_link(&body).await.status().as_u16(), 200);
+    assert_eq!(app.post_magic_link(&body).await.status().as_u16(), 429);
+
+    // Other emails have their own limit
+    let body = serde_json::json!({ "email": get_random_email() });
+    assert_eq!(app.post_magic_link(&body).await.status().as_u16(), 200);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+// A failed delivery would otherwise only ever show up for registered addresses
+#[tokio::test]
+async fn should_return_200_if_email_fails_to_send() {
+    let mut app = TestApp::new().await;
+
+    let email = get_random_email();
+    signup(&app, &email, false).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_magic_link(&serde_json::json!({ "email": email })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Wait for the delivery attempt in the background
+    app.get_token_from_sent_email("/login/magic-link/callback").await.unwrap();
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_invalid_email() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_magic_link(&serde_json::json!({ "email": "exampleattest.com" })).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_invalid_token() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_magic_link_callback("invalid").await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
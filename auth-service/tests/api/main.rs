@@ -5,3 +5,15 @@ mod login;
 mod logout;
 mod verify_2fa;
 mod verify_token;
+mod change_email;
+mod magic_link;
+mod email_otp;
+mod dev;
+mod email_outbox;
+mod webhooks;
+mod account_lockout;
+mod metrics;
+mod rate_limit;
+mod csrf;
+mod sessions;
+mod postgres_stores;
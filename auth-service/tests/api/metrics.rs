@@ -0,0 +1,48 @@
+use auth_service::utils::config::{AdminConfig, Config};
+use auth_service::utils::metrics::init_metrics;
+use secrecy::SecretString;
+
+use crate::helpers::TestApp;
+
+const ADMIN_API_TOKEN: &str = "admin-token";
+
+fn admin_config() -> Config {
+    Config {
+        admin: AdminConfig {
+            api_token: Some(SecretString::new(ADMIN_API_TOKEN.to_owned().into_boxed_str())),
+        },
+        ..Config::default()
+    }
+}
+
+#[tokio::test]
+async fn should_return_metrics_with_admin_token() {
+    // Fails if the recorder is installed already, which is fine
+    let _ = init_metrics();
+    let mut app = TestApp::new_with_config(admin_config()).await;
+
+    let response = app.get_metrics(ADMIN_API_TOKEN).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_return_401_without_admin_token() {
+    let mut app = TestApp::new_with_config(admin_config()).await;
+
+    let response = app.get_metrics("wrong-token").await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_return_404_if_admin_token_is_not_configured() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_metrics(ADMIN_API_TOKEN).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
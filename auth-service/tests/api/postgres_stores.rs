@@ -0,0 +1,174 @@
+use std::time::Duration;
+
+use auth_service::{
+    domain::{Email, SessionId, TwoFACodePurpose},
+    services::postgres_purger::PostgresPurger,
+    utils::{
+        config::{AccountLockoutConfig, AuthMode, Config, IpRateLimitConfig, StoreKind, StoresConfig},
+        constants::JWT_COOKIE_NAME,
+    },
+};
+use secrecy::SecretString;
+use wiremock::{Mock, ResponseTemplate, matchers::{method, path}};
+
+use crate::helpers::{TestApp, get_all_cookies, get_random_email};
+
+// Everything is kept in PostgreSQL, so these apps run without Redis
+async fn postgres_app(auth_mode: AuthMode) -> TestApp {
+    let config = Config {
+        auth_mode,
+        stores: StoresConfig {
+            banned_tokens: StoreKind::Postgres,
+            two_fa_codes: StoreKind::Postgres,
+            sessions: StoreKind::Postgres,
+            rate_limits: StoreKind::Memory,
+            ..StoresConfig::default()
+        },
+        account_lockout: AccountLockoutConfig { store: StoreKind::Postgres, ..AccountLockoutConfig::default() },
+        ip_rate_limit: IpRateLimitConfig { enabled: false, ..IpRateLimitConfig::default() },
+        ..Config::default()
+    };
+    assert!(!config.uses(StoreKind::Redis));
+
+    TestApp::new_with_config(config).await
+}
+
+async fn signup_and_login(app: &TestApp, email: &str, requires_2fa: bool) -> reqwest::Response {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": requires_2fa
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+    });
+    app.post_login(&login_body).await
+}
+
+#[tokio::test]
+async fn logout_should_ban_jwt() {
+    let mut app = postgres_app(AuthMode::Jwt).await;
+
+    let response = signup_and_login(&app, &get_random_email(), false).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let token = get_all_cookies(&response).remove(JWT_COOKIE_NAME).unwrap();
+
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let is_banned = app.banned_token_store
+        .check_token(&SecretString::new(token.clone().into_boxed_str()))
+        .await
+        .unwrap();
+    assert!(is_banned);
+
+    let response = app.post_verify_token(&serde_json::json!({ "token": token })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_login_with_2fa_code() {
+    let mut app = postgres_app(AuthMode::Jwt).await;
+    let random_email = get_random_email();
+    let email = Email::parse(SecretString::new(random_email.clone().into_boxed_str())).unwrap();
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = signup_and_login(&app, &random_email, true).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let (login_attempt_id, two_fa_code) = app.two_fa_code_store.get_code(&email, TwoFACodePurpose::Login).await.unwrap();
+
+    let verify_body = serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": login_attempt_id.as_ref(),
+        "2FACode": two_fa_code.as_ref(),
+    });
+    let response = app.post_verify_2fa(&verify_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The code can only be used once
+    let response = app.post_verify_2fa(&verify_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn logout_should_end_session() {
+    let mut app = postgres_app(AuthMode::Session).await;
+    let random_email = get_random_email();
+
+    let response = signup_and_login(&app, &random_email, false).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let session_id = get_all_cookies(&response).remove(JWT_COOKIE_NAME).unwrap();
+
+    let session = app.session_store
+        .get_session(&SessionId::parse(session_id.clone()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(session.email.as_ref(), random_email);
+    assert!(session.ip.is_some());
+
+    let response = app.post_verify_token(&serde_json::json!({ "token": session_id })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_verify_token(&serde_json::json!({ "token": session_id })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn purge_should_only_delete_expired_rows() {
+    let mut app = postgres_app(AuthMode::Jwt).await;
+
+    let token = SecretString::new("live-token".to_owned().into_boxed_str());
+    app.banned_token_store.add_token(&token, Duration::from_secs(60)).await.unwrap();
+    sqlx::query("INSERT INTO banned_tokens (token_hash, expires_at) VALUES ('expired', now() - interval '1 second')")
+        .execute(&app.pg_pool)
+        .await
+        .unwrap();
+
+    // Only the email that finished longer ago than the retention is deleted
+    sqlx::query(
+        r#"
+        INSERT INTO email_outbox (id, recipient, subject, html_body, text_body, status, sent_at, dead_at)
+        VALUES
+            (gen_random_uuid(), 'a@example.com', 'Subject', '', '', 'sent', now() - interval '2 hours', NULL),
+            (gen_random_uuid(), 'a@example.com', 'Subject', '', '', 'dead', NULL, now() - interval '1 minute'),
+            (gen_random_uuid(), 'a@example.com', 'Subject', '<p>Body</p>', 'Body', 'pending', NULL, NULL)
+        "#,
+    )
+    .execute(&app.pg_pool)
+    .await
+    .unwrap();
+
+    let purger = PostgresPurger::new(app.pg_pool.clone(), Duration::from_secs(60), Duration::from_secs(3600));
+    assert_eq!(purger.purge().await.unwrap(), 2);
+    assert_eq!(purger.purge().await.unwrap(), 0);
+
+    assert!(app.banned_token_store.check_token(&token).await.unwrap());
+
+    let outbox: i64 = sqlx::query_scalar("SELECT count(*) FROM email_outbox")
+        .fetch_one(&app.pg_pool)
+        .await
+        .unwrap();
+    assert_eq!(outbox, 2);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
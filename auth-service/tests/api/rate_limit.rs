@@ -0,0 +1,115 @@
+use std::time::Duration;
+
+use auth_service::domain::RateLimitPolicy;
+use auth_service::utils::config::{Config, IpRateLimitConfig};
+
+use crate::helpers::{TestApp, get_random_email};
+
+fn rate_limit_config(trusted_proxies: &str) -> Config {
+    Config {
+        ip_rate_limit: IpRateLimitConfig {
+            trusted_proxies: trusted_proxies.parse().unwrap(),
+            signup: RateLimitPolicy::new(2, Duration::from_secs(60)),
+            login: RateLimitPolicy::new(1, Duration::from_secs(60)),
+            magic_link: RateLimitPolicy::new(1, Duration::from_secs(60)),
+            ..IpRateLimitConfig::default()
+        },
+        ..Config::default()
+    }
+}
+
+fn signup_body() -> serde_json::Value {
+    serde_json::json!({
+        "email": get_random_email(),
+        "password": "password123",
+        "requires2FA": false
+    })
+}
+
+async fn post_signup_from(app: &TestApp, forwarded_for: &str) -> reqwest::Response {
+    app.http_client
+        .post(format!("{}/signup", &app.address))
+        .header("X-Forwarded-For", forwarded_for)
+        .json(&signup_body())
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+#[tokio::test]
+async fn should_return_429_with_retry_after_when_limit_is_reached() {
+    let mut app = TestApp::new_with_config(rate_limit_config("")).await;
+
+    for _ in 0..2 {
+        let response = app.post_signup(&signup_body()).await;
+        assert_eq!(response.status().as_u16(), 201);
+    }
+
+    let response = app.post_signup(&signup_body()).await;
+    assert_eq!(response.status().as_u16(), 429);
+
+    let retry_after: u64 = response.headers()["retry-after"].to_str().unwrap().parse().unwrap();
+    assert!(retry_after > 0 && retry_after <= 60);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_limit_routes_separately() {
+    let mut app = TestApp::new_with_config(rate_limit_config("")).await;
+
+    let login_body = serde_json::json!({
+        "email": get_random_email(),
+        "password": "password123",
+    });
+
+    assert_eq!(app.post_login(&login_body).await.status().as_u16(), 401);
+    assert_eq!(app.post_login(&login_body).await.status().as_u16(), 429);
+
+    // Signups still have their own tokens
+    assert_eq!(app.post_signup(&signup_body()).await.status().as_u16(), 201);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_limit_link_requests_across_emails() {
+    let mut app = TestApp::new_with_config(rate_limit_config("")).await;
+
+    let response = app.post_magic_link(&serde_json::json!({ "email": get_random_email() })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_magic_link(&serde_json::json!({ "email": get_random_email() })).await;
+    assert_eq!(response.status().as_u16(), 429);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_use_forwarded_address_from_trusted_proxy() {
+    let mut app = TestApp::new_with_config(rate_limit_config("127.0.0.1")).await;
+
+    for _ in 0..2 {
+        assert_eq!(post_signup_from(&app, "198.51.100.1").await.status().as_u16(), 201);
+    }
+    assert_eq!(post_signup_from(&app, "198.51.100.1").await.status().as_u16(), 429);
+
+    // Another client behind the same proxy has its own bucket
+    assert_eq!(post_signup_from(&app, "198.51.100.2").await.status().as_u16(), 201);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_ignore_forwarded_address_from_untrusted_peer() {
+    let mut app = TestApp::new_with_config(rate_limit_config("")).await;
+
+    for _ in 0..2 {
+        assert_eq!(post_signup_from(&app, "198.51.100.1").await.status().as_u16(), 201);
+    }
+
+    // A client can't get a new bucket by making up an address
+    assert_eq!(post_signup_from(&app, "198.51.100.2").await.status().as_u16(), 429);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
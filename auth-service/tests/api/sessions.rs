@@ -0,0 +1,292 @@
+use std::time::Duration;
+
+use auth_service::domain::{Email, SessionId, SessionPolicy};
+use auth_service::utils::config::{AuthMode, Config, IpRateLimitConfig, SessionConfig};
+use auth_service::utils::{auth, constants::JWT_COOKIE_NAME};
+use secrecy::SecretString;
+
+use crate::helpers::{TestApp, get_random_email, get_all_cookies};
+
+async fn session_app(policy: SessionPolicy) -> TestApp {
+    let config = Config {
+        auth_mode: AuthMode::Session,
+        session: SessionConfig { policy, ..SessionConfig::default() },
+        ip_rate_limit: IpRateLimitConfig { enabled: false, ..IpRateLimitConfig::default() },
+        ..Config::default()
+    };
+
+    TestApp::new_with_config(config).await
+}
+
+async fn signup_and_login(app: &TestApp, email: &str, return_token: bool) -> reqwest::Response {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "returnToken": return_token,
+    });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+}
+
+#[tokio::test]
+async fn should_set_session_id_cookie_on_login() {
+    let mut app = session_app(SessionConfig::default().policy).await;
+    let random_email = get_random_email();
+
+    let response = signup_and_login(&app, &random_email, false).await;
+    let session_id = get_all_cookies(&response).remove(JWT_COOKIE_NAME).expect("No auth cookie found");
+
+    let session_id = SessionId::parse(session_id).expect("Cookie is not a session ID");
+    let session = app.session_store.get_session(&session_id).await.unwrap();
+    assert_eq!(session.email.as_ref(), random_email);
+    assert!(session.ip.is_some());
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn verify_token_should_return_claims_of_session() {
+    let mut app = session_app(SessionConfig::default().policy).await;
+    let random_email = get_random_email();
+
+    let response = signup_and_login(&app, &random_email, false).await;
+    let session_id = get_all_cookies(&response).remove(JWT_COOKIE_NAME).unwrap();
+
+    let response = app.post_verify_token(&serde_json::json!({ "token": session_id })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let claims: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(claims["sub"], random_email);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn logout_should_revoke_session_immediately() {
+    let mut app = session_app(SessionConfig::default().policy).await;
+    let random_email = get_random_email();
+
+    let response = signup_and_login(&app, &random_email, false).await;
+    let session_id = get_all_cookies(&response).remove(JWT_COOKIE_NAME).unwrap();
+
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_verify_token(&serde_json::json!({ "token": session_id })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn bearer_session_should_work_until_logout() {
+    let mut app = session_app(SessionConfig::default().policy).await;
+    let random_email = get_random_email();
+
+    let response = signup_and_login(&app, &random_email, true).await;
+    assert!(!get_all_cookies(&response).contains_key(JWT_COOKIE_NAME));
+
+    let body: serde_json::Value = response.json().await.unwrap();
+    let session_id = body["token"].as_str().unwrap().to_owned();
+    assert_eq!(body["tokenType"], "Bearer");
+    assert_eq!(body["expiresIn"], 1800);
+
+    let response = app.post_logout_with_bearer(&session_id).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_logout_with_bearer(&session_id).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn session_should_end_after_idle_timeout() {
+    let policy = SessionPolicy::new(Duration::from_secs(1), Duration::from_secs(3600));
+    let mut app = session_app(policy).await;
+    let random_email = get_random_email();
+
+    let response = signup_and_login(&app, &random_email, false).await;
+    let session_id = get_all_cookies(&response).remove(JWT_COOKIE_NAME).unwrap();
+
+    tokio::time::sleep(Duration::from_secs(2)).await;
+
+    let response = app.post_verify_token(&serde_json::json!({ "token": session_id })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn jwt_should_be_rejected_in_session_mode() {
+    let mut app = session_app(SessionConfig::default().policy).await;
+    let email = Email::parse(SecretString::new(get_random_email().into_boxed_str())).unwrap();
+    let token = auth::generate_auth_token(&email, None).unwrap();
+
+    let response = app.post_verify_token(&serde_json::json!({ "token": token })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+async fn login_with_bearer(app: &TestApp, email: &str) -> String {
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "returnToken": true,
+    });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body: serde_json::Value = response.json().await.unwrap();
+    body["token"].as_str().unwrap().to_owned()
+}
+
+async fn list_sessions(app: &TestApp) -> Vec<serde_json::Value> {
+    let response = app.get_sessions().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body: serde_json::Value = response.json().await.unwrap();
+    body["sessions"].as_array().unwrap().clone()
+}
+
+#[tokio::test]
+async fn should_list_sessions_of_user() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+
+    signup_and_login(&app, &random_email, false).await;
+    login_with_bearer(&app, &random_email).await;
+
+    let sessions = list_sessions(&app).await;
+    assert_eq!(sessions.len(), 2);
+    assert_eq!(sessions.iter().filter(|session| session["current"] == true).count(), 1);
+    assert!(sessions.iter().all(|session| session["ip"] == "127.0.0.1"));
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_return_401_listing_sessions_without_token() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_sessions_with_bearer("invalid").await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn revoking_session_should_reject_its_jwt() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+
+    signup_and_login(&app, &random_email, false).await;
+    let bearer_token = login_with_bearer(&app, &random_email).await;
+
+    let other = list_sessions(&app).await
+        .into_iter()
+        .find(|session| session["current"] == false)
+        .unwrap();
+
+    let response = app.delete_session(other["id"].as_str().unwrap()).await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let response = app.post_verify_token(&serde_json::json!({ "token": bearer_token })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // The session making the request is untouched
+    assert_eq!(list_sessions(&app).await.len(), 1);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn jwt_should_be_rejected_once_its_session_is_gone() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+
+    signup_and_login(&app, &random_email, false).await;
+    let bearer_token = login_with_bearer(&app, &random_email).await;
+
+    let response = app.post_verify_token(&serde_json::json!({ "token": bearer_token })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body: serde_json::Value = response.json().await.unwrap();
+    let session_id = SessionId::parse(body["sid"].as_str().unwrap().to_owned()).unwrap();
+    app.session_store.remove_session(&session_id).await.unwrap();
+
+    let response = app.post_verify_token(&serde_json::json!({ "token": bearer_token })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn revoking_session_should_end_server_side_session() {
+    let mut app = session_app(SessionConfig::default().policy).await;
+    let random_email = get_random_email();
+
+    signup_and_login(&app, &random_email, false).await;
+    let bearer_token = login_with_bearer(&app, &random_email).await;
+
+    let response = app.get_sessions_with_bearer(&bearer_token).await;
+    let body: serde_json::Value = response.json().await.unwrap();
+    let current = body["sessions"].as_array().unwrap()
+        .iter()
+        .find(|session| session["current"] == true)
+        .unwrap()
+        .clone();
+
+    // The listing never shows the session ID itself
+    assert_ne!(current["id"], bearer_token.as_str());
+
+    let response = app.delete_session(current["id"].as_str().unwrap()).await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let response = app.get_sessions_with_bearer(&bearer_token).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_return_404_revoking_unknown_session() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+
+    signup_and_login(&app, &random_email, false).await;
+
+    let response = app.delete_session(&SessionId::default().public_id()).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_return_403_revoking_session_without_csrf_token() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+
+    signup_and_login(&app, &random_email, false).await;
+    let id = list_sessions(&app).await[0]["id"].as_str().unwrap().to_owned();
+
+    let response = app.http_client
+        .delete(format!("{}/sessions/{}", &app.address, id))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 403);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
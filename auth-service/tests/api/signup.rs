@@ -1,4 +1,7 @@
 use auth_service::domain::ErrorResponse;
+use auth_service::utils::config::Config;
+use wiremock::{Mock, ResponseTemplate};
+use wiremock::matchers::{method, path};
 
 use crate::helpers::{TestApp, get_random_email};
 
@@ -106,4 +109,38 @@ async fn should_return_422_if_malformed_input() {
     }
 
     app.delete_database(&app.db_name.clone()).await;
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn should_email_owner_instead_of_409_in_enumeration_safe_mode() {
+    let config = Config {
+        enumeration_safe_signup: true,
+        ..Config::default()
+    };
+    let mut app = TestApp::new_with_config(config).await;
+
+    let signup_body = serde_json::json!({
+        "email": get_random_email(),
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    // Only the second signup sends an email, to the existing owner
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let first = app.post_signup(&signup_body).await;
+    let first_status = first.status().as_u16();
+    let first_body = first.text().await.unwrap();
+
+    let second = app.post_signup(&signup_body).await;
+    assert_eq!(second.status().as_u16(), first_status);
+    assert_eq!(second.text().await.unwrap(), first_body);
+    assert_eq!(first_status, 201);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
@@ -1,4 +1,9 @@
-use auth_service::{domain::{Email, LoginAttemptId, TwoFACode}, utils::constants::JWT_COOKIE_NAME};
+use std::time::Duration;
+
+use auth_service::{
+    domain::{Email, LoginAttemptId, RateLimitPolicy, TwoFACode, TwoFACodePurpose},
+    utils::{config::{Config, TwoFAConfig}, constants::JWT_COOKIE_NAME},
+};
 use secrecy::{ExposeSecret, SecretString};
 use wiremock::{Mock, ResponseTemplate, matchers::{method, path}};
 
@@ -41,9 +46,8 @@ async fn should_return_200_if_correct_code() {
     assert_eq!(response.status().as_u16(), 206);
 
     // Verify 2FA
-    let two_fa_code_store = app.two_fa_code_store.read().await;
-    let (login_attempt_id, two_fa_code) = two_fa_code_store.get_code(&email).await.unwrap();
-    drop(two_fa_code_store);
+    let two_fa_code_store = &app.two_fa_code_store;
+    let (login_attempt_id, two_fa_code) = two_fa_code_store.get_code(&email, TwoFACodePurpose::Login).await.unwrap();
 
     let verify_body = serde_json::json!({
             "email": random_email.expose_secret(),
@@ -150,9 +154,8 @@ async fn should_return_401_if_incorrect_credentials() {
     assert_eq!(response.status().as_u16(), 206);
 
     // Verify 2FA
-    let two_fa_code_store = app.two_fa_code_store.read().await;
-    let (login_attempt_id, two_fa_code) = two_fa_code_store.get_code(&email).await.unwrap();
-    drop(two_fa_code_store);
+    let two_fa_code_store = &app.two_fa_code_store;
+    let (login_attempt_id, two_fa_code) = two_fa_code_store.get_code(&email, TwoFACodePurpose::Login).await.unwrap();
 
     let verify_body = serde_json::json!({
             "email": random_email.expose_secret(),
@@ -212,9 +215,8 @@ async fn should_return_401_if_old_code() {
     assert_eq!(response.status().as_u16(), 206);
 
     // Store Login Attempt ID and 2FA Code
-    let two_fa_code_store = app.two_fa_code_store.read().await;
-    let (login_attempt_id_one, two_fa_code_one) = two_fa_code_store.get_code(&email).await.unwrap();
-    drop(two_fa_code_store);
+    let two_fa_code_store = &app.two_fa_code_store;
+    let (login_attempt_id_one, two_fa_code_one) = two_fa_code_store.get_code(&email, TwoFACodePurpose::Login).await.unwrap();
 
     // Second Login
     let login_body = serde_json::json!({
@@ -276,9 +278,8 @@ async fn should_return_401_if_same_code_twice() {
     assert_eq!(response.status().as_u16(), 206);
 
     // Verify 2FA
-    let two_fa_code_store = app.two_fa_code_store.read().await;
-    let (login_attempt_id, two_fa_code) = two_fa_code_store.get_code(&email).await.unwrap();
-    drop(two_fa_code_store);
+    let two_fa_code_store = &app.two_fa_code_store;
+    let (login_attempt_id, two_fa_code) = two_fa_code_store.get_code(&email, TwoFACodePurpose::Login).await.unwrap();
 
     let verify_body = serde_json::json!({
             "email": random_email.expose_secret(),
@@ -335,4 +336,87 @@ async fn should_return_422_if_malformed_input() {
     }
 
     app.delete_database(&app.db_name.clone()).await;
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn should_return_token_in_body_if_requested() {
+    let mut app = TestApp::new().await;
+
+    let random_email = SecretString::new(get_random_email().into_boxed_str());
+    let email = Email::parse(random_email.clone()).unwrap();
+
+    let signup_body = serde_json::json!({
+        "email": random_email.expose_secret(),
+        "password": "password123",
+        "requires2FA": true
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = serde_json::json!({
+        "email": random_email.expose_secret(),
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let two_fa_code_store = &app.two_fa_code_store;
+    let (login_attempt_id, two_fa_code) = two_fa_code_store.get_code(&email, TwoFACodePurpose::Login).await.unwrap();
+
+    let verify_body = serde_json::json!({
+        "email": random_email.expose_secret(),
+        "loginAttemptId": login_attempt_id.as_ref(),
+        "2FACode": two_fa_code.as_ref(),
+        "returnToken": true,
+    });
+
+    let response = app.post_verify_2fa(&verify_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(!get_all_cookies(&response).contains_key(JWT_COOKIE_NAME));
+
+    let body = response.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(body["tokenType"], "Bearer");
+
+    let verify_token_body = serde_json::json!({
+        "token": body["token"],
+    });
+
+    let response = app.post_verify_token(&verify_token_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_return_429_if_too_many_guesses_for_email() {
+    let config = Config {
+        two_fa: TwoFAConfig {
+            verify_limit: RateLimitPolicy::new(2, Duration::from_secs(60)),
+            ..TwoFAConfig::default()
+        },
+        ..Config::default()
+    };
+    let mut app = TestApp::new_with_config(config).await;
+
+    let verify_body = serde_json::json!({
+        "email": get_random_email(),
+        "loginAttemptId": LoginAttemptId::default().as_ref(),
+        "2FACode": TwoFACode::default().as_ref(),
+    });
+
+    for _ in 0..2 {
+        assert_eq!(app.post_verify_2fa(&verify_body).await.status().as_u16(), 401);
+    }
+    assert_eq!(app.post_verify_2fa(&verify_body).await.status().as_u16(), 429);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
@@ -0,0 +1,161 @@
+use auth_service::domain::ErrorResponse;
+use auth_service::utils::config::{Config, PostmarkWebhookConfig};
+use auth_service::utils::constants::WEBHOOK_SECRET_HEADER;
+use secrecy::SecretString;
+use wiremock::{Mock, ResponseTemplate};
+use wiremock::matchers::{method, path};
+
+use crate::helpers::{TestApp, get_random_email};
+
+const WEBHOOK_SECRET: &str = "webhook-secret";
+
+fn webhook_config() -> Config {
+    Config {
+        postmark_webhook: PostmarkWebhookConfig {
+            basic_auth: None,
+            secret: Some(SecretString::new(WEBHOOK_SECRET.to_owned().into_boxed_str())),
+        },
+        ..Config::default()
+    }
+}
+
+async fn signup_with_2fa(app: &TestApp, email: &str) {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": true
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+}
+
+#[tokio::test]
+async fn should_block_2fa_login_after_hard_bounce() {
+    let mut app = TestApp::new_with_config(webhook_config()).await;
+
+    let random_email = get_random_email();
+    signup_with_2fa(&app, &random_email).await;
+
+    let webhook_body = serde_json::json!({
+        "RecordType": "Bounce",
+        "Type": "HardBounce",
+        "Email": random_email,
+        "Inactive": true
+    });
+
+    let response = app.post_postmark_webhook(&webhook_body, &[(WEBHOOK_SECRET_HEADER, WEBHOOK_SECRET)]).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // No 2FA email is sent to a bouncing address
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 422);
+    assert_eq!(
+        response.json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialized response body to ErrorResponse")
+            .error,
+        "Emails to this address are bouncing, contact support".to_owned()
+    );
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_ignore_soft_bounces() {
+    let mut app = TestApp::new_with_config(webhook_config()).await;
+
+    let random_email = get_random_email();
+    signup_with_2fa(&app, &random_email).await;
+
+    let webhook_body = serde_json::json!({
+        "RecordType": "Bounce",
+        "Type": "SoftBounce",
+        "Email": random_email,
+        "Inactive": false
+    });
+
+    let response = app.post_postmark_webhook(&webhook_body, &[(WEBHOOK_SECRET_HEADER, WEBHOOK_SECRET)]).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_return_200_for_unknown_recipient() {
+    let mut app = TestApp::new_with_config(webhook_config()).await;
+
+    let webhook_body = serde_json::json!({
+        "RecordType": "SpamComplaint",
+        "Email": get_random_email(),
+    });
+
+    let response = app.post_postmark_webhook(&webhook_body, &[(WEBHOOK_SECRET_HEADER, WEBHOOK_SECRET)]).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_secret_is_incorrect() {
+    let mut app = TestApp::new_with_config(webhook_config()).await;
+
+    let webhook_body = serde_json::json!({
+        "RecordType": "SpamComplaint",
+        "Email": get_random_email(),
+    });
+
+    let test_cases = [
+        vec![],
+        vec![(WEBHOOK_SECRET_HEADER, "wrong-secret")],
+    ];
+
+    for headers in test_cases {
+        let response = app.post_postmark_webhook(&webhook_body, &headers).await;
+        assert_eq!(response.status().as_u16(), 401, "Failed for headers: {:?}", headers);
+    }
+
+    app.delete_database(&app.db_name.clone()).await;
+}
+
+#[tokio::test]
+async fn should_return_404_if_webhook_is_not_configured() {
+    let mut app = TestApp::new().await;
+
+    let webhook_body = serde_json::json!({
+        "RecordType": "SpamComplaint",
+        "Email": get_random_email(),
+    });
+
+    let response = app.post_postmark_webhook(&webhook_body, &[(WEBHOOK_SECRET_HEADER, WEBHOOK_SECRET)]).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    app.delete_database(&app.db_name.clone()).await;
+}
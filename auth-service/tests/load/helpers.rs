@@ -0,0 +1,143 @@
+use auth_service::{
+    Application,
+    app_state::AppState,
+    domain::{
+        Email, EmailChangeRevert, HashedPassword, User,
+        data_stores::{UserStore, UserStoreError},
+    },
+    services::{
+        data_stores::{
+            hashmap_two_fa_code_store::HashmapTwoFACodeStore,
+            hashmap_account_lockout_store::HashmapAccountLockoutStore,
+            hashmap_rate_limit_store::HashmapRateLimitStore,
+            hashmap_session_store::HashmapSessionStore,
+            hashmap_user_store::HashmapUserStore,
+            hashset_banned_token_store::HashsetBannedTokenStore,
+        },
+        geoip::GeoIp,
+        mock_email_client::MockEmailClient,
+    },
+    utils::{config::{Config, IpRateLimitConfig}, constants::test},
+};
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHasher, SaltString},
+    Algorithm, Argon2, Params, Version,
+};
+use secrecy::SecretString;
+
+use std::{sync::Arc, time::Duration};
+
+pub const PASSWORD: &str = "password123";
+
+// Stands in for the round trip to Postgres, which is what requests used to queue behind
+pub const STORE_LATENCY: Duration = Duration::from_millis(50);
+
+#[derive(Clone)]
+pub struct LoadTestApp {
+    pub address: String,
+    pub http_client: reqwest::Client,
+}
+
+impl LoadTestApp {
+    // Everything runs in memory, so no database or Redis is needed
+    pub async fn new(users: &[Email]) -> Self {
+        let user_store = SlowUserStore::default();
+        let password = cheap_password_hash();
+        for email in users {
+            user_store.add_user(User::new(email.clone(), password.clone(), false)).await.unwrap();
+        }
+
+        let config = Config {
+            ip_rate_limit: IpRateLimitConfig { enabled: false, ..IpRateLimitConfig::default() },
+            ..Config::default()
+        };
+
+        let app_state = AppState::new(
+            Arc::new(user_store),
+            Arc::new(HashsetBannedTokenStore::default()),
+            Arc::new(HashmapTwoFACodeStore::default()),
+            Arc::new(MockEmailClient::default()),
+            Arc::new(HashmapRateLimitStore::default()),
+            Arc::new(HashmapAccountLockoutStore::default()),
+            Arc::new(HashmapSessionStore::default()),
+            GeoIp::default(),
+            config,
+        );
+
+        let app = Application::build(app_state, test::APP_ADDRESS)
+            .await
+            .expect("Failed to build app");
+
+        let address = format!("http://{}", app.address.clone());
+
+        #[allow(clippy::let_underscore_future)]
+        let _ = tokio::spawn(app.run());
+
+        Self {
+            address,
+            http_client: reqwest::Client::new(),
+        }
+    }
+
+    pub async fn post_login(&self, email: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/login", &self.address))
+            .json(&serde_json::json!({
+                "email": email,
+                "password": PASSWORD,
+            }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+}
+
+pub fn secret(value: &str) -> SecretString {
+    SecretString::new(value.to_owned().into_boxed_str())
+}
+
+// Real hashes cost a lot of CPU, which would hide how much time is spent waiting on locks
+fn cheap_password_hash() -> HashedPassword {
+    let salt = SaltString::generate(&mut OsRng);
+    let hash = Argon2::new(Algorithm::Argon2id, Version::V0x13, Params::new(8, 1, 1, None).unwrap())
+        .hash_password(PASSWORD.as_bytes(), &salt)
+        .unwrap()
+        .to_string();
+
+    HashedPassword::parse_password_hash(secret(&hash)).unwrap()
+}
+
+// In-memory user store that waits before every call, like a networked database would
+#[derive(Default)]
+pub struct SlowUserStore {
+    inner: HashmapUserStore,
+}
+
+#[async_trait::async_trait]
+impl UserStore for SlowUserStore {
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError> {
+        self.inner.add_user(user).await
+    }
+
+    async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
+        tokio::time::sleep(STORE_LATENCY).await;
+        self.inner.get_user(email).await
+    }
+
+    async fn validate_user(&self, email: &Email, raw_password: &str) -> Result<(), UserStoreError> {
+        tokio::time::sleep(STORE_LATENCY).await;
+        self.inner.validate_user(email, raw_password).await
+    }
+
+    async fn confirm_email_change(&self, change_id: &str, current_email: &Email, new_email: Email) -> Result<(), UserStoreError> {
+        self.inner.confirm_email_change(change_id, current_email, new_email).await
+    }
+
+    async fn revert_email_change(&self, change_id: &str) -> Result<EmailChangeRevert, UserStoreError> {
+        self.inner.revert_email_change(change_id).await
+    }
+
+    async fn mark_email_undeliverable(&self, email: &Email) -> Result<(), UserStoreError> {
+        self.inner.mark_email_undeliverable(email).await
+    }
+}
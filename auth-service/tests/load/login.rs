@@ -0,0 +1,71 @@
+use crate::helpers::{LoadTestApp, STORE_LATENCY, secret};
+use auth_service::domain::Email;
+
+use std::time::{Duration, Instant};
+use tokio::task::JoinSet;
+
+const LOGINS: usize = 20;
+
+fn emails() -> Vec<Email> {
+    (0..LOGINS)
+        .map(|i| Email::parse(secret(&format!("load{i}@example.com"))).unwrap())
+        .collect()
+}
+
+async fn sequential_logins(app: &LoadTestApp, emails: &[Email]) -> Duration {
+    let start = Instant::now();
+
+    for email in emails {
+        let response = app.post_login(email.as_ref()).await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
+    start.elapsed()
+}
+
+async fn concurrent_logins(app: &LoadTestApp, emails: &[Email]) -> Duration {
+    let start = Instant::now();
+
+    let mut requests = JoinSet::new();
+    for email in emails {
+        let app = app.clone();
+        let email = email.as_ref().to_owned();
+        requests.spawn(async move { app.post_login(&email).await.status().as_u16() });
+    }
+
+    while let Some(status) = requests.join_next().await {
+        assert_eq!(status.unwrap(), 200);
+    }
+
+    start.elapsed()
+}
+
+fn report(label: &str, elapsed: Duration) {
+    println!(
+        "{label:>10}: {LOGINS} logins in {:>7.1?} ({:.1} logins/s)",
+        elapsed,
+        LOGINS as f64 / elapsed.as_secs_f64()
+    );
+}
+
+// Slow store calls used to hold a lock every other request waited on, so concurrent
+// logins ran one after another. Without it they overlap and finish much sooner.
+// Run with `cargo test --release --test load -- --ignored --nocapture`
+#[tokio::test(flavor = "multi_thread")]
+#[ignore]
+async fn concurrent_logins_do_not_queue_behind_each_other() {
+    let emails = emails();
+    let app = LoadTestApp::new(&emails).await;
+
+    let sequential = sequential_logins(&app, &emails).await;
+    let concurrent = concurrent_logins(&app, &emails).await;
+
+    println!("store latency: {STORE_LATENCY:?} per call");
+    report("sequential", sequential);
+    report("concurrent", concurrent);
+
+    assert!(
+        concurrent * 2 < sequential,
+        "concurrent logins took {concurrent:?}, sequential ones {sequential:?}"
+    );
+}
@@ -0,0 +1,285 @@
+use std::io::Write;
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use auth_service::domain::Email;
+use auth_service::utils::config::{SmtpConfig, SmtpTls};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use secrecy::SecretString;
+use tempfile::NamedTempFile;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::rustls::{self, pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer}};
+
+pub const HOST: &str = "127.0.0.1";
+pub const SENDER: &str = "sender@example.com";
+
+// A minimal SMTP server that accepts everything and keeps the received emails in memory
+pub struct FakeSmtpServer {
+    pub port: u16,
+    state: Arc<Mutex<ServerState>>,
+    // Kept alive so the CA file exists for the whole test
+    ca_cert: Option<NamedTempFile>,
+}
+
+#[derive(Default, Clone)]
+pub struct FakeSmtpOptions {
+    pub tls: Option<FakeTls>,
+    pub credentials: Option<(String, String)>,
+    pub greeting_delay: Option<Duration>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum FakeTls {
+    StartTls,
+    Implicit,
+}
+
+#[derive(Default)]
+struct ServerState {
+    emails: Vec<ReceivedEmail>,
+    connections: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReceivedEmail {
+    pub from: String,
+    pub to: Vec<String>,
+    pub data: String,
+    pub username: Option<String>,
+    pub secure: bool,
+}
+
+enum SessionEnd {
+    Quit,
+    StartTls,
+}
+
+struct Session {
+    options: FakeSmtpOptions,
+    state: Arc<Mutex<ServerState>>,
+    secure: bool,
+    username: Option<String>,
+    from: String,
+    to: Vec<String>,
+}
+
+impl FakeSmtpServer {
+    pub async fn start(options: FakeSmtpOptions) -> Self {
+        let listener = TcpListener::bind((IpAddr::V4(Ipv4Addr::LOCALHOST), 0)).await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let state = Arc::new(Mutex::new(ServerState::default()));
+
+        let (acceptor, ca_cert) = match options.tls {
+            Some(_) => {
+                let (acceptor, ca_cert) = tls_acceptor();
+                (Some(acceptor), Some(ca_cert))
+            }
+            None => (None, None),
+        };
+
+        let server_state = state.clone();
+        tokio::spawn(async move {
+            loop {
+                let Ok((stream, _)) = listener.accept().await else { break };
+                server_state.lock().unwrap().connections += 1;
+
+                let session = Session::new(options.clone(), server_state.clone());
+                tokio::spawn(session.run(stream, acceptor.clone()));
+            }
+        });
+
+        Self { port, state, ca_cert }
+    }
+
+    pub fn emails(&self) -> Vec<ReceivedEmail> {
+        self.state.lock().unwrap().emails.clone()
+    }
+
+    pub fn connections(&self) -> usize {
+        self.state.lock().unwrap().connections
+    }
+
+    // Client settings that trust this server's certificate
+    pub fn smtp_config(&self, tls: SmtpTls) -> SmtpConfig {
+        let sender = Email::parse(SecretString::new(SENDER.to_owned().into_boxed_str())).unwrap();
+
+        let mut config = SmtpConfig::new(HOST.to_owned(), tls, sender);
+        config.port = self.port;
+        config.timeout = Duration::from_secs(2);
+        config.ca_cert_path = self.ca_cert.as_ref().map(|file| file.path().to_owned());
+        config
+    }
+}
+
+impl Session {
+    fn new(options: FakeSmtpOptions, state: Arc<Mutex<ServerState>>) -> Self {
+        Self {
+            options,
+            state,
+            secure: false,
+            username: None,
+            from: String::new(),
+            to: Vec::new(),
+        }
+    }
+
+    async fn run(mut self, stream: TcpStream, acceptor: Option<TlsAcceptor>) {
+        if let Some(delay) = self.options.greeting_delay {
+            tokio::time::sleep(delay).await;
+        }
+
+        match (self.options.tls, acceptor) {
+            (Some(FakeTls::Implicit), Some(acceptor)) => {
+                let Ok(stream) = acceptor.accept(stream).await else { return };
+                self.secure = true;
+                self.serve(BufReader::new(stream), true).await;
+            }
+            (_, acceptor) => {
+                let mut reader = BufReader::new(stream);
+                if let Some(SessionEnd::StartTls) = self.serve(&mut reader, true).await {
+                    let Some(acceptor) = acceptor else { return };
+                    let Ok(stream) = acceptor.accept(reader.into_inner()).await else { return };
+                    self.secure = true;
+                    self.serve(BufReader::new(stream), false).await;
+                }
+            }
+        }
+    }
+
+    async fn serve<S>(&mut self, mut stream: S, greet: bool) -> Option<SessionEnd>
+    where
+        S: AsyncBufReadExt + AsyncWrite + AsyncRead + Unpin,
+    {
+        if greet {
+            reply(&mut stream, "220 localhost ESMTP fake").await?;
+        }
+
+        loop {
+            let line = read_line(&mut stream).await?;
+            let command = line.to_ascii_uppercase();
+
+            if command.starts_with("EHLO") || command.starts_with("HELO") {
+                let mut lines = vec!["localhost".to_owned()];
+                if self.options.tls == Some(FakeTls::StartTls) && !self.secure {
+                    lines.push("STARTTLS".to_owned());
+                }
+                if self.options.credentials.is_some() {
+                    lines.push("AUTH PLAIN".to_owned());
+                }
+                let last = lines.len() - 1;
+                let response = lines
+                    .iter()
+                    .enumerate()
+                    .map(|(i, line)| format!("250{}{}", if i == last { " " } else { "-" }, line))
+                    .collect::<Vec<_>>()
+                    .join("\r\n");
+                reply(&mut stream, &response).await?;
+            } else if command.starts_with("STARTTLS") {
+                reply(&mut stream, "220 Ready to start TLS").await?;
+                return Some(SessionEnd::StartTls);
+            } else if command.starts_with("AUTH PLAIN ") {
+                let username = self.check_credentials(&line["AUTH PLAIN ".len()..]);
+                match username {
+                    Some(username) => {
+                        self.username = Some(username);
+                        reply(&mut stream, "235 Authentication successful").await?;
+                    }
+                    None => reply(&mut stream, "535 Authentication failed").await?,
+                }
+            } else if command.starts_with("MAIL FROM:") {
+                if self.options.credentials.is_some() && self.username.is_none() {
+                    reply(&mut stream, "530 Authentication required").await?;
+                    continue;
+                }
+                self.from = address(&line);
+                self.to.clear();
+                reply(&mut stream, "250 OK").await?;
+            } else if command.starts_with("RCPT TO:") {
+                self.to.push(address(&line));
+                reply(&mut stream, "250 OK").await?;
+            } else if command.starts_with("DATA") {
+                reply(&mut stream, "354 End data with <CR><LF>.<CR><LF>").await?;
+                let mut data = String::new();
+                loop {
+                    let line = read_line(&mut stream).await?;
+                    if line == "." {
+                        break;
+                    }
+                    data.push_str(&line);
+                    data.push('\n');
+                }
+                self.state.lock().unwrap().emails.push(ReceivedEmail {
+                    from: self.from.clone(),
+                    to: self.to.clone(),
+                    data,
+                    username: self.username.clone(),
+                    secure: self.secure,
+                });
+                reply(&mut stream, "250 OK queued").await?;
+            } else if command.starts_with("RSET") || command.starts_with("NOOP") {
+                reply(&mut stream, "250 OK").await?;
+            } else if command.starts_with("QUIT") {
+                reply(&mut stream, "221 Bye").await?;
+                return Some(SessionEnd::Quit);
+            } else {
+                reply(&mut stream, "502 Command not implemented").await?;
+            }
+        }
+    }
+
+    // AUTH PLAIN sends base64("\0username\0password")
+    fn check_credentials(&self, encoded: &str) -> Option<String> {
+        let decoded = STANDARD.decode(encoded.trim()).ok()?;
+        let decoded = String::from_utf8(decoded).ok()?;
+        let mut parts = decoded.split('\0').skip(1);
+        let (username, password) = (parts.next()?, parts.next()?);
+
+        let (expected_username, expected_password) = self.options.credentials.as_ref()?;
+        (username == expected_username && password == expected_password).then(|| username.to_owned())
+    }
+}
+
+async fn read_line<S: AsyncBufReadExt + Unpin>(stream: &mut S) -> Option<String> {
+    let mut line = String::new();
+    match stream.read_line(&mut line).await {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(line.trim_end_matches(['\r', '\n']).to_owned()),
+    }
+}
+
+async fn reply<S: AsyncWrite + Unpin>(stream: &mut S, response: &str) -> Option<()> {
+    stream.write_all(format!("{}\r\n", response).as_bytes()).await.ok()?;
+    stream.flush().await.ok()
+}
+
+// "MAIL FROM:<a@b.com> SIZE=10" -> "a@b.com"
+fn address(line: &str) -> String {
+    let start = line.find('<').map(|i| i + 1).unwrap_or(0);
+    let end = line.find('>').unwrap_or(line.len());
+    line[start..end].to_owned()
+}
+
+// Self-signed certificate for 127.0.0.1, also written to a file for the client to trust
+fn tls_acceptor() -> (TlsAcceptor, NamedTempFile) {
+    let certified = rcgen::generate_simple_self_signed(vec![HOST.to_owned()]).unwrap();
+
+    let mut ca_cert = NamedTempFile::new().unwrap();
+    ca_cert.write_all(certified.cert.pem().as_bytes()).unwrap();
+
+    let cert_chain = vec![CertificateDer::from(certified.cert.der().to_vec())];
+    let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(certified.signing_key.serialize_der()));
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let server_config = rustls::ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_no_client_auth()
+        .with_single_cert(cert_chain, key)
+        .unwrap();
+
+    (TlsAcceptor::from(Arc::new(server_config)), ca_cert)
+}
@@ -0,0 +1,2 @@
+mod helpers;
+mod smtp_email_client;
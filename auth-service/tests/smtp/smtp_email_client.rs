@@ -0,0 +1,150 @@
+use std::time::{Duration, Instant};
+
+use auth_service::domain::{Email, EmailClient};
+use auth_service::services::smtp_email_client::SmtpEmailClient;
+use auth_service::utils::config::SmtpTls;
+use secrecy::SecretString;
+
+use crate::helpers::{FakeSmtpOptions, FakeSmtpServer, FakeTls, SENDER};
+
+fn recipient() -> Email {
+    Email::parse(SecretString::new("recipient@example.com".to_owned().into_boxed_str())).unwrap()
+}
+
+async fn send(client: &SmtpEmailClient) -> color_eyre::Result<()> {
+    client
+        .send_email(recipient(), "Test subject", "<p>HTML body</p>", "Text body")
+        .await
+}
+
+fn credentials() -> Option<(String, String)> {
+    Some(("user".to_owned(), "password".to_owned()))
+}
+
+#[tokio::test]
+async fn should_send_email_with_html_and_text_parts() {
+    let server = FakeSmtpServer::start(FakeSmtpOptions::default()).await;
+    let client = SmtpEmailClient::new(&server.smtp_config(SmtpTls::None)).unwrap();
+
+    send(&client).await.unwrap();
+
+    let emails = server.emails();
+    assert_eq!(emails.len(), 1);
+    assert_eq!(emails[0].from, SENDER);
+    assert_eq!(emails[0].to, vec!["recipient@example.com".to_owned()]);
+    assert!(emails[0].data.contains("Subject: Test subject"));
+    assert!(emails[0].data.contains("multipart/alternative"));
+    assert!(emails[0].data.contains("Text body"));
+    assert!(emails[0].data.contains("<p>HTML body</p>"));
+    assert!(!emails[0].secure);
+}
+
+#[tokio::test]
+async fn should_authenticate_with_credentials() {
+    let options = FakeSmtpOptions { credentials: credentials(), ..Default::default() };
+    let server = FakeSmtpServer::start(options).await;
+
+    let mut config = server.smtp_config(SmtpTls::None);
+    config.username = Some("user".to_owned());
+    config.password = Some(SecretString::new("password".to_owned().into_boxed_str()));
+    let client = SmtpEmailClient::new(&config).unwrap();
+
+    send(&client).await.unwrap();
+
+    assert_eq!(server.emails()[0].username.as_deref(), Some("user"));
+}
+
+#[tokio::test]
+async fn should_fail_with_incorrect_credentials() {
+    let options = FakeSmtpOptions { credentials: credentials(), ..Default::default() };
+    let server = FakeSmtpServer::start(options).await;
+
+    let mut config = server.smtp_config(SmtpTls::None);
+    config.username = Some("user".to_owned());
+    config.password = Some(SecretString::new("wrong".to_owned().into_boxed_str()));
+    let client = SmtpEmailClient::new(&config).unwrap();
+
+    assert!(send(&client).await.is_err());
+    assert!(server.emails().is_empty());
+}
+
+#[tokio::test]
+async fn should_send_over_starttls() {
+    let options = FakeSmtpOptions { tls: Some(FakeTls::StartTls), credentials: credentials(), ..Default::default() };
+    let server = FakeSmtpServer::start(options).await;
+
+    let mut config = server.smtp_config(SmtpTls::StartTls);
+    config.username = Some("user".to_owned());
+    config.password = Some(SecretString::new("password".to_owned().into_boxed_str()));
+    let client = SmtpEmailClient::new(&config).unwrap();
+
+    send(&client).await.unwrap();
+
+    let emails = server.emails();
+    assert_eq!(emails.len(), 1);
+    assert!(emails[0].secure);
+}
+
+#[tokio::test]
+async fn should_fail_if_server_does_not_offer_starttls() {
+    let server = FakeSmtpServer::start(FakeSmtpOptions::default()).await;
+    let client = SmtpEmailClient::new(&server.smtp_config(SmtpTls::StartTls)).unwrap();
+
+    assert!(send(&client).await.is_err());
+    assert!(server.emails().is_empty());
+}
+
+#[tokio::test]
+async fn should_send_over_implicit_tls() {
+    let options = FakeSmtpOptions { tls: Some(FakeTls::Implicit), ..Default::default() };
+    let server = FakeSmtpServer::start(options).await;
+    let client = SmtpEmailClient::new(&server.smtp_config(SmtpTls::Implicit)).unwrap();
+
+    send(&client).await.unwrap();
+
+    let emails = server.emails();
+    assert_eq!(emails.len(), 1);
+    assert!(emails[0].secure);
+}
+
+#[tokio::test]
+async fn should_fail_if_certificate_is_not_trusted() {
+    let options = FakeSmtpOptions { tls: Some(FakeTls::Implicit), ..Default::default() };
+    let server = FakeSmtpServer::start(options).await;
+
+    let mut config = server.smtp_config(SmtpTls::Implicit);
+    config.ca_cert_path = None;
+    let client = SmtpEmailClient::new(&config).unwrap();
+
+    assert!(send(&client).await.is_err());
+    assert!(server.emails().is_empty());
+}
+
+#[tokio::test]
+async fn should_reuse_pooled_connection() {
+    let server = FakeSmtpServer::start(FakeSmtpOptions::default()).await;
+    let client = SmtpEmailClient::new(&server.smtp_config(SmtpTls::None)).unwrap();
+
+    for _ in 0..3 {
+        send(&client).await.unwrap();
+        // Connections go back to the pool in a background task
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    assert_eq!(server.emails().len(), 3);
+    assert_eq!(server.connections(), 1);
+}
+
+#[tokio::test]
+async fn should_time_out_if_server_takes_too_long() {
+    let options = FakeSmtpOptions { greeting_delay: Some(Duration::from_secs(10)), ..Default::default() };
+    let server = FakeSmtpServer::start(options).await;
+
+    let mut config = server.smtp_config(SmtpTls::None);
+    config.timeout = Duration::from_millis(200);
+    let client = SmtpEmailClient::new(&config).unwrap();
+
+    let start = Instant::now();
+    assert!(send(&client).await.is_err());
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
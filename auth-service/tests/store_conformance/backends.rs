@@ -0,0 +1,102 @@
+use std::time::Duration;
+
+use auth_service::{
+    domain::Email,
+    get_postgres_pool,
+    get_redis_connection,
+    services::redis_connection::RedisConnection,
+    utils::{
+        config::{redis_url, RedisConfig},
+        constants::{DATABASE_URL, REDIS_HOST_NAME},
+    },
+};
+use secrecy::{ExposeSecret, SecretString};
+use sqlx::{Connection, Executor, PgConnection, PgPool, SqlitePool, sqlite::SqlitePoolOptions};
+use tokio::sync::OnceCell;
+use uuid::Uuid;
+
+// How long entries live in stores built for these tests. SQLite and Redis
+// count whole seconds, so entries are alive for at least a second.
+pub const TTL: Duration = Duration::from_secs(2);
+
+// Waits until entries added before have expired in every store
+pub async fn wait_for_expiry() {
+    tokio::time::sleep(TTL + Duration::from_secs(1)).await;
+}
+
+pub fn random_email() -> Email {
+    let email = format!("{}@example.com", Uuid::new_v4());
+    Email::parse(SecretString::new(email.into_boxed_str())).unwrap()
+}
+
+// Cases only use fresh emails, tokens and IDs, so they share one database
+const POSTGRES_DATABASE: &str = "auth_store_conformance";
+
+static POSTGRES_URL: OnceCell<SecretString> = OnceCell::const_new();
+
+pub async fn postgres_pool() -> PgPool {
+    let url = POSTGRES_URL.get_or_init(configure_postgresql).await;
+
+    get_postgres_pool(url)
+        .await
+        .expect("Failed to create Postgres connection pool")
+}
+
+async fn configure_postgresql() -> SecretString {
+    let server_url = DATABASE_URL.expose_secret();
+
+    let mut connection = PgConnection::connect(server_url)
+        .await
+        .expect("Failed to connect to Postgres");
+
+    let exists: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM pg_database WHERE datname = $1)")
+        .bind(POSTGRES_DATABASE)
+        .fetch_one(&mut connection)
+        .await
+        .expect("Failed to look up the database");
+
+    if !exists {
+        connection
+            .execute(format!(r#"CREATE DATABASE "{}";"#, POSTGRES_DATABASE).as_str())
+            .await
+            .expect("Failed to create database");
+    }
+
+    let url = format!("{}/{}", server_url, POSTGRES_DATABASE);
+
+    let mut connection = PgConnection::connect(&url)
+        .await
+        .expect("Failed to connect to Postgres");
+
+    sqlx::migrate!()
+        .run(&mut connection)
+        .await
+        .expect("Failed to migrate the database");
+
+    SecretString::new(url.into_boxed_str())
+}
+
+// A migrated in-memory database, private to one test
+pub async fn sqlite_pool() -> SqlitePool {
+    // Every connection to `:memory:` opens its own database
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .expect("Failed to open SQLite database");
+
+    sqlx::migrate!("./migrations_sqlite")
+        .run(&pool)
+        .await
+        .expect("Failed to run SQLite migrations");
+
+    pool
+}
+
+pub async fn redis_connection() -> RedisConnection {
+    let config = RedisConfig::Standalone { url: redis_url(&REDIS_HOST_NAME) };
+
+    get_redis_connection(&config)
+        .await
+        .expect("Failed to get Redis connection")
+}
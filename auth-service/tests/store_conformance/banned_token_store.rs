@@ -0,0 +1,88 @@
+use std::{sync::Arc, time::Duration};
+
+use auth_service::{
+    app_state::BannedTokenStoreType,
+    services::data_stores::{
+        hashset_banned_token_store::HashsetBannedTokenStore,
+        postgres_banned_token_store::PostgresBannedTokenStore,
+        redis_banned_token_store::RedisBannedTokenStore,
+        sqlite_banned_token_store::SqliteBannedTokenStore,
+    },
+};
+use secrecy::SecretString;
+use uuid::Uuid;
+
+use crate::backends::{postgres_pool, redis_connection, sqlite_pool, wait_for_expiry, TTL};
+
+conformance_tests!(conformance: [hashset, postgres, sqlite, redis], [
+    banned_token_is_reported,
+    other_tokens_are_not_banned,
+    banning_twice_is_fine,
+    token_is_forgotten_after_ttl,
+    each_token_is_kept_for_its_own_ttl,
+]);
+
+async fn hashset() -> BannedTokenStoreType {
+    Arc::new(HashsetBannedTokenStore::default())
+}
+
+async fn postgres() -> BannedTokenStoreType {
+    Arc::new(PostgresBannedTokenStore::new(postgres_pool().await))
+}
+
+async fn sqlite() -> BannedTokenStoreType {
+    Arc::new(SqliteBannedTokenStore::new(sqlite_pool().await))
+}
+
+async fn redis() -> BannedTokenStoreType {
+    Arc::new(RedisBannedTokenStore::new(redis_connection().await))
+}
+
+fn random_token() -> SecretString {
+    SecretString::new(Uuid::new_v4().to_string().into_boxed_str())
+}
+
+async fn banned_token_is_reported(store: BannedTokenStoreType) {
+    let token = random_token();
+
+    store.add_token(&token, TTL).await.unwrap();
+
+    assert_eq!(store.check_token(&token).await, Ok(true));
+}
+
+async fn other_tokens_are_not_banned(store: BannedTokenStoreType) {
+    store.add_token(&random_token(), TTL).await.unwrap();
+
+    assert_eq!(store.check_token(&random_token()).await, Ok(false));
+}
+
+async fn banning_twice_is_fine(store: BannedTokenStoreType) {
+    let token = random_token();
+
+    store.add_token(&token, TTL).await.unwrap();
+    store.add_token(&token, TTL).await.unwrap();
+
+    assert_eq!(store.check_token(&token).await, Ok(true));
+}
+
+async fn token_is_forgotten_after_ttl(store: BannedTokenStoreType) {
+    let token = random_token();
+    store.add_token(&token, TTL).await.unwrap();
+
+    wait_for_expiry().await;
+
+    assert_eq!(store.check_token(&token).await, Ok(false));
+}
+
+// Link tokens live much longer than auth tokens and stay banned as long
+async fn each_token_is_kept_for_its_own_ttl(store: BannedTokenStoreType) {
+    let short_lived = random_token();
+    let long_lived = random_token();
+    store.add_token(&short_lived, TTL).await.unwrap();
+    store.add_token(&long_lived, Duration::from_secs(24 * 60 * 60)).await.unwrap();
+
+    wait_for_expiry().await;
+
+    assert_eq!(store.check_token(&short_lived).await, Ok(false));
+    assert_eq!(store.check_token(&long_lived).await, Ok(true));
+}
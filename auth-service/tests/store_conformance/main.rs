@@ -0,0 +1,30 @@
+// Every store implementation must pass the same cases. Each case is an async fn
+// taking the store, each backend an async fn building one.
+//
+//   conformance_tests!(group: [backend, ...], [case, ...]);
+//
+// generates `<group>::<backend>::<case>` tests. The Postgres and Redis backends
+// need the servers the API tests use.
+macro_rules! conformance_tests {
+    ($group:ident: [$($backend:ident),* $(,)?], $cases:tt) => {
+        mod $group {
+            $( conformance_tests!(@backend $backend, $cases); )*
+        }
+    };
+    (@backend $backend:ident, [$($case:ident),* $(,)?]) => {
+        mod $backend {
+            $(
+                #[tokio::test]
+                async fn $case() {
+                    super::super::$case(super::super::$backend().await).await;
+                }
+            )*
+        }
+    };
+}
+
+mod backends;
+mod banned_token_store;
+mod session_store;
+mod two_fa_code_store;
+mod user_store;
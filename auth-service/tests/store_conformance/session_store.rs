@@ -0,0 +1,131 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use auth_service::{
+    app_state::SessionStoreType,
+    domain::{data_stores::SessionStoreError, Session, SessionId},
+    services::data_stores::{
+        hashmap_session_store::HashmapSessionStore,
+        postgres_session_store::PostgresSessionStore,
+        redis_session_store::RedisSessionStore,
+    },
+};
+use chrono::{SubsecRound, Utc};
+
+use crate::backends::{postgres_pool, random_email, redis_connection, wait_for_expiry, TTL};
+
+// Sessions get their TTL on every call, so expiry is covered for every backend
+conformance_tests!(conformance: [hashmap, postgres, redis], [
+    returns_added_session,
+    unknown_session_is_not_found,
+    touch_updates_last_seen,
+    touching_unknown_session_fails,
+    removed_session_is_gone,
+    removing_unknown_session_is_fine,
+    lists_only_sessions_of_user,
+    session_is_forgotten_after_ttl,
+    touch_extends_ttl,
+]);
+
+async fn hashmap() -> SessionStoreType {
+    Arc::new(HashmapSessionStore::default())
+}
+
+async fn postgres() -> SessionStoreType {
+    Arc::new(PostgresSessionStore::new(postgres_pool().await))
+}
+
+async fn redis() -> SessionStoreType {
+    Arc::new(RedisSessionStore::new(redis_connection().await))
+}
+
+fn session() -> Session {
+    Session::new(random_email(), Some("127.0.0.1".parse().unwrap()), Some("curl/8.0".to_owned()))
+}
+
+async fn returns_added_session(store: SessionStoreType) {
+    let session = session();
+
+    store.add_session(session.clone(), TTL).await.unwrap();
+
+    assert_eq!(store.get_session(&session.id).await, Ok(session));
+}
+
+async fn unknown_session_is_not_found(store: SessionStoreType) {
+    store.add_session(session(), TTL).await.unwrap();
+
+    assert_eq!(store.get_session(&SessionId::default()).await.err(), Some(SessionStoreError::SessionNotFound));
+}
+
+async fn touch_updates_last_seen(store: SessionStoreType) {
+    let session = session();
+    let last_seen = Utc::now().trunc_subsecs(3) + chrono::Duration::seconds(5);
+    store.add_session(session.clone(), TTL).await.unwrap();
+
+    store.touch_session(&session.id, last_seen, TTL).await.unwrap();
+
+    let touched = store.get_session(&session.id).await.unwrap();
+    assert_eq!(touched.last_seen, last_seen);
+    assert_eq!(touched.created_at, session.created_at);
+}
+
+async fn touching_unknown_session_fails(store: SessionStoreType) {
+    assert_eq!(
+        store.touch_session(&SessionId::default(), Utc::now(), TTL).await,
+        Err(SessionStoreError::SessionNotFound)
+    );
+}
+
+async fn removed_session_is_gone(store: SessionStoreType) {
+    let session = session();
+    store.add_session(session.clone(), TTL).await.unwrap();
+
+    store.remove_session(&session.id).await.unwrap();
+
+    assert_eq!(store.get_session(&session.id).await.err(), Some(SessionStoreError::SessionNotFound));
+    assert_eq!(store.list_sessions(&session.email).await, Ok(vec![]));
+}
+
+async fn removing_unknown_session_is_fine(store: SessionStoreType) {
+    assert_eq!(store.remove_session(&SessionId::default()).await, Ok(()));
+}
+
+async fn lists_only_sessions_of_user(store: SessionStoreType) {
+    let first = session();
+    let second = Session::new(first.email.clone(), None, None);
+    store.add_session(first.clone(), TTL).await.unwrap();
+    store.add_session(second.clone(), TTL).await.unwrap();
+    store.add_session(session(), TTL).await.unwrap();
+
+    let mut sessions = store.list_sessions(&first.email).await.unwrap();
+    sessions.sort_by_key(|session| session.id.as_ref().to_owned());
+    let mut expected = vec![first, second];
+    expected.sort_by_key(|session| session.id.as_ref().to_owned());
+
+    assert_eq!(sessions, expected);
+}
+
+async fn session_is_forgotten_after_ttl(store: SessionStoreType) {
+    let session = session();
+    store.add_session(session.clone(), TTL).await.unwrap();
+
+    wait_for_expiry().await;
+
+    assert_eq!(store.get_session(&session.id).await.err(), Some(SessionStoreError::SessionNotFound));
+    assert_eq!(store.list_sessions(&session.email).await, Ok(vec![]));
+    assert_eq!(
+        store.touch_session(&session.id, Utc::now(), TTL).await,
+        Err(SessionStoreError::SessionNotFound)
+    );
+}
+
+async fn touch_extends_ttl(store: SessionStoreType) {
+    let session = session();
+    store.add_session(session.clone(), TTL).await.unwrap();
+
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    store.touch_session(&session.id, Utc::now(), TTL * 2).await.unwrap();
+    wait_for_expiry().await;
+
+    assert!(store.get_session(&session.id).await.is_ok());
+}
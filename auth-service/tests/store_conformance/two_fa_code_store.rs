@@ -0,0 +1,141 @@
+use std::sync::Arc;
+
+use auth_service::{
+    app_state::TwoFACodeStoreType,
+    domain::{data_stores::TwoFACodeStoreError, LoginAttemptId, TwoFACode, TwoFACodePurpose},
+    services::data_stores::{
+        hashmap_two_fa_code_store::HashmapTwoFACodeStore,
+        postgres_two_fa_code_store::PostgresTwoFACodeStore,
+        redis_two_fa_code_store::RedisTwoFACodeStore,
+        sqlite_two_fa_code_store::SqliteTwoFACodeStore,
+    },
+};
+
+use crate::backends::{postgres_pool, random_email, redis_connection, sqlite_pool, wait_for_expiry, TTL};
+
+conformance_tests!(conformance: [hashmap, postgres, sqlite, redis], [
+    returns_added_code,
+    new_code_replaces_previous_one,
+    unknown_email_is_not_found,
+    removed_code_is_not_found,
+    removing_missing_code_is_fine,
+    code_is_forgotten_after_ttl,
+    purposes_keep_separate_codes,
+    counts_failed_attempts,
+    new_code_resets_failed_attempts,
+    failed_attempt_without_code_is_not_found,
+]);
+
+const LOGIN: TwoFACodePurpose = TwoFACodePurpose::Login;
+
+async fn hashmap() -> TwoFACodeStoreType {
+    Arc::new(HashmapTwoFACodeStore::default().with_ttl(TTL))
+}
+
+async fn postgres() -> TwoFACodeStoreType {
+    Arc::new(PostgresTwoFACodeStore::new(postgres_pool().await).with_ttl(TTL))
+}
+
+async fn sqlite() -> TwoFACodeStoreType {
+    Arc::new(SqliteTwoFACodeStore::new(sqlite_pool().await).with_ttl(TTL))
+}
+
+async fn redis() -> TwoFACodeStoreType {
+    Arc::new(RedisTwoFACodeStore::new(redis_connection().await).with_ttl(TTL))
+}
+
+async fn returns_added_code(store: TwoFACodeStoreType) {
+    let email = random_email();
+    let login_attempt_id = LoginAttemptId::default();
+    let code = TwoFACode::default();
+
+    store.add_code(email.clone(), LOGIN, login_attempt_id.clone(), code.clone()).await.unwrap();
+
+    assert_eq!(store.get_code(&email, LOGIN).await.unwrap(), (login_attempt_id, code));
+}
+
+async fn new_code_replaces_previous_one(store: TwoFACodeStoreType) {
+    let email = random_email();
+    let login_attempt_id = LoginAttemptId::default();
+    let code = TwoFACode::default();
+
+    store.add_code(email.clone(), LOGIN, LoginAttemptId::default(), TwoFACode::default()).await.unwrap();
+    store.add_code(email.clone(), LOGIN, login_attempt_id.clone(), code.clone()).await.unwrap();
+
+    assert_eq!(store.get_code(&email, LOGIN).await.unwrap(), (login_attempt_id, code));
+}
+
+async fn unknown_email_is_not_found(store: TwoFACodeStoreType) {
+    store.add_code(random_email(), LOGIN, LoginAttemptId::default(), TwoFACode::default()).await.unwrap();
+
+    assert_eq!(store.get_code(&random_email(), LOGIN).await.err(), Some(TwoFACodeStoreError::LoginAttempIdNotFound));
+}
+
+async fn removed_code_is_not_found(store: TwoFACodeStoreType) {
+    let email = random_email();
+    store.add_code(email.clone(), LOGIN, LoginAttemptId::default(), TwoFACode::default()).await.unwrap();
+
+    store.remove_code(&email, LOGIN).await.unwrap();
+
+    assert_eq!(store.get_code(&email, LOGIN).await.err(), Some(TwoFACodeStoreError::LoginAttempIdNotFound));
+}
+
+async fn removing_missing_code_is_fine(store: TwoFACodeStoreType) {
+    assert_eq!(store.remove_code(&random_email(), LOGIN).await, Ok(()));
+}
+
+async fn code_is_forgotten_after_ttl(store: TwoFACodeStoreType) {
+    let email = random_email();
+    store.add_code(email.clone(), LOGIN, LoginAttemptId::default(), TwoFACode::default()).await.unwrap();
+
+    wait_for_expiry().await;
+
+    assert_eq!(store.get_code(&email, LOGIN).await.err(), Some(TwoFACodeStoreError::LoginAttempIdNotFound));
+}
+
+async fn purposes_keep_separate_codes(store: TwoFACodeStoreType) {
+    let email = random_email();
+    let login_attempt_id = LoginAttemptId::default();
+    let code = TwoFACode::default();
+    store.add_code(email.clone(), LOGIN, login_attempt_id.clone(), code.clone()).await.unwrap();
+
+    assert_eq!(store.get_code(&email, TwoFACodePurpose::EmailOtp).await.err(), Some(TwoFACodeStoreError::LoginAttempIdNotFound));
+
+    store.add_code(email.clone(), TwoFACodePurpose::EmailOtp, LoginAttemptId::default(), TwoFACode::default()).await.unwrap();
+    store.record_failed_attempt(&email, TwoFACodePurpose::EmailOtp).await.unwrap();
+    store.remove_code(&email, TwoFACodePurpose::EmailOtp).await.unwrap();
+
+    assert_eq!(store.get_code(&email, LOGIN).await.unwrap(), (login_attempt_id, code));
+    assert_eq!(store.record_failed_attempt(&email, LOGIN).await, Ok(1));
+}
+
+async fn counts_failed_attempts(store: TwoFACodeStoreType) {
+    let email = random_email();
+    store.add_code(email.clone(), LOGIN, LoginAttemptId::default(), TwoFACode::default()).await.unwrap();
+
+    assert_eq!(store.record_failed_attempt(&email, LOGIN).await, Ok(1));
+    assert_eq!(store.record_failed_attempt(&email, LOGIN).await, Ok(2));
+    assert!(store.get_code(&email, LOGIN).await.is_ok());
+}
+
+async fn new_code_resets_failed_attempts(store: TwoFACodeStoreType) {
+    let email = random_email();
+    store.add_code(email.clone(), LOGIN, LoginAttemptId::default(), TwoFACode::default()).await.unwrap();
+    store.record_failed_attempt(&email, LOGIN).await.unwrap();
+    store.record_failed_attempt(&email, LOGIN).await.unwrap();
+
+    store.add_code(email.clone(), LOGIN, LoginAttemptId::default(), TwoFACode::default()).await.unwrap();
+
+    assert_eq!(store.record_failed_attempt(&email, LOGIN).await, Ok(1));
+}
+
+async fn failed_attempt_without_code_is_not_found(store: TwoFACodeStoreType) {
+    let email = random_email();
+
+    assert_eq!(store.record_failed_attempt(&email, LOGIN).await, Err(TwoFACodeStoreError::LoginAttempIdNotFound));
+
+    store.add_code(email.clone(), LOGIN, LoginAttemptId::default(), TwoFACode::default()).await.unwrap();
+    store.remove_code(&email, LOGIN).await.unwrap();
+
+    assert_eq!(store.record_failed_attempt(&email, LOGIN).await, Err(TwoFACodeStoreError::LoginAttempIdNotFound));
+}
@@ -0,0 +1,193 @@
+use std::sync::Arc;
+
+use auth_service::{
+    app_state::UserStoreType,
+    domain::{data_stores::UserStoreError, Email, EmailChangeRevert, HashedPassword, User},
+    services::data_stores::{
+        hashmap_user_store::HashmapUserStore,
+        postgres_user_store::PostgresUserStore,
+        sqlite_user_store::SqliteUserStore,
+    },
+};
+use secrecy::SecretString;
+
+use crate::backends::{postgres_pool, random_email, sqlite_pool};
+
+const PASSWORD: &str = "password123";
+
+conformance_tests!(conformance: [hashmap, postgres, sqlite], [
+    returns_added_user,
+    adding_existing_user_fails,
+    unknown_user_is_not_found,
+    validates_password,
+    updates_email,
+    updating_to_taken_email_fails,
+    updating_to_same_email_is_fine,
+    reverts_email_change,
+    reverting_unconfirmed_change_changes_nothing,
+    reverts_email_change_once,
+    change_followed_by_another_is_not_reverted,
+    marks_email_undeliverable,
+]);
+
+async fn hashmap() -> UserStoreType {
+    Arc::new(HashmapUserStore::default())
+}
+
+async fn postgres() -> UserStoreType {
+    Arc::new(PostgresUserStore::new(postgres_pool().await))
+}
+
+async fn sqlite() -> UserStoreType {
+    Arc::new(SqliteUserStore::new(sqlite_pool().await))
+}
+
+async fn new_user(email: &Email) -> User {
+    let password = HashedPassword::parse(SecretString::new(PASSWORD.to_owned().into_boxed_str()))
+        .await
+        .unwrap();
+
+    User::new(email.clone(), password, true)
+}
+
+// Changes of every test share the Postgres table
+fn change_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+async fn returns_added_user(store: UserStoreType) {
+    let user = new_user(&random_email()).await;
+
+    store.add_user(user.clone()).await.unwrap();
+
+    assert_eq!(store.get_user(&user.email).await, Ok(user));
+}
+
+async fn adding_existing_user_fails(store: UserStoreType) {
+    let user = new_user(&random_email()).await;
+    store.add_user(user.clone()).await.unwrap();
+
+    assert_eq!(store.add_user(user).await, Err(UserStoreError::UserAlreadyExists));
+}
+
+async fn unknown_user_is_not_found(store: UserStoreType) {
+    let email = random_email();
+
+    assert_eq!(store.get_user(&email).await.err(), Some(UserStoreError::UserNotFound));
+    assert_eq!(store.validate_user(&email, PASSWORD).await, Err(UserStoreError::UserNotFound));
+    assert_eq!(store.confirm_email_change(&change_id(), &email, random_email()).await, Err(UserStoreError::UserNotFound));
+    assert_eq!(store.mark_email_undeliverable(&email).await, Err(UserStoreError::UserNotFound));
+}
+
+async fn validates_password(store: UserStoreType) {
+    let user = new_user(&random_email()).await;
+    store.add_user(user.clone()).await.unwrap();
+
+    assert_eq!(store.validate_user(&user.email, PASSWORD).await, Ok(()));
+    assert_eq!(
+        store.validate_user(&user.email, "wrong-password").await,
+        Err(UserStoreError::InvalidCredentials)
+    );
+}
+
+async fn updates_email(store: UserStoreType) {
+    let user = new_user(&random_email()).await;
+    let new_email = random_email();
+    store.add_user(user.clone()).await.unwrap();
+    store.mark_email_undeliverable(&user.email).await.unwrap();
+
+    store.confirm_email_change(&change_id(), &user.email, new_email.clone()).await.unwrap();
+
+    assert_eq!(store.get_user(&user.email).await.err(), Some(UserStoreError::UserNotFound));
+    let updated = store.get_user(&new_email).await.unwrap();
+    assert_eq!(updated.requires_2fa, user.requires_2fa);
+    // The new address hasn't bounced yet
+    assert!(!updated.email_undeliverable);
+    assert_eq!(store.validate_user(&new_email, PASSWORD).await, Ok(()));
+}
+
+async fn updating_to_taken_email_fails(store: UserStoreType) {
+    let user = new_user(&random_email()).await;
+    let other = new_user(&random_email()).await;
+    store.add_user(user.clone()).await.unwrap();
+    store.add_user(other.clone()).await.unwrap();
+
+    assert_eq!(
+        store.confirm_email_change(&change_id(), &user.email, other.email.clone()).await,
+        Err(UserStoreError::UserAlreadyExists)
+    );
+    assert_eq!(store.get_user(&user.email).await, Ok(user));
+    assert_eq!(store.get_user(&other.email).await, Ok(other));
+}
+
+async fn updating_to_same_email_is_fine(store: UserStoreType) {
+    let user = new_user(&random_email()).await;
+    store.add_user(user.clone()).await.unwrap();
+
+    assert_eq!(store.confirm_email_change(&change_id(), &user.email, user.email.clone()).await, Ok(()));
+    assert_eq!(store.get_user(&user.email).await, Ok(user));
+}
+
+async fn reverts_email_change(store: UserStoreType) {
+    let user = new_user(&random_email()).await;
+    let new_email = random_email();
+    let id = change_id();
+    store.add_user(user.clone()).await.unwrap();
+    store.confirm_email_change(&id, &user.email, new_email.clone()).await.unwrap();
+
+    assert_eq!(store.revert_email_change(&id).await, Ok(EmailChangeRevert::Reverted));
+
+    assert_eq!(store.get_user(&new_email).await.err(), Some(UserStoreError::UserNotFound));
+    assert_eq!(store.validate_user(&user.email, PASSWORD).await, Ok(()));
+}
+
+async fn reverting_unconfirmed_change_changes_nothing(store: UserStoreType) {
+    let user = new_user(&random_email()).await;
+    store.add_user(user.clone()).await.unwrap();
+
+    assert_eq!(store.revert_email_change(&change_id()).await, Ok(EmailChangeRevert::NotConfirmed));
+    assert_eq!(store.get_user(&user.email).await, Ok(user));
+}
+
+async fn reverts_email_change_once(store: UserStoreType) {
+    let user = new_user(&random_email()).await;
+    let new_email = random_email();
+    let id = change_id();
+    store.add_user(user.clone()).await.unwrap();
+    store.confirm_email_change(&id, &user.email, new_email.clone()).await.unwrap();
+    store.revert_email_change(&id).await.unwrap();
+
+    // Another account takes the address the change moved to
+    let other = new_user(&new_email).await;
+    store.add_user(other.clone()).await.unwrap();
+
+    assert_eq!(store.revert_email_change(&id).await, Ok(EmailChangeRevert::Ended));
+    assert_eq!(store.get_user(&other.email).await, Ok(other));
+    assert!(store.get_user(&user.email).await.is_ok());
+}
+
+async fn change_followed_by_another_is_not_reverted(store: UserStoreType) {
+    let user = new_user(&random_email()).await;
+    let second_email = random_email();
+    let first_change = change_id();
+    store.add_user(user.clone()).await.unwrap();
+    store.confirm_email_change(&first_change, &user.email, second_email.clone()).await.unwrap();
+    store.confirm_email_change(&change_id(), &second_email, random_email()).await.unwrap();
+
+    // Someone else signs up with the address the account left behind
+    let other = new_user(&second_email).await;
+    store.add_user(other.clone()).await.unwrap();
+
+    assert_eq!(store.revert_email_change(&first_change).await, Ok(EmailChangeRevert::Ended));
+    assert_eq!(store.get_user(&other.email).await, Ok(other));
+    assert_eq!(store.get_user(&user.email).await.err(), Some(UserStoreError::UserNotFound));
+}
+
+async fn marks_email_undeliverable(store: UserStoreType) {
+    let user = new_user(&random_email()).await;
+    store.add_user(user.clone()).await.unwrap();
+
+    store.mark_email_undeliverable(&user.email).await.unwrap();
+
+    assert!(store.get_user(&user.email).await.unwrap().email_undeliverable);
+}